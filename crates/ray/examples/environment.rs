@@ -0,0 +1,118 @@
+use ray::Float;
+
+const SAMPLES_PER_PIXEL: usize = 16;
+
+fn main() {
+    let camera = ray::camera::Camera::new(
+        640,
+        360,
+        ray::distance::Distance::from_um(2.0),
+        ray::distance::Distance::from_mm(1.5),
+    );
+    // looking north from 1.5 m above the ground, north-east-down world
+    let camera_to_ned =
+        nalgebra::Rotation3::from_euler_angles(Float::to_radians(90.), 0., Float::to_radians(90.));
+    let camera_pose = nalgebra::Isometry3::from_parts(
+        nalgebra::Translation3::new(-8., 0., -1.5),
+        nalgebra::UnitQuaternion::from_rotation_matrix(&camera_to_ned),
+    );
+
+    let mut materials = ray::material::MaterialLibrary::new();
+    let ground = materials.add(ray::material::Material::diffuse(nalgebra::Vector3::repeat(
+        0.3,
+    )));
+    let red = materials.add(ray::material::Material::diffuse(
+        ray::color::srgb_to_linear_rgb([200, 40, 40]),
+    ));
+    let white = materials.add(ray::material::Material::diffuse(nalgebra::Vector3::repeat(
+        0.8,
+    )));
+
+    let objects = [
+        ray::object::Object::new(
+            ray::shapes::Shape::Plane(ray::shapes::plane::Plane::new(
+                nalgebra::Vector3::zeros(),
+                -nalgebra::Vector3::z_axis(),
+            )),
+            ground,
+        ),
+        ray::object::Object::new(
+            ray::shapes::Shape::Sphere(ray::shapes::sphere::Sphere::new(
+                nalgebra::Vector3::new(0., -0.8, -1.),
+                1.,
+            )),
+            red,
+        ),
+        ray::object::Object::new(
+            ray::shapes::Shape::Sphere(ray::shapes::sphere::Sphere::new(
+                nalgebra::Vector3::new(-1.5, 1.2, -0.6),
+                0.6,
+            )),
+            white,
+        ),
+    ];
+
+    // image based lighting only, the same sky is seen by rays missing the scene
+    let lights = [ray::light::Light::Environment(
+        ray::environment::Environment::new(ray::environment::Background::Gradient(
+            ray::environment::Gradient {
+                zenith: nalgebra::Vector3::new(0.2, 0.4, 1.),
+                horizon: nalgebra::Vector3::new(0.9, 0.9, 1.),
+                ground: nalgebra::Vector3::new(0.2, 0.15, 0.1),
+            },
+        )),
+    )];
+
+    let closest_hit = |ray: &ray::ray::Ray| {
+        objects
+            .iter()
+            .filter_map(|object| object.hit(ray).map(|hit| (hit, object.material())))
+            .min_by(|a, b| a.0.distance.total_cmp(&b.0.distance))
+    };
+    let occluded = |ray: &ray::ray::Ray| {
+        objects
+            .iter()
+            .any(|object| object.closest_ray_intersection(ray).is_some())
+    };
+
+    let mut rng = ray::random::Rng::new(0);
+    let radiance = ray::projection::pixel_rays(&camera)
+        .map(|ray| {
+            let Some(ray) = ray else {
+                return nalgebra::Vector3::zeros();
+            };
+            let ray = ray.transform(&camera_pose);
+            match closest_hit(&ray) {
+                Some((hit, material)) => {
+                    let wo = -*ray.unit_direction();
+                    (0..SAMPLES_PER_PIXEL)
+                        .map(|_| {
+                            ray::light::direct_lighting(
+                                &lights,
+                                &hit,
+                                &wo,
+                                &materials[material],
+                                &mut rng,
+                                occluded,
+                            )
+                        })
+                        .sum::<nalgebra::Vector3<Float>>()
+                        / SAMPLES_PER_PIXEL as Float
+                }
+                None => ray::light::environment_radiance(&lights, ray.unit_direction()),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let mut color_map = image::RgbImage::new(camera.width_px(), camera.height_px());
+    color_map
+        .pixels_mut()
+        .zip(&radiance)
+        .for_each(|(rgb, radiance)| {
+            *rgb = image::Rgb(ray::color::linear_rgb_to_srgb(radiance));
+        });
+
+    color_map
+        .save("environment_color.tiff")
+        .expect("able to save color_map");
+}
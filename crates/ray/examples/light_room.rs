@@ -1,11 +1,9 @@
-use std::f32::consts::PI;
-
-use ray::{camera, distance, shapes::triangle};
+use ray::{Float, camera, consts::PI, distance};
 
 const CAMERA_WIDTH: u32 = 1920;
 const CAMERA_HEIGHT: u32 = 1080;
-const CAMERA_PIXEL_PITCH: distance::Distance = distance::Distance::from_um(2.);
-const CAMERA_FOCAL_LENGTH: distance::Distance = distance::Distance::from_mm(30.);
+const CAMERA_PIXEL_PITCH: distance::Distance = distance::Distance::from_m(2e-6);
+const CAMERA_FOCAL_LENGTH: distance::Distance = distance::Distance::from_m(30e-3);
 
 const ROOM_HEIGHT: distance::Distance = distance::Distance::from_m(10.);
 const ROOM_WIDTH: distance::Distance = distance::Distance::from_m(10.);
@@ -20,7 +18,7 @@ fn main() {
     );
     let camera_orientation_ned = nalgebra::Rotation3::from_euler_angles(0., 0., 0.);
     let camera_to_body =
-        nalgebra::Rotation3::from_euler_angles(90f32.to_radians(), 0., 90f32.to_radians());
+        nalgebra::Rotation3::from_euler_angles(Float::to_radians(90.), 0., Float::to_radians(90.));
     let camera_to_ned = camera_orientation_ned * camera_to_body;
 
     let hfov = camera.hfov();
@@ -46,11 +44,19 @@ fn main() {
             let row = i / camera.width_px();
             let col = i % camera.width_px();
             nalgebra::UnitVector3::new_normalize(
-                camera_to_ned * camera.pixel_to_camera_vector(col as f32, row as f32),
+                camera_to_ned * camera.pixel_to_camera_vector(col as Float, row as Float),
             )
         })
         .collect::<Vec<_>>();
 
+    let mut materials = ray::material::MaterialLibrary::new();
+    let white = materials.add(ray::material::Material::diffuse(
+        ray::color::srgb_to_linear_rgb([255, 255, 255]),
+    ));
+    let red = materials.add(ray::material::Material::diffuse(
+        ray::color::srgb_to_linear_rgb([255, 0, 0]),
+    ));
+
     let walls = [
         (
             nalgebra::Vector3::new(0., 0., -ROOM_HEIGHT.m()),
@@ -77,64 +83,12 @@ fn main() {
     .map(|(origin, normal)| {
         let plane =
             ray::shapes::plane::Plane::new(*origin, nalgebra::UnitVector3::new_normalize(*normal));
-        ray::object::Object::new(ray::shapes::Shape::Plane(plane), [255, 255, 255])
+        ray::object::Object::new(ray::shapes::Shape::Plane(plane), white)
     })
     .collect::<Vec<_>>();
 
-    let mut unit_cube = ray::shapes::composite::CompositeObject::new(
-        [
-            // bottom
-            triangle::quad_to_triangles(
-                nalgebra::Vector3::new(0., 0., 0.),
-                nalgebra::Vector3::new(1., 0., 0.),
-                nalgebra::Vector3::new(1., 1., 0.),
-                nalgebra::Vector3::new(0., 1., 0.),
-            ),
-            // back
-            triangle::quad_to_triangles(
-                nalgebra::Vector3::new(1., 0., 0.),
-                nalgebra::Vector3::new(1., 0., 1.),
-                nalgebra::Vector3::new(1., 1., 1.),
-                nalgebra::Vector3::new(1., 1., 0.),
-            ),
-            // top
-            triangle::quad_to_triangles(
-                nalgebra::Vector3::new(0., 0., 1.),
-                nalgebra::Vector3::new(0., 1., 1.),
-                nalgebra::Vector3::new(1., 1., 1.),
-                nalgebra::Vector3::new(1., 0., 1.),
-            ),
-            // front
-            triangle::quad_to_triangles(
-                nalgebra::Vector3::new(0., 0., 0.),
-                nalgebra::Vector3::new(0., 0., 1.),
-                nalgebra::Vector3::new(0., 1., 1.),
-                nalgebra::Vector3::new(0., 1., 0.),
-            ),
-            // right
-            triangle::quad_to_triangles(
-                nalgebra::Vector3::new(1., 1., 0.),
-                nalgebra::Vector3::new(1., 1., 1.),
-                nalgebra::Vector3::new(0., 1., 1.),
-                nalgebra::Vector3::new(0., 1., 0.),
-            ),
-            // left
-            triangle::quad_to_triangles(
-                nalgebra::Vector3::new(1., 0., 0.),
-                nalgebra::Vector3::new(1., 0., 1.),
-                nalgebra::Vector3::new(0., 0., 1.),
-                nalgebra::Vector3::new(0., 0., 0.),
-            ),
-        ]
-        .into_iter()
-        .flatten()
-        .collect::<Vec<_>>(),
-        [255, 0, 0],
-        1.,
-        0.,
-    );
-
-    unit_cube.translate_in_place(&nalgebra::Vector3::new(-0.5, -0.5, -0.5));
+    let mut unit_cube =
+        ray::shapes::generate::cuboid(&nalgebra::Vector3::new(1., 1., 1.)).into_object(red);
     let rotation = nalgebra::UnitQuaternion::from_axis_angle(
         &nalgebra::UnitVector3::new_normalize(nalgebra::Vector3::new(-1., 0., -1.)),
         PI / 2.,
@@ -142,51 +96,54 @@ fn main() {
     unit_cube.rotate_in_place(&rotation);
     unit_cube.translate_in_place(&nalgebra::Vector3::new(0., 0., -ROOM_HEIGHT.m() / 2.));
 
-    let intersections = camera_rays
+    let mut objects = walls;
+    objects.push(ray::object::Object::new(
+        ray::shapes::Shape::Composite(unit_cube),
+        red,
+    ));
+
+    // a panel on the ceiling and a bulb in front of the cube
+    let lights = [
+        ray::light::Light::Area(ray::light::area::AreaLight::new(
+            ray::light::area::AreaShape::Rectangle {
+                center: nalgebra::Vector3::new(10., 0., -ROOM_HEIGHT.m() + 0.01),
+                u: nalgebra::Vector3::new(2., 0., 0.),
+                v: nalgebra::Vector3::new(0., 2., 0.),
+            },
+            nalgebra::Vector3::repeat(5.),
+        )),
+        ray::light::Light::Point(ray::light::PointLight::new(
+            nalgebra::Vector3::new(-3., 2., -ROOM_HEIGHT.m() / 2. - 2.),
+            nalgebra::Vector3::new(20., 18., 14.),
+        )),
+    ];
+
+    let closest_hit = |ray: &ray::ray::Ray| {
+        objects
+            .iter()
+            .filter_map(|object| object.hit(ray).map(|hit| (hit, object.material())))
+            .min_by(|a, b| a.0.distance.total_cmp(&b.0.distance))
+    };
+    let occluded = |ray: &ray::ray::Ray| {
+        objects
+            .iter()
+            .any(|object| object.closest_ray_intersection(ray).is_some())
+    };
+
+    let hits = camera_rays
         .iter()
-        .map(|ray| {
-            let camera_ray = ray::ray::Ray::new(camera_position_ned, *ray);
-            walls
-                .iter()
-                .filter_map(|shape| {
-                    shape
-                        .closest_ray_intersection(&camera_ray, 1e-6)
-                        .map(|dist| (dist, shape.color()))
-                })
-                .min_by(|a, b| a.0.total_cmp(&b.0))
-        })
+        .map(|ray| closest_hit(&ray::ray::Ray::new(camera_position_ned, *ray)))
         .collect::<Vec<_>>();
-
-    let box_intersections = camera_rays.iter().map(|ray| {
-        let camera_ray = ray::ray::Ray::new(camera_position_ned, *ray);
-        unit_cube
-            .ray_intersection(&camera_ray, 1e-6)
-            .filter(|a| *a > 0.)
-            .min_by(|a, b| a.total_cmp(b))
-            .map(|dist| (dist, *unit_cube.color()))
-    });
-
-    let intersections = box_intersections
-        .zip(intersections.iter())
-        .map(|(mbox, wall)| match (mbox, wall) {
-            (None, None) => None,
-            (Some(a), None) => Some(a),
-            (None, Some(a)) => Some(a).copied(),
-            (Some(a), Some(b)) => {
-                if a.0 < b.0 {
-                    Some(a)
-                } else {
-                    Some(b).copied()
-                }
-            }
-        })
+    let intersections = hits
+        .iter()
+        .map(|hit| hit.map(|(hit, material)| (hit.distance, material)))
         .collect::<Vec<_>>();
 
     // ---- plot results ----
 
     let (max, min) =
         intersections.iter().fold(
-            (f32::MIN, f32::MAX),
+            (Float::MIN, Float::MAX),
             |(max, min), distance| match distance {
                 Some((distance, _)) => (distance.max(max), distance.min(min)),
                 None => (max, min),
@@ -213,9 +170,20 @@ fn main() {
         .save("room_depth.tiff")
         .expect("able to save distance_map");
 
+    let mut rng = ray::random::Rng::new(0);
     let mut color_map = image::RgbImage::new(camera.width_px(), camera.height_px());
     color_map.pixels_mut().enumerate().for_each(|(i, rgb)| {
-        if let Some((_, color)) = intersections[i] {
+        if let Some((hit, material)) = hits[i] {
+            let wo = -camera_rays[i];
+            let radiance = ray::light::direct_lighting(
+                &lights,
+                &hit,
+                &wo,
+                &materials[material],
+                &mut rng,
+                occluded,
+            );
+            let color = ray::color::linear_rgb_to_srgb(&radiance);
             rgb[0] = color[0];
             rgb[1] = color[1];
             rgb[2] = color[2];
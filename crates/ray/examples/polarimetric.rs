@@ -0,0 +1,95 @@
+use ray::{Float, consts::PI};
+
+fn main() {
+    let camera = ray::camera::Camera::new(
+        320,
+        240,
+        ray::distance::Distance::from_um(3.45),
+        ray::distance::Distance::from_mm(2.),
+    );
+    // looking north and slightly down from 1.5 m above the ground, north-east-down world
+    let camera_to_ned = nalgebra::Rotation3::from_euler_angles(
+        Float::to_radians(90. - 10.),
+        0.,
+        Float::to_radians(90.),
+    );
+    let camera_pose = nalgebra::Isometry3::from_parts(
+        nalgebra::Translation3::new(-6., 0., -1.5),
+        nalgebra::UnitQuaternion::from_rotation_matrix(&camera_to_ned),
+    );
+
+    let mut materials = ray::material::MaterialLibrary::new();
+    // dark dielectrics polarize strongly by reflection, glass by refraction as well
+    let asphalt = materials
+        .add(ray::material::Material::diffuse(nalgebra::Vector3::repeat(0.05)).with_roughness(0.2));
+    let glass = materials.add(
+        ray::material::Material::diffuse(nalgebra::Vector3::repeat(1.))
+            .with_roughness(0.)
+            .with_transmission(1.),
+    );
+
+    let objects = [
+        ray::object::Object::new(
+            ray::shapes::Shape::Plane(ray::shapes::plane::Plane::new(
+                nalgebra::Vector3::zeros(),
+                -nalgebra::Vector3::z_axis(),
+            )),
+            asphalt,
+        ),
+        ray::object::Object::new(
+            ray::shapes::Shape::Sphere(ray::shapes::sphere::Sphere::new(
+                nalgebra::Vector3::new(0., 0., -1.),
+                1.,
+            )),
+            glass,
+        ),
+    ];
+
+    let lights = [ray::light::Light::Environment(
+        ray::environment::Environment::new(ray::environment::Background::Gradient(
+            ray::environment::Gradient {
+                zenith: nalgebra::Vector3::new(0.2, 0.4, 1.),
+                horizon: nalgebra::Vector3::new(0.9, 0.9, 1.),
+                ground: nalgebra::Vector3::new(0.2, 0.15, 0.1),
+            },
+        )),
+    )];
+
+    let closest_hit = |ray: &ray::ray::Ray| {
+        objects
+            .iter()
+            .filter_map(|object| object.hit(ray).map(|hit| (hit, object.material())))
+            .min_by(|a, b| a.0.distance.total_cmp(&b.0.distance))
+    };
+
+    let mut rng = ray::random::Rng::new(0);
+    let image = ray::polarization::render(
+        &camera,
+        &camera_pose,
+        &materials,
+        &lights,
+        4,
+        &mut rng,
+        closest_hit,
+    );
+
+    let dolp = image.degree_of_linear_polarization();
+    let aolp = image.angle_of_linear_polarization();
+    println!(
+        "max dolp: {}",
+        dolp.iter().fold(0., |max: Float, d| max.max(*d))
+    );
+
+    // dolp black to white, aolp from -90° black to 90° white
+    let mut dolp_map = image::GrayImage::new(image.width(), image.height());
+    let mut aolp_map = image::GrayImage::new(image.width(), image.height());
+    for (i, (dolp_pixel, aolp_pixel)) in
+        dolp_map.pixels_mut().zip(aolp_map.pixels_mut()).enumerate()
+    {
+        dolp_pixel[0] = (dolp[i] * 255.).round() as u8;
+        aolp_pixel[0] = ((aolp[i] / PI + 0.5) * 255.).round() as u8;
+    }
+
+    dolp_map.save("dolp.tiff").expect("able to save dolp_map");
+    aolp_map.save("aolp.tiff").expect("able to save aolp_map");
+}
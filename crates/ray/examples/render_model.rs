@@ -1,14 +1,16 @@
-use std::{f32::consts::PI, fs, path};
+use std::{fs, path};
 
-use ray::{distance, shapes::Traceable as _};
+use ray::{Float, consts::PI, distance, shapes::Traceable as _};
 use rayon::prelude::*;
 
 const CAMERA_WIDTH: u32 = 1920;
 const CAMERA_HEIGHT: u32 = 1080;
-const CAMERA_PIXEL_PITCH: distance::Distance = distance::Distance::from_um(2.);
-const CAMERA_FOCAL_LENGTH: distance::Distance = distance::Distance::from_mm(30.);
+const CAMERA_PIXEL_PITCH: distance::Distance = distance::Distance::from_m(2e-6);
+const CAMERA_FOCAL_LENGTH: distance::Distance = distance::Distance::from_m(30e-3);
 
-const CAMERA_LOCATION: nalgebra::Vector3<f32> = nalgebra::Vector3::new(-100., 0., 0.);
+const PACKET_SIZE: usize = 8;
+
+const CAMERA_LOCATION: nalgebra::Vector3<Float> = nalgebra::Vector3::new(-100., 0., 0.);
 
 #[derive(argh::FromArgs)]
 /// opens and renders a .glb
@@ -46,7 +48,7 @@ fn main() {
     );
     let body_to_ned = nalgebra::Rotation3::from_euler_angles(0., 0., 0.);
     let camera_to_body =
-        nalgebra::Rotation3::from_euler_angles(90f32.to_radians(), 0., 90f32.to_radians());
+        nalgebra::Rotation3::from_euler_angles(Float::to_radians(90.), 0., Float::to_radians(90.));
     let camera_to_ned = body_to_ned * camera_to_body;
 
     let camera_rays = (0..camera.width_px() * camera.height_px())
@@ -54,7 +56,7 @@ fn main() {
             let row = i / camera.width_px();
             let col = i % camera.width_px();
             nalgebra::UnitVector3::new_normalize(
-                camera_to_ned * camera.pixel_to_camera_vector(col as f32, row as f32),
+                camera_to_ned * camera.pixel_to_camera_vector(col as Float, row as Float),
             )
         })
         .collect::<Vec<_>>();
@@ -83,9 +85,9 @@ fn main() {
                     let b = glb_positions[triangle[1] as usize];
                     let c = glb_positions[triangle[2] as usize];
                     triangles.push(ray::shapes::triangle::Triangle::new(
-                        nalgebra::Vector3::from(a),
-                        nalgebra::Vector3::from(b),
-                        nalgebra::Vector3::from(c),
+                        nalgebra::Vector3::from(a).cast::<Float>(),
+                        nalgebra::Vector3::from(b).cast::<Float>(),
+                        nalgebra::Vector3::from(c).cast::<Float>(),
                     ));
                 } else {
                     eprintln!("got incomplete triangle");
@@ -95,6 +97,9 @@ fn main() {
         }
     }
 
+    let mut materials = ray::material::MaterialLibrary::new();
+    let material = materials.add(ray::material::Material::default());
+
     // creating our giff encoders
     let mut depth_giff = fs::File::create("depth.gif").expect("able to create depth.gif");
     let mut intersection_giff =
@@ -128,8 +133,8 @@ fn main() {
     let frame_count = (loop_time_s / (frame_delay_ms / 1000.)) as usize;
     println!("{}", frame_count);
 
-    let angle_deltas = loop_angle / frame_count as f32;
-    let angles = (0..frame_count).map(|a| a as f32 * angle_deltas);
+    let angle_deltas = loop_angle / frame_count as Float;
+    let angles = (0..frame_count).map(|a| a as Float * angle_deltas);
 
     let axis = nalgebra::UnitVector3::new_normalize(nalgebra::Vector3::new(0., 0., 1.));
 
@@ -139,20 +144,32 @@ fn main() {
         let start = std::time::Instant::now();
         let rotation = nalgebra::UnitQuaternion::from_axis_angle(&axis, angle);
 
+        let objects = meshes
+            .iter()
+            .map(|mesh| {
+                let mut object =
+                    ray::shapes::composite::CompositeObject::new(mesh.clone(), material);
+                object.rotate_in_place(&rotation);
+                object
+            })
+            .collect::<Vec<_>>();
+
         let intersections = camera_rays
-            .par_iter()
-            .map(|ray| {
-                let camera_ray = ray::ray::Ray::new(CAMERA_LOCATION, *ray);
-                meshes
+            .par_chunks(PACKET_SIZE)
+            .flat_map_iter(|rays| {
+                let rays = rays
                     .iter()
-                    .filter_map(|mesh| {
-                        mesh.iter()
-                            .filter_map(|triangle| {
-                                triangle.rotate(&rotation).trace(&camera_ray, 0.0001)
-                            })
-                            .min_by(|a, b| a.total_cmp(b))
+                    .map(|ray| ray::ray::Ray::new(CAMERA_LOCATION, *ray))
+                    .collect::<Vec<_>>();
+                let packet = ray::packet::RayPacket::<PACKET_SIZE>::from_slice(&rays);
+                let hits = objects.iter().fold([None; PACKET_SIZE], |closest, object| {
+                    let hits = object.trace_packet(&packet);
+                    std::array::from_fn(|lane| match (closest[lane], hits[lane]) {
+                        (Some(a), Some(b)) => Some(Float::min(a, b)),
+                        (a, b) => a.or(b),
                     })
-                    .min_by(|a, b| a.total_cmp(b))
+                });
+                hits.into_iter().take(rays.len())
             })
             .collect::<Vec<_>>();
 
@@ -162,13 +179,15 @@ fn main() {
         );
 
         let (max, min) =
-            intersections.iter().fold(
-                (f32::MIN, f32::MAX),
-                |(max, min), distance| match distance {
-                    Some(distance) => (distance.max(max), distance.min(min)),
-                    None => (max, min),
-                },
-            );
+            intersections
+                .iter()
+                .fold(
+                    (Float::MIN, Float::MAX),
+                    |(max, min), distance| match distance {
+                        Some(distance) => (distance.max(max), distance.min(min)),
+                        None => (max, min),
+                    },
+                );
         println!("{} -> {}", min, max);
         println!(
             "max,min: {}",
@@ -0,0 +1,79 @@
+use ray::Float;
+
+fn main() {
+    let camera = ray::camera::Camera::new(
+        160,
+        120,
+        ray::distance::Distance::from_um(5.0),
+        ray::distance::Distance::from_m(0.004),
+    );
+    let rig = ray::rig::Rig::stereo(camera, ray::distance::Distance::from_m(0.12));
+
+    let mut materials = ray::material::MaterialLibrary::new();
+    let grey = materials.add(ray::material::Material::default());
+    let shapes = [
+        ray::object::Object::new(
+            ray::shapes::Shape::Sphere(ray::shapes::sphere::Sphere::new(
+                nalgebra::Vector3::new(-0.3, 0., 2.),
+                0.3,
+            )),
+            grey,
+        ),
+        ray::object::Object::new(
+            ray::shapes::Shape::Sphere(ray::shapes::sphere::Sphere::new(
+                nalgebra::Vector3::new(0.4, 0.1, 4.),
+                0.6,
+            )),
+            grey,
+        ),
+        ray::object::Object::new(
+            ray::shapes::Shape::Plane(ray::shapes::plane::Plane::new(
+                nalgebra::Vector3::new(0., 0., 8.),
+                -nalgebra::Vector3::z_axis(),
+            )),
+            grey,
+        ),
+    ];
+    let trace = |ray: &ray::ray::Ray| {
+        shapes
+            .iter()
+            .filter_map(|shape| shape.closest_ray_intersection(ray))
+            .min_by(|a, b| a.total_cmp(b))
+    };
+
+    // hits of the left camera, shared by all the ground truth
+    let hits = rig.hits(0, trace);
+    let depth = rig.depth(0, &hits);
+    let correspondences = rig.correspondences(1, &hits, trace);
+    let disparity = rig.disparity(0, &correspondences);
+
+    let max = disparity
+        .iter()
+        .flatten()
+        .fold(0., |max: Float, d| max.max(*d));
+    println!(
+        "max disparity {max} px, closest {:?} m",
+        depth
+            .iter()
+            .flatten()
+            .reduce(|a, b| if a < b { a } else { b })
+    );
+
+    // occluded pixels (seen by the left camera only) are black
+    let mut disparity_map =
+        image::GrayImage::new(rig.camera(0).width_px(), rig.camera(0).height_px());
+    disparity_map
+        .pixels_mut()
+        .enumerate()
+        .for_each(|(i, pixel)| {
+            if let (Some(d), Some(c)) = (disparity[i], correspondences[i])
+                && c.visible
+            {
+                pixel[0] = (d / max * 255.) as u8;
+            }
+        });
+
+    disparity_map
+        .save("disparity.tiff")
+        .expect("able to save disparity_map");
+}
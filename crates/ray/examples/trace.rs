@@ -1,3 +1,5 @@
+use ray::Float;
+
 fn main() {
     let camera = ray::camera::Camera::new(
         101,
@@ -5,52 +7,67 @@ fn main() {
         ray::distance::Distance::from_um(2.0),
         ray::distance::Distance::from_m(0.03),
     );
-    let camera_location = nalgebra::Vector3::new(0., 0., 0.);
-
-    let camera_rays = (0..camera.width_px() * camera.height_px())
-        .map(|i| {
-            let row = i / camera.width_px();
-            let col = i % camera.width_px();
-            nalgebra::UnitVector3::new_normalize(
-                camera.pixel_to_camera_vector(col as f32, row as f32),
-            )
-        })
-        .collect::<Vec<_>>();
+
+    // camera at the origin, rays carry differentials for texture filtering
+    let camera_rays = ray::projection::pixel_rays(&camera).collect::<Vec<_>>();
+
+    let mut materials = ray::material::MaterialLibrary::new();
+    let red = materials.add(ray::material::Material::diffuse(
+        ray::color::srgb_to_linear_rgb([255, 0, 0]),
+    ));
+    let checkerboard = materials.add_texture(ray::texture::Texture::Checkerboard(
+        ray::texture::Checkerboard::new(
+            ray::color::srgb_to_linear_rgb([0, 255, 0]),
+            ray::color::srgb_to_linear_rgb([255, 255, 255]),
+            1.,
+        ),
+    ));
+    let green = materials.add(ray::material::Material::default().with_albedo_texture(checkerboard));
+    let blue = materials.add(ray::material::Material::diffuse(
+        ray::color::srgb_to_linear_rgb([0, 0, 255]),
+    ));
 
     let sphere = ray::object::Object::new(
         ray::shapes::Shape::Sphere(ray::shapes::sphere::Sphere::new(
             nalgebra::Vector3::new(0., 0., 1000.),
             1.,
         )),
-        [255, 0, 0],
+        red,
     );
     let plane = ray::object::Object::new(
-        ray::shapes::Shape::Plane(ray::shapes::plane::Plane::new(
-            nalgebra::Vector3::new(0., 0., 1000.),
-            nalgebra::UnitVector3::new_normalize(nalgebra::Vector3::new(2., 0., -1.)),
-        )),
-        [0, 255, 0],
+        ray::shapes::Shape::Plane(
+            ray::shapes::plane::Plane::new(
+                nalgebra::Vector3::new(0., 0., 1000.),
+                nalgebra::UnitVector3::new_normalize(nalgebra::Vector3::new(2., 0., -1.)),
+            )
+            .with_uv(&nalgebra::Vector3::y(), 0.5),
+        ),
+        green,
     );
     let sphere2 = ray::object::Object::new(
         ray::shapes::Shape::Sphere(ray::shapes::sphere::Sphere::new(
             nalgebra::Vector3::new(1., 1.5, 900.),
             1.,
         )),
-        [0, 0, 255],
+        blue,
     );
 
     let shapes = [sphere, plane, sphere2];
 
     let intersections = camera_rays
         .iter()
-        .map(|ray| {
-            let camera_ray = ray::ray::Ray::new(camera_location, *ray);
+        .map(|camera_ray| {
+            let camera_ray = camera_ray.as_ref()?;
             shapes
                 .iter()
                 .filter_map(|shape| {
-                    shape
-                        .closest_ray_intersection(&camera_ray, 0.0001)
-                        .map(|dist| (dist, shape.color()))
+                    shape.hit(camera_ray).map(|hit| {
+                        let material = materials.evaluate_textures(shape.material(), &hit);
+                        (
+                            hit.distance,
+                            ray::color::linear_rgb_to_srgb(material.albedo()),
+                        )
+                    })
                 })
                 .min_by(|a, b| a.0.total_cmp(&b.0))
         })
@@ -58,7 +75,7 @@ fn main() {
 
     let (max, min) =
         intersections.iter().fold(
-            (f32::MIN, f32::MAX),
+            (Float::MIN, Float::MAX),
             |(max, min), distance| match distance {
                 Some((distance, _)) => (distance.max(max), distance.min(min)),
                 None => (max, min),
@@ -99,4 +116,56 @@ fn main() {
     color_map
         .save("color.tiff")
         .expect("able to save color_map");
+
+    let ranges = intersections
+        .iter()
+        .map(|hit| hit.map(|(distance, _)| distance))
+        .collect::<Vec<_>>();
+    let colors = intersections
+        .iter()
+        .map(|hit| hit.map(|(_, color)| color).unwrap_or_default())
+        .collect::<Vec<_>>();
+    let cloud =
+        ray::pointcloud::PointCloud::from_range_image(&camera, &ranges).with_sample_colors(&colors);
+
+    let mut ply = std::io::BufWriter::new(
+        std::fs::File::create("points.ply").expect("able to create points.ply"),
+    );
+    ray::pointcloud::ply::write(
+        &cloud,
+        &mut ply,
+        ray::pointcloud::ply::Encoding::BinaryLittleEndian,
+    )
+    .expect("able to write points.ply");
+
+    // the earth from orbit, where f32 runs out of precision
+    let orbit_camera = ray::camera::Camera::<f64>::new(
+        101,
+        101,
+        ray::distance::Distance::from_um(2.0),
+        ray::distance::Distance::from_m(0.03),
+    );
+    let earth = ray::shapes::sphere::Sphere::new(
+        nalgebra::Vector3::new(0., 0., 6_371_000. + 400_000.),
+        6_371_000.,
+    );
+    let altitude =
+        ray::shapes::Traceable::trace(&earth, &orbit_camera.pixel_to_camera_ray(50.5, 50.5));
+    println!("ground at {:?} m", altitude);
+
+    // every pixel sees the ground, and projects back onto itself
+    let worst = ray::projection::pixel_rays(&orbit_camera)
+        .enumerate()
+        .filter_map(|(i, ray)| {
+            let ray = ray?;
+            let ground = ray.at(ray::shapes::Traceable::trace(&earth, &ray)?);
+            let pixel = ray::projection::Projection::world_to_pixel(
+                &orbit_camera,
+                &nalgebra::Isometry3::identity(),
+                &ground,
+            )?;
+            Some((pixel - nalgebra::Vector2::new((i % 101) as f64, (i / 101) as f64)).norm())
+        })
+        .fold(0., f64::max);
+    println!("reprojection error from orbit: {worst} px");
 }
@@ -1,6 +1,4 @@
-use std::f32::consts::PI;
-
-use ray::shapes::triangle;
+use ray::{Float, consts::PI, shapes::triangle};
 
 fn main() {
     let camera_location = nalgebra::Vector3::new(-500., 0., 0.);
@@ -13,7 +11,7 @@ fn main() {
     );
 
     // make a square
-    // no care was taken w.r.t winding direction
+    // no care was taken w.r.t winding direction, mesh::orient sorts that out
     //
     // lets say we are making these in world coordinates
     // - x: north
@@ -52,15 +50,16 @@ fn main() {
 
     let rotation_axis = nalgebra::UnitVector3::new_normalize(nalgebra::Vector3::new(-1., 0., -1.));
     let rotation = nalgebra::UnitQuaternion::from_axis_angle(&rotation_axis, PI / 2.);
-    let triangles = input_triangles
+    let mut triangles = input_triangles
         .iter()
         .flatten()
         .map(|t| t.rotate(&rotation))
         .collect::<Vec<_>>();
+    ray::shapes::mesh::orient(&mut triangles);
 
     // transforms from camera vec to ned
     let camera_to_body =
-        nalgebra::Rotation3::from_euler_angles(90f32.to_radians(), 0., 90f32.to_radians());
+        nalgebra::Rotation3::from_euler_angles(Float::to_radians(90.), 0., Float::to_radians(90.));
     let camera_to_ned = camera_orientation_ned * camera_to_body;
 
     let camera_rays = (0..camera.width_px() * camera.height_px())
@@ -68,7 +67,7 @@ fn main() {
             let row = i / camera.width_px();
             let col = i % camera.width_px();
             nalgebra::UnitVector3::new_normalize(
-                camera_to_ned * camera.pixel_to_camera_vector(col as f32, row as f32),
+                camera_to_ned * camera.pixel_to_camera_vector(col as Float, row as Float),
             )
         })
         .collect::<Vec<_>>();
@@ -79,14 +78,14 @@ fn main() {
             let camera_ray = ray::ray::Ray::new(camera_location, *ray);
             triangles
                 .iter()
-                .filter_map(|shape| shape.ray_intersection(&camera_ray, 0.0001))
+                .filter_map(|shape| shape.ray_intersection(&camera_ray))
                 .min_by(|a, b| a.total_cmp(b))
         })
         .collect::<Vec<_>>();
 
     let (max, min) =
         intersections.iter().fold(
-            (f32::MIN, f32::MAX),
+            (Float::MIN, Float::MAX),
             |(max, min), distance| match distance {
                 Some(distance) => (distance.max(max), distance.min(min)),
                 None => (max, min),
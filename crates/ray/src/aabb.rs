@@ -0,0 +1,132 @@
+use crate::Float;
+use crate::Real;
+use crate::packet;
+use crate::ray;
+
+/// axis aligned bounding box
+#[derive(Copy, Clone, Debug)]
+pub struct Aabb<T = Float> {
+    min: nalgebra::Vector3<T>,
+    max: nalgebra::Vector3<T>,
+}
+
+impl<T: Real> Aabb<T> {
+    /// contains nothing, growing it by anything results in that thing
+    pub const EMPTY: Self = Self {
+        min: nalgebra::Vector3::new(T::INFINITY, T::INFINITY, T::INFINITY),
+        max: nalgebra::Vector3::new(T::NEG_INFINITY, T::NEG_INFINITY, T::NEG_INFINITY),
+    };
+
+    pub fn new(min: nalgebra::Vector3<T>, max: nalgebra::Vector3<T>) -> Self {
+        Self { min, max }
+    }
+
+    pub fn from_points<'a>(points: impl IntoIterator<Item = &'a nalgebra::Vector3<T>>) -> Self {
+        points
+            .into_iter()
+            .fold(Self::EMPTY, |aabb, point| aabb.grow(point))
+    }
+
+    pub fn min(&self) -> &nalgebra::Vector3<T> {
+        &self.min
+    }
+
+    pub fn max(&self) -> &nalgebra::Vector3<T> {
+        &self.max
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    pub fn grow(&self, point: &nalgebra::Vector3<T>) -> Self {
+        Self::new(self.min.inf(point), self.max.sup(point))
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        Self::new(self.min.inf(&other.min), self.max.sup(&other.max))
+    }
+
+    pub fn centroid(&self) -> nalgebra::Vector3<T> {
+        (self.min + self.max) * nalgebra::convert::<_, T>(0.5)
+    }
+
+    pub fn extent(&self) -> nalgebra::Vector3<T> {
+        self.max - self.min
+    }
+
+    /// squared distance from the point to the box, 0 inside it
+    pub fn distance_squared(&self, point: &nalgebra::Vector3<T>) -> T {
+        (self.min - point)
+            .sup(&(point - self.max))
+            .sup(&nalgebra::Vector3::zeros())
+            .norm_squared()
+    }
+
+    pub fn surface_area(&self) -> T {
+        let extent = self.extent();
+        (extent.x * extent.y + extent.y * extent.z + extent.z * extent.x) * nalgebra::convert(2.)
+    }
+
+    /// Slab test, the (entry, exit) distances of the ray through the box.
+    ///
+    /// Either may be negative when the box is behind or around the origin.
+    pub fn ray_intersection(&self, ray: &ray::Ray<T>) -> Option<(T, T)> {
+        let mut near = T::NEG_INFINITY;
+        let mut far = T::INFINITY;
+
+        for axis in 0..3 {
+            let inv_direction = T::one() / ray.direction()[axis];
+            let t0 = (self.min[axis] - ray.origin()[axis]) * inv_direction;
+            let t1 = (self.max[axis] - ray.origin()[axis]) * inv_direction;
+            // 0 * inf, running within the plane of a face, which is inside the slab
+            if t0.is_nan() || t1.is_nan() {
+                continue;
+            }
+            near = near.max(t0.min(t1));
+            far = far.min(t0.max(t1));
+        }
+
+        if near <= far { Some((near, far)) } else { None }
+    }
+
+    /// Slab test for every ray in a packet, the entry distance of each lane
+    /// or infinity if the lane misses the box within its interval.
+    pub fn ray_packet_intersection<const N: usize>(
+        &self,
+        packet: &packet::RayPacket<N, T>,
+    ) -> packet::Lanes<T, N> {
+        let mut near = [T::NEG_INFINITY; N];
+        let mut far = [T::INFINITY; N];
+
+        for axis in 0..3 {
+            let origin = &packet.origin()[axis];
+            let inv_direction = &packet.inv_direction()[axis];
+            for lane in 0..N {
+                let t0 = (self.min[axis] - origin[lane]) * inv_direction[lane];
+                let t1 = (self.max[axis] - origin[lane]) * inv_direction[lane];
+                // selected rather than skipped, keeping the loop branch free
+                let in_plane = t0.is_nan() | t1.is_nan();
+                near[lane] = match in_plane {
+                    true => near[lane],
+                    false => near[lane].max(t0.min(t1)),
+                };
+                far[lane] = match in_plane {
+                    true => far[lane],
+                    false => far[lane].min(t0.max(t1)),
+                };
+            }
+        }
+
+        std::array::from_fn(|lane| {
+            if near[lane] <= far[lane]
+                && far[lane] >= packet.t_min()[lane]
+                && near[lane] <= packet.t_max()[lane]
+            {
+                near[lane]
+            } else {
+                T::INFINITY
+            }
+        })
+    }
+}
@@ -1,55 +1,65 @@
+use crate::Float;
+use crate::Real;
+
 /// angle abstracted from units,
 /// internally represented as a radian
-pub struct Angle(f32);
+#[derive(Copy, Clone, Debug)]
+pub struct Angle<T = Float>(T);
 
-impl Angle {
+impl Angle<f32> {
     pub const ZERO: Self = Self(0.);
+}
+
+impl Angle<f64> {
+    pub const ZERO: Self = Self(0.);
+}
 
+impl<T: Real> Angle<T> {
     #[inline(always)]
-    pub fn from_rads(rads: f32) -> Self {
+    pub const fn from_rads(rads: T) -> Self {
         Self(rads)
     }
 
     #[inline(always)]
-    pub fn from_degs(degs: f32) -> Self {
-        Self(degs.to_radians())
+    pub fn from_degs(degs: T) -> Self {
+        Self(degs * (T::pi() / nalgebra::convert(180.)))
     }
 
     #[inline(always)]
-    pub fn degs(&self) -> f32 {
-        self.0.to_degrees()
+    pub fn degs(&self) -> T {
+        self.0 * nalgebra::convert(180. / std::f64::consts::PI)
     }
 
     #[inline(always)]
-    pub fn rads(&self) -> f32 {
+    pub fn rads(&self) -> T {
         self.0
     }
 
     #[inline(always)]
-    pub fn cos(&self) -> f32 {
+    pub fn cos(&self) -> T {
         self.0.cos()
     }
 
     #[inline(always)]
-    pub fn sin(&self) -> f32 {
+    pub fn sin(&self) -> T {
         self.0.sin()
     }
 
     #[inline(always)]
-    pub fn tan(&self) -> f32 {
+    pub fn tan(&self) -> T {
         self.0.tan()
     }
 }
 
-impl std::ops::Mul<f32> for Angle {
-    type Output = Angle;
+impl<T: Real> std::ops::Mul<T> for Angle<T> {
+    type Output = Angle<T>;
 
-    fn mul(self, rhs: f32) -> Self::Output {
+    fn mul(self, rhs: T) -> Self::Output {
         Angle(self.0 * rhs)
     }
 }
 
-impl std::fmt::Display for Angle {
+impl<T: Real> std::fmt::Display for Angle<T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{:.03}°", self.degs())
     }
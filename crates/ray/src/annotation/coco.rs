@@ -0,0 +1,168 @@
+//! [COCO](https://cocodataset.org/#format-data) object detection export
+
+use std::io;
+
+use crate::Float;
+
+#[derive(Copy, Clone, Debug)]
+pub enum Segmentation {
+    Polygons,
+    /// uncompressed run lengths, exact including holes
+    Rle,
+}
+
+struct Image {
+    file_name: String,
+    width: u32,
+    height: u32,
+}
+
+/// Images, categories and annotations collected into one COCO file
+pub struct Dataset {
+    images: Vec<Image>,
+    categories: Vec<String>,
+    // image id, category id, annotation
+    annotations: Vec<(usize, usize, super::Annotation)>,
+}
+
+impl Dataset {
+    pub fn new() -> Self {
+        Self {
+            images: Vec::new(),
+            categories: Vec::new(),
+            annotations: Vec::new(),
+        }
+    }
+
+    /// id of the new category
+    pub fn add_category(&mut self, name: &str) -> usize {
+        self.categories.push(name.to_string());
+        self.categories.len()
+    }
+
+    /// id of the new image
+    pub fn add_image(&mut self, file_name: &str, width: u32, height: u32) -> usize {
+        self.images.push(Image {
+            file_name: file_name.to_string(),
+            width,
+            height,
+        });
+        self.images.len()
+    }
+
+    pub fn add_annotation(&mut self, image: usize, category: usize, annotation: super::Annotation) {
+        debug_assert!(
+            (1..=self.images.len()).contains(&image),
+            "image is not in the dataset"
+        );
+        debug_assert!(
+            (1..=self.categories.len()).contains(&category),
+            "category is not in the dataset"
+        );
+        self.annotations.push((image, category, annotation));
+    }
+
+    pub fn write(&self, writer: &mut impl io::Write, segmentation: Segmentation) -> io::Result<()> {
+        writeln!(writer, "{{")?;
+        writeln!(writer, "  \"images\": [")?;
+        for (i, image) in self.images.iter().enumerate() {
+            writeln!(
+                writer,
+                "    {{\"id\": {}, \"file_name\": {}, \"width\": {}, \"height\": {}}}{}",
+                i + 1,
+                json_string(&image.file_name),
+                image.width,
+                image.height,
+                separator(i, self.images.len())
+            )?;
+        }
+        writeln!(writer, "  ],")?;
+
+        writeln!(writer, "  \"categories\": [")?;
+        for (i, name) in self.categories.iter().enumerate() {
+            writeln!(
+                writer,
+                "    {{\"id\": {}, \"name\": {}}}{}",
+                i + 1,
+                json_string(name),
+                separator(i, self.categories.len())
+            )?;
+        }
+        writeln!(writer, "  ],")?;
+
+        writeln!(writer, "  \"annotations\": [")?;
+        for (i, (image, category, annotation)) in self.annotations.iter().enumerate() {
+            let bbox = &annotation.bbox;
+            let segmentation = match segmentation {
+                Segmentation::Polygons => format!(
+                    "[{}]",
+                    annotation
+                        .polygons
+                        .iter()
+                        .map(|polygon| format!(
+                            "[{}]",
+                            numbers(polygon.iter().flat_map(|p| [p.x, p.y]))
+                        ))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+                Segmentation::Rle => {
+                    let size = &self.images[image - 1];
+                    format!(
+                        "{{\"counts\": [{}], \"size\": [{}, {}]}}",
+                        annotation
+                            .rle
+                            .iter()
+                            .map(|c| c.to_string())
+                            .collect::<Vec<_>>()
+                            .join(", "),
+                        size.height,
+                        size.width
+                    )
+                }
+            };
+            writeln!(
+                writer,
+                "    {{\"id\": {}, \"image_id\": {image}, \"category_id\": {category}, \"bbox\": [{}], \"area\": {}, \"iscrowd\": 0, \"segmentation\": {segmentation}}}{}",
+                i + 1,
+                numbers([bbox.min.x, bbox.min.y, bbox.width(), bbox.height()]),
+                annotation.area,
+                separator(i, self.annotations.len())
+            )?;
+        }
+        writeln!(writer, "  ]")?;
+        writeln!(writer, "}}")
+    }
+}
+
+impl Default for Dataset {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn separator(i: usize, len: usize) -> &'static str {
+    if i + 1 < len { "," } else { "" }
+}
+
+fn numbers(values: impl IntoIterator<Item = Float>) -> String {
+    values
+        .into_iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn json_string(text: &str) -> String {
+    let mut quoted = String::from('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
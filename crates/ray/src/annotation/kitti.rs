@@ -0,0 +1,62 @@
+//! [KITTI object](https://www.cvlibs.net/datasets/kitti/eval_object.php) label export
+//!
+//! KITTI boxes only turn around the camera y axis and are measured in the
+//! object frame x forward (length), y down (height), z (width); the local
+//! bounds of an object should follow that convention.
+
+use std::io;
+
+use crate::Float;
+use crate::consts;
+
+/// Writes one label line per annotation, `class` being e.g. `Car` or `Pedestrian`.
+pub fn write(
+    annotations: &[(&str, &super::Annotation)],
+    writer: &mut impl io::Write,
+) -> io::Result<()> {
+    for (class, annotation) in annotations {
+        let bbox = &annotation.bbox;
+        // 0 fully visible, 1 partly occluded, 2 largely occluded, 3 unknown
+        let occluded = match annotation.visibility {
+            Some(visibility) if visibility >= 0.9 => 0,
+            Some(visibility) if visibility >= 0.5 => 1,
+            Some(_) => 2,
+            None => 3,
+        };
+
+        let (alpha, dimensions, location, rotation_y) = match annotation.box3 {
+            Some(b) => {
+                let forward = b.rotation * nalgebra::Vector3::x();
+                let rotation_y = (-forward.z).atan2(forward.x);
+                let alpha = wrap(rotation_y - b.center.x.atan2(b.center.z));
+                let size = b.half_extents * 2.;
+                // location is the center of the bottom face, y points down
+                let bottom =
+                    b.center + b.rotation * nalgebra::Vector3::new(0., b.half_extents.y, 0.);
+                (alpha, [size.y, size.z, size.x], bottom.into(), rotation_y)
+            }
+            // what KITTI uses for boxes without 3D labels
+            None => (-10., [-1.; 3], [-1000.; 3], -10.),
+        };
+
+        let [x, y, z]: [Float; 3] = location;
+        writeln!(
+            writer,
+            "{class} {:.2} {occluded} {alpha:.2} {:.2} {:.2} {:.2} {:.2} {:.2} {:.2} {:.2} {x:.2} {y:.2} {z:.2} {rotation_y:.2}",
+            annotation.truncation,
+            bbox.min.x,
+            bbox.min.y,
+            bbox.max.x,
+            bbox.max.y,
+            dimensions[0],
+            dimensions[1],
+            dimensions[2],
+        )?;
+    }
+    Ok(())
+}
+
+/// angle within [-pi, pi]
+fn wrap(angle: Float) -> Float {
+    (angle + consts::PI).rem_euclid(2. * consts::PI) - consts::PI
+}
@@ -0,0 +1,366 @@
+//! Detection and segmentation labels from rendered instance ids.
+//!
+//! An [`InstanceMask`] holds the id of the object seen by every pixel and,
+//! when rendered with [`InstanceMask::render`], how many pixels each object
+//! would cover without occluders, giving its visibility. Boxes, polygons and
+//! run lengths are in pixel units with (0, 0) at the top left corner of the
+//! top left pixel, as in COCO and KITTI.
+
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+
+use crate::Float;
+use crate::aabb;
+use crate::projection;
+use crate::ray;
+
+pub mod coco;
+pub mod kitti;
+
+/// axis aligned box in the image, `max` exclusive
+#[derive(Copy, Clone, Debug)]
+pub struct BoundingBox {
+    pub min: nalgebra::Vector2<Float>,
+    pub max: nalgebra::Vector2<Float>,
+}
+
+impl BoundingBox {
+    pub fn width(&self) -> Float {
+        self.max.x - self.min.x
+    }
+
+    pub fn height(&self) -> Float {
+        self.max.y - self.min.y
+    }
+
+    pub fn area(&self) -> Float {
+        self.width().max(0.) * self.height().max(0.)
+    }
+
+    /// the part of the box inside a `width` x `height` image
+    pub fn clip(&self, width: u32, height: u32) -> Self {
+        let size = nalgebra::Vector2::new(width as Float, height as Float);
+        Self {
+            min: self.min.sup(&nalgebra::Vector2::zeros()).inf(&size),
+            max: self.max.sup(&nalgebra::Vector2::zeros()).inf(&size),
+        }
+    }
+}
+
+/// Box with its own orientation, e.g. an object's local bounds placed in the world
+#[derive(Copy, Clone, Debug)]
+pub struct OrientedBox {
+    pub center: nalgebra::Vector3<Float>,
+    pub half_extents: nalgebra::Vector3<Float>,
+    pub rotation: nalgebra::UnitQuaternion<Float>,
+}
+
+impl OrientedBox {
+    /// local bounds of an object moved by its pose (object to world)
+    pub fn from_aabb(bounds: &aabb::Aabb, pose: &nalgebra::Isometry3<Float>) -> Self {
+        Self {
+            center: pose.transform_point(&bounds.centroid().into()).coords,
+            half_extents: bounds.extent() / 2.,
+            rotation: pose.rotation,
+        }
+    }
+
+    pub fn transform(&self, transform: &nalgebra::Isometry3<Float>) -> Self {
+        Self {
+            center: transform.transform_point(&self.center.into()).coords,
+            half_extents: self.half_extents,
+            rotation: transform.rotation * self.rotation,
+        }
+    }
+
+    pub fn corners(&self) -> [nalgebra::Vector3<Float>; 8] {
+        std::array::from_fn(|i| {
+            let sign = |bit: usize| if i & bit == 0 { -1. } else { 1. };
+            let local =
+                self.half_extents
+                    .component_mul(&nalgebra::Vector3::new(sign(1), sign(2), sign(4)));
+            self.center + self.rotation * local
+        })
+    }
+
+    /// Image box around the projected corners of a box in world coordinates.
+    ///
+    /// `None` when any corner can not be projected, e.g. is behind a pinhole
+    /// camera; the box then reaches out of the image without bound and the
+    /// corners that can be seen would give too small a box.
+    pub fn project<P: projection::Projection>(
+        &self,
+        projection: &P,
+        camera_pose: &nalgebra::Isometry3<Float>,
+    ) -> Option<BoundingBox> {
+        let corners = self
+            .corners()
+            .iter()
+            .map(|corner| projection.world_to_pixel(camera_pose, corner))
+            .collect::<Option<Vec<_>>>()?;
+        let (min, max) = corners
+            .iter()
+            .fold((corners[0], corners[0]), |(min, max), p| {
+                (p.inf(&min), p.sup(&max))
+            });
+        Some(BoundingBox { min, max })
+    }
+}
+
+/// Labels of one object in one image
+#[derive(Clone, Debug)]
+pub struct Annotation {
+    pub id: u32,
+    /// tight box around the visible pixels
+    pub bbox: BoundingBox,
+    /// visible pixels
+    pub area: usize,
+    /// visible fraction of the pixels the object would cover without occluders
+    pub visibility: Option<Float>,
+    /// fraction of the projected 3D box outside the image, 1 when it reaches behind the camera
+    pub truncation: Float,
+    /// 3D box in the camera frame
+    pub box3: Option<OrientedBox>,
+    /// outer boundaries of the visible pixels
+    pub polygons: Vec<Vec<nalgebra::Vector2<Float>>>,
+    /// run lengths of the visible pixels, see [`InstanceMask::rle`]
+    pub rle: Vec<u32>,
+}
+
+/// Object id seen by every pixel, row major
+pub struct InstanceMask {
+    width: u32,
+    height: u32,
+    ids: Vec<Option<u32>>,
+    // pixels each object covers when nothing is in front of it
+    unoccluded: Option<BTreeMap<u32, usize>>,
+}
+
+impl InstanceMask {
+    /// from an id image, without occlusion information
+    pub fn new(width: u32, height: u32, ids: Vec<Option<u32>>) -> Self {
+        debug_assert_eq!(
+            ids.len(),
+            (width * height) as usize,
+            "ids do not match image size"
+        );
+        Self {
+            width,
+            height,
+            ids,
+            unoccluded: None,
+        }
+    }
+
+    /// Renders the ids and the unoccluded coverage of every object.
+    ///
+    /// # Arguments
+    /// - `hits`: every object a world ray hits, as (id, distance)
+    pub fn render<P: projection::Projection>(
+        projection: &P,
+        camera_pose: &nalgebra::Isometry3<Float>,
+        hits: impl Fn(&ray::Ray) -> Vec<(u32, Float)>,
+    ) -> Self {
+        let mut unoccluded = BTreeMap::new();
+        let ids = projection::pixel_rays(projection)
+            .map(|ray| {
+                let hits = hits(&ray?.transform(camera_pose));
+                for (id, _) in &hits {
+                    *unoccluded.entry(*id).or_insert(0) += 1;
+                }
+                hits.iter()
+                    .min_by(|a, b| a.1.total_cmp(&b.1))
+                    .map(|(id, _)| *id)
+            })
+            .collect();
+
+        Self {
+            width: projection.width_px(),
+            height: projection.height_px(),
+            ids,
+            unoccluded: Some(unoccluded),
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn ids(&self) -> &[Option<u32>] {
+        &self.ids
+    }
+
+    /// ids of the visible objects, in increasing order
+    pub fn instances(&self) -> Vec<u32> {
+        let mut ids = self.ids.iter().flatten().copied().collect::<Vec<_>>();
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+
+    fn contains(&self, id: u32, x: i64, y: i64) -> bool {
+        x >= 0
+            && y >= 0
+            && x < self.width as i64
+            && y < self.height as i64
+            && self.ids[(y * self.width as i64 + x) as usize] == Some(id)
+    }
+
+    pub fn area(&self, id: u32) -> usize {
+        self.ids.iter().filter(|i| **i == Some(id)).count()
+    }
+
+    /// visible fraction of the object, `None` without occlusion information
+    pub fn visibility(&self, id: u32) -> Option<Float> {
+        let unoccluded = *self.unoccluded.as_ref()?.get(&id)?;
+        Some(self.area(id) as Float / unoccluded as Float)
+    }
+
+    pub fn bounding_box(&self, id: u32) -> Option<BoundingBox> {
+        let (min, max) = self
+            .ids
+            .iter()
+            .enumerate()
+            .filter(|(_, i)| **i == Some(id))
+            .map(|(i, _)| {
+                let i = i as u32;
+                nalgebra::Vector2::new((i % self.width) as Float, (i / self.width) as Float)
+            })
+            .fold(None, |bounds, p| match bounds {
+                None => Some((p, p)),
+                Some((min, max)) => Some((p.inf(&min), p.sup(&max))),
+            })?;
+        Some(BoundingBox {
+            min,
+            max: max.add_scalar(1.),
+        })
+    }
+
+    /// COCO style uncompressed run lengths, column major, alternating
+    /// between pixels outside and inside the object, starting outside.
+    pub fn rle(&self, id: u32) -> Vec<u32> {
+        let mut counts = Vec::new();
+        let mut inside = false;
+        let mut run = 0;
+        for x in 0..self.width as i64 {
+            for y in 0..self.height as i64 {
+                if self.contains(id, x, y) != inside {
+                    counts.push(run);
+                    inside = !inside;
+                    run = 0;
+                }
+                run += 1;
+            }
+        }
+        counts.push(run);
+        counts
+    }
+
+    /// Outer boundaries of the object's pixels along the pixel edges, one
+    /// per connected region; holes are left out, the run lengths keep them.
+    pub fn polygons(&self, id: u32) -> Vec<Vec<nalgebra::Vector2<Float>>> {
+        // directed pixel edges between the object and the rest, object on the right
+        let mut edges = HashMap::<(i64, i64), Vec<(i64, i64)>>::new();
+        for y in 0..self.height as i64 {
+            for x in 0..self.width as i64 {
+                if !self.contains(id, x, y) {
+                    continue;
+                }
+                let sides = [
+                    ((0, -1), (x, y), (x + 1, y)),
+                    ((1, 0), (x + 1, y), (x + 1, y + 1)),
+                    ((0, 1), (x + 1, y + 1), (x, y + 1)),
+                    ((-1, 0), (x, y + 1), (x, y)),
+                ];
+                for ((dx, dy), from, to) in sides {
+                    if !self.contains(id, x + dx, y + dy) {
+                        edges.entry(from).or_default().push(to);
+                    }
+                }
+            }
+        }
+
+        let mut starts = edges.keys().copied().collect::<Vec<_>>();
+        starts.sort_unstable_by_key(|(x, y)| (*y, *x));
+        let mut polygons = Vec::new();
+        for start in starts {
+            while let Some(mut to) = edges.get_mut(&start).and_then(|next| next.pop()) {
+                let mut corners = vec![start];
+                while to != start {
+                    corners.push(to);
+                    to = edges
+                        .get_mut(&to)
+                        .and_then(|next| next.pop())
+                        .expect("pixel boundaries are closed");
+                }
+
+                // clockwise on screen is positive, holes run the other way
+                let area = (0..corners.len())
+                    .map(|i| {
+                        let (a, b) = (corners[i], corners[(i + 1) % corners.len()]);
+                        a.0 * b.1 - b.0 * a.1
+                    })
+                    .sum::<i64>();
+                if area > 0 {
+                    polygons.push(simplify(&corners));
+                }
+            }
+        }
+        polygons
+    }
+
+    /// Labels of every visible object.
+    ///
+    /// # Arguments
+    /// - `boxes`: 3D box in the world of an object id, if it has one
+    pub fn annotations<P: projection::Projection>(
+        &self,
+        projection: &P,
+        camera_pose: &nalgebra::Isometry3<Float>,
+        boxes: impl Fn(u32) -> Option<OrientedBox>,
+    ) -> Vec<Annotation> {
+        self.instances()
+            .into_iter()
+            .filter_map(|id| {
+                let bbox = self.bounding_box(id)?;
+                let world_box = boxes(id);
+                let truncation = match world_box.map(|b| b.project(projection, camera_pose)) {
+                    None => 0.,
+                    // partly behind the camera, the projection has no bound
+                    Some(None) => 1.,
+                    Some(Some(projected)) if projected.area() > 0. => {
+                        1. - projected.clip(self.width, self.height).area() / projected.area()
+                    }
+                    Some(Some(_)) => 0.,
+                };
+
+                Some(Annotation {
+                    id,
+                    bbox,
+                    area: self.area(id),
+                    visibility: self.visibility(id),
+                    truncation,
+                    box3: world_box.map(|b| b.transform(&camera_pose.inverse())),
+                    polygons: self.polygons(id),
+                    rle: self.rle(id),
+                })
+            })
+            .collect()
+    }
+}
+
+/// drops corners along straight edges
+fn simplify(corners: &[(i64, i64)]) -> Vec<nalgebra::Vector2<Float>> {
+    let n = corners.len();
+    (0..n)
+        .filter(|&i| {
+            let (prev, corner, next) = (corners[(i + n - 1) % n], corners[i], corners[(i + 1) % n]);
+            (corner.0 - prev.0) * (next.1 - corner.1) != (corner.1 - prev.1) * (next.0 - corner.0)
+        })
+        .map(|i| nalgebra::Vector2::new(corners[i].0 as Float, corners[i].1 as Float))
+        .collect()
+}
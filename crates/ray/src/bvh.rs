@@ -0,0 +1,264 @@
+//! Bounding volume hierarchy over anything with bounds.
+//!
+//! The hierarchy only stores primitive indices, intersection of the
+//! primitives themselves is left to the caller. Built by splitting at the
+//! median centroid along the widest axis.
+
+use crate::Float;
+use crate::Real;
+use crate::aabb;
+use crate::packet;
+use crate::ray;
+
+const MAX_LEAF_SIZE: usize = 4;
+
+struct Node<T> {
+    bounds: aabb::Aabb<T>,
+    // leaf: first primitive in indices, inner: index of the left child (right follows it)
+    start: usize,
+    // number of primitives, 0 for inner nodes
+    count: usize,
+}
+
+pub struct Bvh<T = Float> {
+    nodes: Vec<Node<T>>,
+    indices: Vec<usize>,
+}
+
+impl<T: Real> Bvh<T> {
+    /// builds over the bounds of each primitive, primitives are referred to by their index
+    pub fn new(bounds: &[aabb::Aabb<T>]) -> Self {
+        let mut indices = (0..bounds.len()).collect::<Vec<_>>();
+        let mut nodes = Vec::with_capacity(2 * bounds.len() / MAX_LEAF_SIZE + 1);
+
+        if bounds.is_empty() {
+            return Self { nodes, indices };
+        }
+
+        let centroids = bounds.iter().map(|b| b.centroid()).collect::<Vec<_>>();
+
+        nodes.push(Node {
+            bounds: aabb::Aabb::EMPTY,
+            start: 0,
+            count: bounds.len(),
+        });
+
+        let mut stack = vec![0];
+        while let Some(node_index) = stack.pop() {
+            let Node { start, count, .. } = nodes[node_index];
+            let primitives = &mut indices[start..start + count];
+
+            nodes[node_index].bounds = primitives
+                .iter()
+                .fold(aabb::Aabb::EMPTY, |acc, i| acc.union(&bounds[*i]));
+
+            if count <= MAX_LEAF_SIZE {
+                continue;
+            }
+
+            let axis = aabb::Aabb::from_points(primitives.iter().map(|i| &centroids[*i]))
+                .extent()
+                .imax();
+            let half = count / 2;
+            primitives.select_nth_unstable_by(half, |a, b| {
+                centroids[*a][axis].total_cmp(&centroids[*b][axis])
+            });
+
+            let left = nodes.len();
+            nodes.push(Node {
+                bounds: aabb::Aabb::EMPTY,
+                start,
+                count: half,
+            });
+            nodes.push(Node {
+                bounds: aabb::Aabb::EMPTY,
+                start: start + half,
+                count: count - half,
+            });
+            nodes[node_index].start = left;
+            nodes[node_index].count = 0;
+
+            stack.push(left);
+            stack.push(left + 1);
+        }
+
+        Self { nodes, indices }
+    }
+
+    /// bounds of everything in the hierarchy
+    pub fn bounds(&self) -> aabb::Aabb<T> {
+        self.nodes
+            .first()
+            .map(|root| root.bounds)
+            .unwrap_or(aabb::Aabb::EMPTY)
+    }
+
+    /// Closest hit along the ray, as (distance, primitive index).
+    ///
+    /// `intersect` is called with the index of every primitive whose
+    /// bounds the ray passes through.
+    pub fn closest_hit(
+        &self,
+        ray: &ray::Ray<T>,
+        mut intersect: impl FnMut(usize) -> Option<T>,
+    ) -> Option<(T, usize)> {
+        let mut closest: Option<(T, usize)> = None;
+        if self.nodes.is_empty() {
+            return closest;
+        }
+
+        let mut stack = Vec::with_capacity(64);
+        stack.push(0);
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+            let t_max = closest.map_or(ray.t_max(), |(closest, _)| closest);
+            match node.bounds.ray_intersection(ray) {
+                Some((near, far)) if far >= ray.t_min() && near <= t_max => {}
+                _ => continue,
+            }
+
+            if node.count == 0 {
+                stack.push(node.start + 1);
+                stack.push(node.start);
+                continue;
+            }
+
+            for primitive in &self.indices[node.start..node.start + node.count] {
+                if let Some(dist) = intersect(*primitive)
+                    && closest.is_none_or(|(closest, _)| dist < closest)
+                {
+                    closest = Some((dist, *primitive));
+                }
+            }
+        }
+
+        closest
+    }
+
+    /// Whether anything is hit along the ray, stopping at the first hit.
+    ///
+    /// `intersect` is called with the index of every primitive whose
+    /// bounds the ray passes through, until one of them returns true.
+    pub fn any_hit(&self, ray: &ray::Ray<T>, mut intersect: impl FnMut(usize) -> bool) -> bool {
+        if self.nodes.is_empty() {
+            return false;
+        }
+
+        let mut stack = Vec::with_capacity(64);
+        stack.push(0);
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+            match node.bounds.ray_intersection(ray) {
+                Some((near, far)) if far >= ray.t_min() && near <= ray.t_max() => {}
+                _ => continue,
+            }
+
+            if node.count == 0 {
+                stack.push(node.start + 1);
+                stack.push(node.start);
+                continue;
+            }
+
+            if self.indices[node.start..node.start + node.count]
+                .iter()
+                .any(|primitive| intersect(*primitive))
+            {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Primitive nearest to a point, as (squared distance, primitive index).
+    ///
+    /// `distance_squared` is called with the index of every primitive in
+    /// nodes closer than the nearest primitive found so far.
+    pub fn nearest(
+        &self,
+        point: &nalgebra::Vector3<T>,
+        mut distance_squared: impl FnMut(usize) -> T,
+    ) -> Option<(T, usize)> {
+        let mut nearest: Option<(T, usize)> = None;
+        let Some(root) = self.nodes.first() else {
+            return nearest;
+        };
+
+        let mut stack = Vec::with_capacity(64);
+        stack.push((0, root.bounds.distance_squared(point)));
+        while let Some((node_index, bound)) = stack.pop() {
+            if nearest.is_some_and(|(nearest, _)| bound >= nearest) {
+                continue;
+            }
+
+            let node = &self.nodes[node_index];
+            if node.count == 0 {
+                let [left, right] = [node.start, node.start + 1]
+                    .map(|child| (child, self.nodes[child].bounds.distance_squared(point)));
+                // the nearer child goes on top, so it is searched first
+                match left.1 <= right.1 {
+                    true => stack.extend([right, left]),
+                    false => stack.extend([left, right]),
+                }
+                continue;
+            }
+
+            for primitive in &self.indices[node.start..node.start + node.count] {
+                let distance = distance_squared(*primitive);
+                if nearest.is_none_or(|(nearest, _)| distance < nearest) {
+                    nearest = Some((distance, *primitive));
+                }
+            }
+        }
+
+        nearest
+    }
+
+    /// Closest hit for every ray in a packet, as (distance, primitive index).
+    ///
+    /// Nodes are visited while any lane may still hit something closer,
+    /// `intersect` is called with the index of each primitive in those nodes
+    /// and returns the hit of every lane.
+    pub fn closest_hit_packet<const N: usize>(
+        &self,
+        packet: &packet::RayPacket<N, T>,
+        mut intersect: impl FnMut(usize) -> packet::Lanes<Option<T>, N>,
+    ) -> packet::Lanes<Option<(T, usize)>, N> {
+        let mut closest: packet::Lanes<Option<(T, usize)>, N> = [None; N];
+        if self.nodes.is_empty() {
+            return closest;
+        }
+
+        let mut stack = Vec::with_capacity(64);
+        stack.push(0);
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+            let near = node.bounds.ray_packet_intersection(packet);
+            let any_active = near.iter().zip(&closest).any(|(near, closest)| {
+                *near < T::INFINITY && closest.is_none_or(|(closest, _)| *near <= closest)
+            });
+            if !any_active {
+                continue;
+            }
+
+            if node.count == 0 {
+                stack.push(node.start + 1);
+                stack.push(node.start);
+                continue;
+            }
+
+            for primitive in &self.indices[node.start..node.start + node.count] {
+                let hits = intersect(*primitive);
+                for (hit, closest) in hits.iter().zip(closest.iter_mut()) {
+                    if let Some(dist) = *hit
+                        && closest.is_none_or(|(closest, _)| dist < closest)
+                    {
+                        *closest = Some((dist, *primitive));
+                    }
+                }
+            }
+        }
+
+        closest
+    }
+}
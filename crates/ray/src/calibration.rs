@@ -0,0 +1,177 @@
+//! Camera intrinsics from and to calibration files.
+//!
+//! Reads the YAML written by OpenCV's `FileStorage`, ROS `camera_calibration`
+//! (`ost.yaml`) and echoed ROS 1 / ROS 2 `CameraInfo` messages. Only image
+//! size and K are used, distortion is not modeled and is ignored.
+
+use crate::Float;
+use crate::camera;
+use crate::distance::Distance;
+
+#[derive(Debug)]
+pub enum Error {
+    /// none of the keys the value could be stored under were found
+    MissingKey(&'static str),
+    InvalidNumber(String),
+    /// K did not hold 9 numbers
+    InvalidMatrix(usize),
+    /// image width or height that is not a positive integer
+    InvalidData(String),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::MissingKey(key) => write!(f, "missing {key}"),
+            Error::InvalidNumber(value) => write!(f, "invalid number {value:?}"),
+            Error::InvalidMatrix(len) => write!(f, "camera matrix has {len} values instead of 9"),
+            Error::InvalidData(value) => write!(f, "invalid image size {value:?}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Camera from calibration YAML, see [`camera::Camera::from_k`] for why the
+/// horizontal `pixel_pitch` is needed.
+pub fn read_yaml(yaml: &str, pixel_pitch: Distance) -> Result<camera::Camera, Error> {
+    let lines = yaml
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim_end())
+        .collect::<Vec<_>>();
+
+    let width = scalar(&lines, &["image_width", "width"]).ok_or(Error::MissingKey("width"))?;
+    let height = scalar(&lines, &["image_height", "height"]).ok_or(Error::MissingKey("height"))?;
+
+    // OpenCV and ost.yaml nest the values in a matrix, messages have a flat list
+    let k = match key_line(&lines, "camera_matrix", false) {
+        Some(start) => {
+            key_line(&lines[start..], "data", true).map(|i| sequence(&lines[start + i..], "data"))
+        }
+        None => key_line(&lines, "K", false)
+            .map(|i| sequence(&lines[i..], "K"))
+            .or_else(|| key_line(&lines, "k", false).map(|i| sequence(&lines[i..], "k"))),
+    }
+    .ok_or(Error::MissingKey("camera_matrix"))?;
+
+    let k = k
+        .iter()
+        .map(|value| number(value))
+        .collect::<Result<Vec<_>, _>>()?;
+    if k.len() != 9 {
+        return Err(Error::InvalidMatrix(k.len()));
+    }
+
+    Ok(camera::Camera::from_k(
+        size(width)?,
+        size(height)?,
+        &nalgebra::Matrix3::from_row_slice(&k),
+        pixel_pitch,
+    ))
+}
+
+/// Writes the intrinsics in the ROS `camera_calibration` format, readable by
+/// OpenCV and [`read_yaml`], with zero distortion.
+pub fn write_yaml<W: std::io::Write>(
+    camera: &camera::Camera,
+    camera_name: &str,
+    writer: &mut W,
+) -> std::io::Result<()> {
+    let k = camera.k();
+    // row major, like the file format
+    let values = |m: &[Float]| {
+        m.iter()
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let k_rows = k.transpose();
+    let projection = k.insert_column(3, 0.).transpose();
+
+    writeln!(writer, "image_width: {}", camera.width_px())?;
+    writeln!(writer, "image_height: {}", camera.height_px())?;
+    writeln!(writer, "camera_name: {camera_name}")?;
+    writeln!(writer, "camera_matrix:")?;
+    writeln!(writer, "  rows: 3")?;
+    writeln!(writer, "  cols: 3")?;
+    writeln!(writer, "  data: [{}]", values(k_rows.as_slice()))?;
+    writeln!(writer, "distortion_model: plumb_bob")?;
+    writeln!(writer, "distortion_coefficients:")?;
+    writeln!(writer, "  rows: 1")?;
+    writeln!(writer, "  cols: 5")?;
+    writeln!(writer, "  data: [0, 0, 0, 0, 0]")?;
+    writeln!(writer, "rectification_matrix:")?;
+    writeln!(writer, "  rows: 3")?;
+    writeln!(writer, "  cols: 3")?;
+    writeln!(writer, "  data: [1, 0, 0, 0, 1, 0, 0, 0, 1]")?;
+    writeln!(writer, "projection_matrix:")?;
+    writeln!(writer, "  rows: 3")?;
+    writeln!(writer, "  cols: 4")?;
+    writeln!(writer, "  data: [{}]", values(projection.as_slice()))?;
+    Ok(())
+}
+
+/// index of the line holding `key`, top level keys only unless `nested`
+fn key_line(lines: &[&str], key: &str, nested: bool) -> Option<usize> {
+    lines.iter().position(|line| {
+        let trimmed = line.trim_start();
+        (nested || trimmed.len() == line.len())
+            && trimmed
+                .strip_prefix(key)
+                .is_some_and(|rest| rest.starts_with(':'))
+    })
+}
+
+fn value_after<'a>(line: &'a str, key: &str) -> &'a str {
+    let line = line.trim_start();
+    line[key.len() + 1..].trim()
+}
+
+fn scalar<'a>(lines: &[&'a str], keys: &[&str]) -> Option<&'a str> {
+    keys.iter()
+        .find_map(|key| key_line(lines, key, false).map(|i| value_after(lines[i], key)))
+}
+
+/// Values of a flow (`[a, b]`) or block (`- a` per line) sequence starting
+/// at the first line, which holds `key`.
+fn sequence<'a>(lines: &[&'a str], key: &str) -> Vec<&'a str> {
+    let first = value_after(lines[0], key);
+
+    if let Some(start) = first.find('[') {
+        let mut values = Vec::new();
+        for text in std::iter::once(&first[start + 1..]).chain(lines[1..].iter().copied()) {
+            let (text, done) = match text.find(']') {
+                Some(end) => (&text[..end], true),
+                None => (text, false),
+            };
+            values.extend(text.split(',').map(str::trim).filter(|v| !v.is_empty()));
+            if done {
+                break;
+            }
+        }
+        return values;
+    }
+
+    lines[1..]
+        .iter()
+        .map(|line| line.trim_start())
+        .take_while(|line| line.starts_with('-'))
+        .map(|line| line[1..].trim())
+        .collect()
+}
+
+fn number(value: &str) -> Result<Float, Error> {
+    value
+        .trim_matches(|c| c == '"' || c == '\'')
+        .parse::<Float>()
+        .map_err(|_| Error::InvalidNumber(value.to_string()))
+}
+
+/// image width or height in pixels, rejecting anything but positive integers
+fn size(value: &str) -> Result<u32, Error> {
+    let size = number(value)?;
+    match size >= 1. && size.fract() == 0. && size <= u32::MAX as Float {
+        true => Ok(size as u32),
+        false => Err(Error::InvalidData(value.to_string())),
+    }
+}
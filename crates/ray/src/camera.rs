@@ -1,67 +1,479 @@
+use crate::Float;
+use crate::Real;
 use crate::angle;
+use crate::distance;
+use crate::projection;
+use crate::ray;
 
-pub struct Camera {
-    width_px: f32,
-    height_px: f32,
-    pixel_pitch: crate::distance::Distance,
-    focal_length: crate::distance::Distance,
+#[derive(Clone, Debug)]
+pub struct Camera<T = Float> {
+    width_px: T,
+    height_px: T,
+    pixel_pitch_x: crate::distance::Distance<T>,
+    pixel_pitch_y: crate::distance::Distance<T>,
+    // where the optical axis meets the sensor, in pixels
+    principal_point: nalgebra::Vector2<T>,
+    focal_length: crate::distance::Distance<T>,
+    // pinhole without
+    lens: Option<ThinLens<T>>,
+    shutter: Shutter,
 }
 
-impl Camera {
+impl<T: Real> Camera<T> {
     pub fn new(
         width_px: u32,
         height_px: u32,
-        pixel_pitch: crate::distance::Distance,
-        focal_length: crate::distance::Distance,
+        pixel_pitch: crate::distance::Distance<T>,
+        focal_length: crate::distance::Distance<T>,
     ) -> Self {
         debug_assert!(width_px > 0, "width of a camera cannot be <= 0");
-        debug_assert!(width_px > 0, "width of a camera cannot be <= 0");
+        debug_assert!(height_px > 0, "height of a camera cannot be <= 0");
         debug_assert!(
-            pixel_pitch.m() > 0.,
+            pixel_pitch.m() > T::zero(),
             "pixel pitch of a camera cannot be <= 0.0"
         );
 
+        let width_px = count(width_px);
+        let height_px = count(height_px);
         Self {
-            width_px: width_px as f32,
-            height_px: height_px as f32,
-            pixel_pitch,
+            width_px,
+            height_px,
+            pixel_pitch_x: pixel_pitch,
+            pixel_pitch_y: pixel_pitch,
+            principal_point: nalgebra::Vector2::new(width_px, height_px)
+                / nalgebra::convert::<_, T>(2.),
             focal_length,
+            lens: None,
+            shutter: Shutter::global(0.),
         }
     }
 
+    /// Camera from a 3x3 intrinsic matrix K, in pixels, skew is ignored.
+    ///
+    /// K only fixes the ratio of focal length to pitch, so the horizontal
+    /// `pixel_pitch` of the sensor is needed to get physical units.
+    pub fn from_k(
+        width_px: u32,
+        height_px: u32,
+        k: &nalgebra::Matrix3<T>,
+        pixel_pitch: crate::distance::Distance<T>,
+    ) -> Self {
+        debug_assert!(
+            k[(0, 0)] > T::zero() && k[(1, 1)] > T::zero(),
+            "focal lengths in K cannot be <= 0.0"
+        );
+        let focal_length = crate::distance::Distance::from_m(k[(0, 0)] * pixel_pitch.m());
+        Self::new(width_px, height_px, pixel_pitch, focal_length)
+            .with_pixel_pitch_y(crate::distance::Distance::from_m(
+                focal_length.m() / k[(1, 1)],
+            ))
+            .with_principal_point(&nalgebra::Vector2::new(k[(0, 2)], k[(1, 2)]))
+    }
+
+    /// Camera with the principal point in the center and the given fields of view.
+    pub fn from_fov(
+        width_px: u32,
+        height_px: u32,
+        hfov: angle::Angle<T>,
+        vfov: angle::Angle<T>,
+        pixel_pitch: crate::distance::Distance<T>,
+    ) -> Self {
+        let half = nalgebra::convert::<_, T>(0.5);
+        let focal_length = crate::distance::Distance::from_m(
+            half * count(width_px) * pixel_pitch.m() / (hfov * half).tan(),
+        );
+        let pitch_y = focal_length.m() * (vfov * half).tan() / (half * count(height_px));
+        Self::new(width_px, height_px, pixel_pitch, focal_length)
+            .with_pixel_pitch_y(crate::distance::Distance::from_m(pitch_y))
+    }
+
+    /// vertical pitch for non square pixels, the pitch given to [`Camera::new`] is then horizontal
+    pub fn with_pixel_pitch_y(mut self, pixel_pitch_y: crate::distance::Distance<T>) -> Self {
+        debug_assert!(
+            pixel_pitch_y.m() > T::zero(),
+            "pixel pitch of a camera cannot be <= 0.0"
+        );
+        self.pixel_pitch_y = pixel_pitch_y;
+        self
+    }
+
+    /// where the optical axis meets the sensor in pixel coordinates, the center by default
+    pub fn with_principal_point(mut self, principal_point: &nalgebra::Vector2<T>) -> Self {
+        self.principal_point = *principal_point;
+        self
+    }
+
+    /// 3x3 intrinsic matrix, focal lengths and principal point in pixels
+    pub fn k(&self) -> nalgebra::Matrix3<T> {
+        let f = self.focal_length.m();
+        nalgebra::Matrix3::new(
+            f / self.pixel_pitch_x.m(),
+            T::zero(),
+            self.principal_point.x,
+            T::zero(),
+            f / self.pixel_pitch_y.m(),
+            self.principal_point.y,
+            T::zero(),
+            T::zero(),
+            T::one(),
+        )
+    }
+
+    /// when every row integrates light, for motion blur and rolling shutter
+    pub fn with_shutter(mut self, shutter: Shutter) -> Self {
+        self.shutter = shutter;
+        self
+    }
+
+    /// finite aperture, for depth of field
+    pub fn with_thin_lens(mut self, lens: ThinLens<T>) -> Self {
+        self.lens = Some(lens);
+        self
+    }
+
     /// Determines the ray from the camera from the pixel location.
     /// Note that values outside of the defined camera array valid.
     ///
     /// x increases right
     /// y increases down
     /// z increases away from camera
-    pub fn pixel_to_camera_vector(&self, x: f32, y: f32) -> nalgebra::Vector3<f32> {
-        let x = self.pixel_pitch.m() * (x - self.width_px / 2.);
-        let y = self.pixel_pitch.m() * (y - self.height_px / 2.);
+    pub fn pixel_to_camera_vector(&self, x: T, y: T) -> nalgebra::Vector3<T> {
+        let x = self.pixel_pitch_x.m() * (x - self.principal_point.x);
+        let y = self.pixel_pitch_y.m() * (y - self.principal_point.y);
         let z = self.focal_length.m();
 
         nalgebra::Vector3::new(x, y, z)
     }
 
+    /// Ray through the pixel location in the camera frame, carrying the
+    /// differentials to the neighbouring pixels for filtering.
+    pub fn pixel_to_camera_ray(&self, x: T, y: T) -> ray::Ray<T> {
+        let direction =
+            |x, y| nalgebra::UnitVector3::new_normalize(self.pixel_to_camera_vector(x, y));
+        let origin = nalgebra::Vector3::zeros();
+
+        ray::Ray::new(origin, direction(x, y)).with_differentials(ray::RayDifferentials {
+            rx_origin: origin,
+            rx_direction: direction(x + T::one(), y),
+            ry_origin: origin,
+            ry_direction: direction(x, y + T::one()),
+        })
+    }
+
+    /// Ray through the pixel location from a point on the aperture, in the
+    /// camera frame. Points at the focus distance stay sharp, everything
+    /// else blurs as the rays of a pixel spread over the aperture.
+    ///
+    /// Same as [`Camera::pixel_to_camera_ray`] for a pinhole camera.
+    ///
+    /// # Arguments
+    /// - `sample`: uniform in [0, 1)², picks the point on the aperture
+    pub fn pixel_to_camera_lens_ray(
+        &self,
+        x: T,
+        y: T,
+        sample: &nalgebra::Vector2<T>,
+    ) -> ray::Ray<T> {
+        let Some(lens) = self.lens.as_ref() else {
+            return self.pixel_to_camera_ray(x, y);
+        };
+
+        let aperture = lens.sample_aperture(sample);
+        let origin = nalgebra::Vector3::new(aperture.x, aperture.y, T::zero());
+        // where the pinhole ray meets the plane of focus
+        let direction = |x, y| {
+            let v = self.pixel_to_camera_vector(x, y);
+            let focus = v * (lens.focus_distance.m() / v.z);
+            nalgebra::UnitVector3::new_normalize(focus - origin)
+        };
+
+        ray::Ray::new(origin, direction(x, y)).with_differentials(ray::RayDifferentials {
+            rx_origin: origin,
+            rx_direction: direction(x + T::one(), y),
+            ry_origin: origin,
+            ry_direction: direction(x, y + T::one()),
+        })
+    }
+
+    /// Lens ray cast at a time within the exposure of its row, see [`Shutter`].
+    ///
+    /// # Arguments
+    /// - `lens_sample`: uniform in [0, 1)², picks the point on the aperture
+    /// - `time_sample`: uniform in [0, 1), picks the time within the exposure
+    pub fn pixel_to_camera_ray_at(
+        &self,
+        x: T,
+        y: T,
+        lens_sample: &nalgebra::Vector2<T>,
+        time_sample: Float,
+    ) -> ray::Ray<T> {
+        let row = nalgebra::convert_unchecked(y);
+        self.pixel_to_camera_lens_ray(x, y, lens_sample)
+            .with_time(self.shutter.sample_time(row, self.height_px(), time_sample))
+    }
+
+    pub fn shutter(&self) -> &Shutter {
+        &self.shutter
+    }
+
+    pub fn lens(&self) -> Option<&ThinLens<T>> {
+        self.lens.as_ref()
+    }
+
+    /// focal length over aperture diameter, `None` for a pinhole camera
+    pub fn f_number(&self) -> Option<T> {
+        self.lens
+            .as_ref()
+            .map(|lens| self.focal_length.m() / lens.aperture_diameter.m())
+    }
+
+    /// Diameter of the blur spot on the sensor of a point at `depth` along z,
+    /// zero for a pinhole camera.
+    pub fn circle_of_confusion(&self, depth: distance::Distance<T>) -> distance::Distance<T> {
+        let Some(lens) = self.lens.as_ref() else {
+            return distance::Distance::from_m(T::zero());
+        };
+
+        let f = self.focal_length.m();
+        let focus = lens.focus_distance.m();
+        let depth = depth.m();
+        distance::Distance::from_m(
+            lens.aperture_diameter.m() * (depth - focus).abs() / depth * f / (focus - f),
+        )
+    }
+
     pub fn width_px(&self) -> u32 {
-        self.width_px as u32
+        nalgebra::convert_unchecked::<T, f64>(self.width_px) as u32
     }
 
     pub fn height_px(&self) -> u32 {
-        self.height_px as u32
+        nalgebra::convert_unchecked::<T, f64>(self.height_px) as u32
+    }
+
+    pub fn pixel_pitch_x(&self) -> crate::distance::Distance<T> {
+        self.pixel_pitch_x
+    }
+
+    pub fn pixel_pitch_y(&self) -> crate::distance::Distance<T> {
+        self.pixel_pitch_y
+    }
+
+    pub fn principal_point(&self) -> &nalgebra::Vector2<T> {
+        &self.principal_point
+    }
+
+    pub fn focal_length(&self) -> crate::distance::Distance<T> {
+        self.focal_length
     }
 
     /// horizontal field of view of the camera
-    pub fn hfov(&self) -> angle::Angle {
-        let half_width = 0.5 * self.pixel_pitch.m() * self.width_px;
-        let rads = 2. * (half_width / self.focal_length.m()).atan();
-        angle::Angle::from_rads(rads)
+    pub fn hfov(&self) -> angle::Angle<T> {
+        let left = self.pixel_pitch_x.m() * self.principal_point.x;
+        let right = self.pixel_pitch_x.m() * (self.width_px - self.principal_point.x);
+        let f = self.focal_length.m();
+        angle::Angle::from_rads((left / f).atan() + (right / f).atan())
     }
 
     /// vertical field of view of the camera
-    pub fn vfov(&self) -> angle::Angle {
-        let half_height = 0.5 * self.pixel_pitch.m() * self.height_px;
-        let rads = 2. * (half_height / self.focal_length.m()).atan();
-        angle::Angle::from_rads(rads)
+    pub fn vfov(&self) -> angle::Angle<T> {
+        let top = self.pixel_pitch_y.m() * self.principal_point.y;
+        let bottom = self.pixel_pitch_y.m() * (self.height_px - self.principal_point.y);
+        let f = self.focal_length.m();
+        angle::Angle::from_rads((top / f).atan() + (bottom / f).atan())
+    }
+}
+
+impl<T: Real> projection::Projection<T> for Camera<T> {
+    fn width_px(&self) -> u32 {
+        self.width_px()
+    }
+
+    fn height_px(&self) -> u32 {
+        self.height_px()
+    }
+
+    fn pixel_ray(&self, x: T, y: T) -> Option<ray::Ray<T>> {
+        Some(self.pixel_to_camera_ray(x, y))
+    }
+
+    fn camera_to_pixel(&self, point: &nalgebra::Vector3<T>) -> Option<nalgebra::Vector2<T>> {
+        if point.z <= T::zero() {
+            return None;
+        }
+        let scale = self.focal_length.m() / point.z;
+        Some(nalgebra::Vector2::new(
+            point.x * scale / self.pixel_pitch_x.m() + self.principal_point.x,
+            point.y * scale / self.pixel_pitch_y.m() + self.principal_point.y,
+        ))
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum ApertureShape<T = Float> {
+    Circle,
+    /// regular polygon of aperture blades, `rotation` of the first corner from the x axis
+    Polygon {
+        blades: u32,
+        rotation: angle::Angle<T>,
+    },
+}
+
+/// Thin lens focused at a plane in front of the camera
+#[derive(Copy, Clone, Debug)]
+pub struct ThinLens<T = Float> {
+    aperture_diameter: distance::Distance<T>,
+    // along the optical axis
+    focus_distance: distance::Distance<T>,
+    shape: ApertureShape<T>,
+}
+
+impl<T: Real> ThinLens<T> {
+    /// circular aperture
+    pub fn new(
+        aperture_diameter: distance::Distance<T>,
+        focus_distance: distance::Distance<T>,
+    ) -> Self {
+        debug_assert!(
+            aperture_diameter.m() > T::zero(),
+            "aperture diameter can not be <= 0.0"
+        );
+        debug_assert!(
+            focus_distance.m() > T::zero(),
+            "focus distance can not be <= 0.0"
+        );
+        Self {
+            aperture_diameter,
+            focus_distance,
+            shape: ApertureShape::Circle,
+        }
+    }
+
+    /// aperture diameter from the f-number of a lens with `focal_length`
+    pub fn from_f_number(
+        f_number: T,
+        focal_length: distance::Distance<T>,
+        focus_distance: distance::Distance<T>,
+    ) -> Self {
+        debug_assert!(f_number > T::zero(), "f-number can not be <= 0.0");
+        Self::new(
+            distance::Distance::from_m(focal_length.m() / f_number),
+            focus_distance,
+        )
+    }
+
+    /// polygonal aperture, as stopped down real lenses have
+    pub fn with_blades(mut self, blades: u32, rotation: angle::Angle<T>) -> Self {
+        debug_assert!(blades >= 3, "an aperture needs at least 3 blades");
+        self.shape = ApertureShape::Polygon { blades, rotation };
+        self
+    }
+
+    pub fn aperture_diameter(&self) -> distance::Distance<T> {
+        self.aperture_diameter
+    }
+
+    pub fn focus_distance(&self) -> distance::Distance<T> {
+        self.focus_distance
+    }
+
+    pub fn shape(&self) -> ApertureShape<T> {
+        self.shape
+    }
+
+    /// Uniformly distributed point on the aperture, in m from the optical axis.
+    ///
+    /// # Arguments
+    /// - `sample`: uniform in [0, 1)²
+    pub fn sample_aperture(&self, sample: &nalgebra::Vector2<T>) -> nalgebra::Vector2<T> {
+        let radius = self.aperture_diameter.m() * nalgebra::convert(0.5);
+        match self.shape {
+            ApertureShape::Circle => {
+                let r = radius * sample.x.sqrt();
+                let (sin, cos) = (T::two_pi() * sample.y).sin_cos();
+                nalgebra::Vector2::new(r * cos, r * sin)
+            }
+            ApertureShape::Polygon { blades, rotation } => {
+                // pick a wedge between the center and two corners, reuse the sample within it
+                let blades = count::<T>(blades);
+                let scaled = sample.x * blades;
+                let wedge = scaled.floor().min(blades - T::one());
+                let u = scaled - wedge;
+                let corner = |i: T| {
+                    let (sin, cos) = (rotation.rads() + T::two_pi() * i / blades).sin_cos();
+                    nalgebra::Vector2::new(cos, sin) * radius
+                };
+
+                // uniform in the triangle (center, a, b)
+                let s = u.sqrt();
+                corner(wedge) * (s * (T::one() - sample.y))
+                    + corner(wedge + T::one()) * (s * sample.y)
+            }
+        }
+    }
+}
+
+fn count<T: Real>(count: u32) -> T {
+    nalgebra::convert(f64::from(count))
+}
+
+/// Exposure timing of the rows of the sensor.
+///
+/// Row `y` of `height` starts integrating at `start + y / height * readout_time`
+/// and integrates for `exposure_time`, a zero readout time is a global shutter.
+#[derive(Copy, Clone, Debug)]
+pub struct Shutter {
+    // seconds
+    start: Float,
+    exposure_time: Float,
+    readout_time: Float,
+}
+
+impl Shutter {
+    /// every row exposed at once
+    pub fn global(exposure_time: Float) -> Self {
+        Self::rolling(exposure_time, 0.)
+    }
+
+    /// # Arguments
+    /// - `readout_time`: time to read out every row of the sensor
+    pub fn rolling(exposure_time: Float, readout_time: Float) -> Self {
+        debug_assert!(exposure_time >= 0., "exposure time can not be negative");
+        debug_assert!(readout_time >= 0., "readout time can not be negative");
+        Self {
+            start: 0.,
+            exposure_time,
+            readout_time,
+        }
+    }
+
+    /// time the first row starts integrating, in seconds
+    pub fn with_start(mut self, start: Float) -> Self {
+        self.start = start;
+        self
+    }
+
+    pub fn start(&self) -> Float {
+        self.start
+    }
+
+    pub fn exposure_time(&self) -> Float {
+        self.exposure_time
+    }
+
+    pub fn readout_time(&self) -> Float {
+        self.readout_time
+    }
+
+    /// time row `y` of a sensor `height_px` rows high starts integrating
+    pub fn row_start(&self, y: Float, height_px: u32) -> Float {
+        let height = height_px as Float;
+        let row = y.floor().clamp(0., height - 1.);
+        self.start + row / height * self.readout_time
+    }
+
+    /// # Arguments
+    /// - `sample`: uniform in [0, 1), picks the time within the exposure of the row
+    pub fn sample_time(&self, y: Float, height_px: u32, sample: Float) -> Float {
+        self.row_start(y, height_px) + sample * self.exposure_time
     }
 }
@@ -0,0 +1,45 @@
+//! Conversions between 8 bit sRGB and linear RGB
+
+use crate::Float;
+
+/// decodes an 8 bit sRGB channel into linear [0, 1]
+pub fn srgb_to_linear(channel: u8) -> Float {
+    let c = channel as Float / 255.;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// encodes a linear channel into 8 bit sRGB, clamping to [0, 1]
+pub fn linear_to_srgb(channel: Float) -> u8 {
+    let c = channel.clamp(0., 1.);
+    let c = if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1. / 2.4) - 0.055
+    };
+    (c * 255.).round() as u8
+}
+
+pub fn srgb_to_linear_rgb(rgb: [u8; 3]) -> nalgebra::Vector3<Float> {
+    nalgebra::Vector3::new(
+        srgb_to_linear(rgb[0]),
+        srgb_to_linear(rgb[1]),
+        srgb_to_linear(rgb[2]),
+    )
+}
+
+pub fn linear_rgb_to_srgb(rgb: &nalgebra::Vector3<Float>) -> [u8; 3] {
+    [
+        linear_to_srgb(rgb.x),
+        linear_to_srgb(rgb.y),
+        linear_to_srgb(rgb.z),
+    ]
+}
+
+/// relative luminance of linear (Rec. 709) RGB
+pub fn luminance(rgb: &nalgebra::Vector3<Float>) -> Float {
+    0.2126 * rgb.x + 0.7152 * rgb.y + 0.0722 * rgb.z
+}
@@ -1,37 +1,51 @@
+use crate::Float;
+use crate::Real;
+
 #[derive(Copy, Clone, Debug)]
-pub struct Distance(f32);
+pub struct Distance<T = Float>(T);
 
-const UM_FACTOR: f32 = 1e6;
-const MM_FACTOR: f32 = 1e3;
+const NM_FACTOR: f64 = 1e9;
+const UM_FACTOR: f64 = 1e6;
+const MM_FACTOR: f64 = 1e3;
 
-impl Distance {
+impl<T: Real> Distance<T> {
     #[inline(always)]
-    pub const fn from_m(m: f32) -> Self {
+    pub const fn from_m(m: T) -> Self {
         Self(m)
     }
 
     #[inline(always)]
-    pub const fn from_um(um: f32) -> Self {
-        Self(um / UM_FACTOR)
+    pub fn from_nm(nm: T) -> Self {
+        Self(nm / nalgebra::convert(NM_FACTOR))
+    }
+
+    #[inline(always)]
+    pub fn from_um(um: T) -> Self {
+        Self(um / nalgebra::convert(UM_FACTOR))
     }
 
     #[inline(always)]
-    pub const fn from_mm(mm: f32) -> Self {
-        Self(mm / MM_FACTOR)
+    pub fn from_mm(mm: T) -> Self {
+        Self(mm / nalgebra::convert(MM_FACTOR))
     }
 
     #[inline(always)]
-    pub const fn m(&self) -> f32 {
+    pub const fn m(&self) -> T {
         self.0
     }
 
     #[inline(always)]
-    pub const fn um(&self) -> f32 {
-        self.0 * UM_FACTOR
+    pub fn nm(&self) -> T {
+        self.0 * nalgebra::convert(NM_FACTOR)
+    }
+
+    #[inline(always)]
+    pub fn um(&self) -> T {
+        self.0 * nalgebra::convert(UM_FACTOR)
     }
 
     #[inline(always)]
-    pub const fn mm(&self) -> f32 {
-        self.0 * MM_FACTOR
+    pub fn mm(&self) -> T {
+        self.0 * nalgebra::convert(MM_FACTOR)
     }
 }
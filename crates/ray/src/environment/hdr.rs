@@ -0,0 +1,278 @@
+//! High dynamic range images, [Radiance RGBE](https://paulbourke.net/dataformats/pic/)
+//! (.hdr) and [PFM](https://www.pauldebevec.com/Research/HDR/PFM/), the usual
+//! formats of environment maps.
+
+use std::io;
+
+use crate::Float;
+
+// largest image accepted, keeps a corrupt header from allocating without bound
+const MAX_PIXELS: usize = 1 << 28;
+
+/// Linear RGB pixels, row major, top row first
+pub struct HdrImage {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<nalgebra::Vector3<Float>>,
+}
+
+/// Reads either format, told apart by the first bytes.
+pub fn read(reader: &mut impl io::BufRead) -> io::Result<HdrImage> {
+    let first = header_line(reader)?;
+    match first.as_str() {
+        "PF" | "Pf" => read_pfm(reader, first == "PF"),
+        line if line.starts_with("#?") => read_rgbe(reader),
+        _ => Err(invalid("unknown image format")),
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn header_line(reader: &mut impl io::BufRead) -> io::Result<String> {
+    let mut line = Vec::new();
+    reader.read_until(b'\n', &mut line)?;
+    if line.last() != Some(&b'\n') {
+        return Err(invalid("header ended early"));
+    }
+    String::from_utf8(line)
+        .map(|line| line.trim().to_string())
+        .map_err(|_| invalid("header is not text"))
+}
+
+/// rejects empty images and sizes past [`MAX_PIXELS`] before anything is allocated
+fn check_size(width: usize, height: usize) -> io::Result<()> {
+    match width > 0 && height > 0 && width.checked_mul(height).is_some_and(|n| n <= MAX_PIXELS) {
+        true => Ok(()),
+        false => Err(invalid("invalid image size")),
+    }
+}
+
+fn parse<T: std::str::FromStr>(value: Option<&str>) -> io::Result<T> {
+    value
+        .and_then(|v| v.parse().ok())
+        .ok_or_else(|| invalid("invalid header value"))
+}
+
+fn read_pfm(reader: &mut impl io::BufRead, color: bool) -> io::Result<HdrImage> {
+    let size = header_line(reader)?;
+    let mut size = size.split_whitespace();
+    let (width, height): (usize, usize) = (parse(size.next())?, parse(size.next())?);
+    check_size(width, height)?;
+    let scale: f32 = parse(Some(header_line(reader)?.as_str()))?;
+
+    let channels = if color { 3 } else { 1 };
+    let mut data = vec![0u8; width * height * channels * 4];
+    reader.read_exact(&mut data)?;
+    let values = data
+        .chunks_exact(4)
+        .map(|b| {
+            let bytes = [b[0], b[1], b[2], b[3]];
+            // a negative scale marks little endian data
+            match scale < 0. {
+                true => f32::from_le_bytes(bytes),
+                false => f32::from_be_bytes(bytes),
+            }
+        })
+        .map(nalgebra::convert::<f32, Float>)
+        .collect::<Vec<_>>();
+
+    // rows are stored bottom to top
+    let pixels = values
+        .chunks_exact(width * channels)
+        .rev()
+        .flat_map(|row| row.chunks_exact(channels))
+        .map(|p| match color {
+            true => nalgebra::Vector3::new(p[0], p[1], p[2]),
+            false => nalgebra::Vector3::repeat(p[0]),
+        })
+        .collect();
+    Ok(HdrImage {
+        width,
+        height,
+        pixels,
+    })
+}
+
+fn read_rgbe(reader: &mut impl io::BufRead) -> io::Result<HdrImage> {
+    // variables up to an empty line, then the resolution
+    loop {
+        let line = header_line(reader)?;
+        if line.is_empty() {
+            break;
+        }
+        if let Some(format) = line.strip_prefix("FORMAT=")
+            && format != "32-bit_rle_rgbe"
+        {
+            return Err(invalid("only rgbe pixels are supported"));
+        }
+    }
+    let resolution = header_line(reader)?;
+    let mut resolution = resolution.split_whitespace();
+    if resolution.next() != Some("-Y") {
+        return Err(invalid("only top to bottom images are supported"));
+    }
+    let height: usize = parse(resolution.next())?;
+    if resolution.next() != Some("+X") {
+        return Err(invalid("only left to right images are supported"));
+    }
+    let width: usize = parse(resolution.next())?;
+    check_size(width, height)?;
+
+    let mut pixels = Vec::with_capacity(width * height);
+    let mut scanline = vec![[0u8; 4]; width];
+    for _ in 0..height {
+        read_scanline(reader, &mut scanline)?;
+        pixels.extend(scanline.iter().map(|[r, g, b, e]| match e {
+            0 => nalgebra::Vector3::zeros(),
+            e => {
+                let scale = (2. as Float).powi(*e as i32 - 136);
+                nalgebra::Vector3::new(*r, *g, *b)
+                    .cast::<Float>()
+                    .add_scalar(0.5)
+                    * scale
+            }
+        }));
+    }
+    Ok(HdrImage {
+        width,
+        height,
+        pixels,
+    })
+}
+
+/// one row of rgbe pixels, flat or run length encoded per channel
+fn read_scanline(reader: &mut impl io::BufRead, scanline: &mut [[u8; 4]]) -> io::Result<()> {
+    let width = scanline.len();
+    let mut start = [0u8; 4];
+    reader.read_exact(&mut start)?;
+
+    let encoded = (8..0x8000).contains(&width)
+        && start[0] == 2
+        && start[1] == 2
+        && ((start[2] as usize) << 8 | start[3] as usize) == width;
+    if !encoded {
+        scanline[0] = start;
+        for pixel in &mut scanline[1..] {
+            reader.read_exact(pixel)?;
+        }
+        return Ok(());
+    }
+
+    let mut byte = [0u8; 1];
+    for channel in 0..4 {
+        let mut x = 0;
+        while x < width {
+            reader.read_exact(&mut byte)?;
+            let count = byte[0] as usize;
+            let (count, run) = match count > 128 {
+                true => (count - 128, true),
+                false => (count, false),
+            };
+            if count == 0 || x + count > width {
+                return Err(invalid("bad scanline run"));
+            }
+            if run {
+                reader.read_exact(&mut byte)?;
+            }
+            for pixel in &mut scanline[x..x + count] {
+                if !run {
+                    reader.read_exact(&mut byte)?;
+                }
+                pixel[channel] = byte[0];
+            }
+            x += count;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rgbe_header(width: usize, height: usize) -> Vec<u8> {
+        format!("#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {height} +X {width}\n").into_bytes()
+    }
+
+    #[test]
+    fn pfm_rows_and_byte_order() {
+        // big endian, bottom row first
+        let mut bytes = b"Pf\n2 2\n1.0\n".to_vec();
+        for value in [3f32, 4., 1., 2.] {
+            bytes.extend(value.to_be_bytes());
+        }
+        let image = read(&mut bytes.as_slice()).unwrap();
+        let values = image.pixels.iter().map(|p| p.x).collect::<Vec<_>>();
+        assert_eq!(values, [1., 2., 3., 4.]);
+
+        // little endian
+        let mut bytes = b"Pf\n2 2\n-1.0\n".to_vec();
+        for value in [3f32, 4., 1., 2.] {
+            bytes.extend(value.to_le_bytes());
+        }
+        let image = read(&mut bytes.as_slice()).unwrap();
+        let values = image.pixels.iter().map(|p| p.x).collect::<Vec<_>>();
+        assert_eq!(values, [1., 2., 3., 4.]);
+    }
+
+    #[test]
+    fn rgbe_run_length_encoded() {
+        let width = 8;
+        let mut bytes = rgbe_header(width, 1);
+        bytes.extend([2, 2, 0, width as u8]);
+        // red: a run of 8
+        bytes.extend([128 + 8, 128]);
+        // green: 3 literals, then a run of 5
+        bytes.extend([3, 10, 20, 30, 128 + 5, 64]);
+        // blue: 8 literals
+        bytes.extend([8, 0, 1, 2, 3, 4, 5, 6, 7]);
+        // exponent: 2^(129 - 136) scales 128 to 1
+        bytes.extend([128 + 8, 129]);
+
+        let image = read(&mut bytes.as_slice()).unwrap();
+        assert_eq!((image.width, image.height), (width, 1));
+        let scale = (2. as Float).powi(-7);
+        let expected = (0..width).map(|x| {
+            let green = [10., 20., 30.].get(x).copied().unwrap_or(64.);
+            nalgebra::Vector3::new(128., green, x as Float).add_scalar(0.5) * scale
+        });
+        for (pixel, expected) in image.pixels.iter().zip(expected) {
+            assert_eq!(*pixel, expected);
+        }
+    }
+
+    #[test]
+    fn rgbe_flat() {
+        // too narrow to be run length encoded
+        let mut bytes = rgbe_header(2, 1);
+        bytes.extend([128, 0, 0, 129, 0, 0, 0, 0]);
+        let image = read(&mut bytes.as_slice()).unwrap();
+        assert_eq!(
+            image.pixels,
+            [
+                nalgebra::Vector3::new(128.5, 0.5, 0.5) * (2. as Float).powi(-7),
+                nalgebra::Vector3::zeros()
+            ]
+        );
+    }
+
+    #[test]
+    fn rgbe_bad_run() {
+        let mut bytes = rgbe_header(8, 1);
+        bytes.extend([2, 2, 0, 8, 128 + 9, 0]);
+        let error = read(&mut bytes.as_slice()).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn rejects_empty_and_oversized() {
+        for header in ["Pf\n0 4\n-1.0\n", "Pf\n65536 65536\n-1.0\n"] {
+            let error = read(&mut header.as_bytes()).err().unwrap();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        }
+        let error = read(&mut rgbe_header(0, 4).as_slice()).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
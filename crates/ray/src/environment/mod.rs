@@ -0,0 +1,356 @@
+//! Light arriving from infinitely far away, seen by rays that miss the scene.
+//!
+//! An [`Environment`] wraps a [`Background`] given in its own
+//! north-east-down frame, placed in the world by a rotation, and keeps a
+//! table of its brightness over the sphere so image based lighting can
+//! sample the bright parts (sun, windows) more often, see
+//! [`crate::light::Light::Environment`].
+
+use std::io;
+use std::path;
+
+use crate::Float;
+use crate::color;
+use crate::consts;
+use crate::light;
+use crate::projection;
+use crate::projection::Projection as _;
+use crate::sky;
+use crate::texture;
+
+pub mod hdr;
+
+// resolution of the sampling table of backgrounds without an image
+const TABLE_WIDTH: usize = 64;
+const TABLE_HEIGHT: usize = 32;
+
+pub enum Background {
+    Constant(nalgebra::Vector3<Float>),
+    Gradient(Gradient),
+    Sky(sky::Sky),
+    Map(EnvironmentMap),
+}
+
+impl Background {
+    /// radiance arriving from a direction in the north-east-down frame of the background
+    pub fn radiance(&self, direction: &nalgebra::UnitVector3<Float>) -> nalgebra::Vector3<Float> {
+        match self {
+            Background::Constant(radiance) => *radiance,
+            Background::Gradient(g) => g.radiance(direction),
+            Background::Sky(s) => s.radiance(direction),
+            Background::Map(m) => m.radiance(direction),
+        }
+    }
+}
+
+/// Blend from the horizon to the zenith, with a constant ground
+#[derive(Copy, Clone, Debug)]
+pub struct Gradient {
+    pub zenith: nalgebra::Vector3<Float>,
+    pub horizon: nalgebra::Vector3<Float>,
+    pub ground: nalgebra::Vector3<Float>,
+}
+
+impl Gradient {
+    pub fn radiance(&self, direction: &nalgebra::UnitVector3<Float>) -> nalgebra::Vector3<Float> {
+        // sine of the elevation, up being -z
+        let up = -direction.z;
+        match up < 0. {
+            true => self.ground,
+            false => self.horizon.lerp(&self.zenith, up),
+        }
+    }
+}
+
+/// how the directions of an environment map are laid out in its image
+#[derive(Copy, Clone, Debug)]
+pub enum Layout {
+    /// [`projection::Equirectangular`], north in the middle and up at the top
+    Equirectangular,
+    /// [`projection::Cubemap`] strip of the faces, seen by a camera looking north (x east, y down)
+    Cubemap,
+}
+
+/// HDR image of the surroundings
+pub struct EnvironmentMap {
+    image: texture::ImageTexture,
+    layout: Layout,
+}
+
+impl EnvironmentMap {
+    /// row major linear RGB radiance, W/(sr m²), top row first
+    pub fn new(
+        width: usize,
+        height: usize,
+        radiance: Vec<nalgebra::Vector3<Float>>,
+        layout: Layout,
+    ) -> Self {
+        if let Layout::Cubemap = layout {
+            debug_assert_eq!(width, 6 * height, "cubemap strip is 6 faces wide");
+        }
+        Self {
+            image: texture::ImageTexture::new(width, height, radiance, texture::Wrap::Repeat),
+            layout,
+        }
+    }
+
+    /// Radiance HDR (.hdr) or PFM image from disk, see [`hdr::read`]
+    pub fn open(path: &path::Path, layout: Layout) -> io::Result<Self> {
+        let image = hdr::read(&mut io::BufReader::new(std::fs::File::open(path)?))?;
+        Ok(Self::new(image.width, image.height, image.pixels, layout))
+    }
+
+    pub fn width(&self) -> usize {
+        self.image.width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.image.height()
+    }
+
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    pub fn radiance(&self, direction: &nalgebra::UnitVector3<Float>) -> nalgebra::Vector3<Float> {
+        // north-east-down to the frame of a camera looking north
+        let camera = nalgebra::Vector3::new(direction.y, direction.z, direction.x);
+        let (width, height) = (self.width() as u32, self.height() as u32);
+        let pixel = match self.layout {
+            Layout::Equirectangular => {
+                projection::Equirectangular::new(width, height).camera_to_pixel(&camera)
+            }
+            Layout::Cubemap => projection::Cubemap::new(height).camera_to_pixel(&camera),
+        };
+        pixel.map_or(nalgebra::Vector3::zeros(), |p| {
+            self.image.bilinear(
+                0,
+                &nalgebra::Vector2::new(p.x / width as Float, p.y / height as Float),
+            )
+        })
+    }
+}
+
+/// A direction picked in proportion to the brightness of the environment
+#[derive(Copy, Clone, Debug)]
+pub struct EnvironmentSample {
+    /// towards the environment, world frame
+    pub direction: nalgebra::UnitVector3<Float>,
+    pub radiance: nalgebra::Vector3<Float>,
+    /// solid angle density
+    pub pdf: Float,
+}
+
+pub struct Environment {
+    background: Background,
+    // background to world
+    rotation: nalgebra::UnitQuaternion<Float>,
+    // brightness over azimuth (columns) and angle from the zenith (rows)
+    distribution: Distribution2d,
+}
+
+impl Environment {
+    /// background in a north-east-down world
+    pub fn new(background: Background) -> Self {
+        let (width, height) = match &background {
+            Background::Map(map) => match map.layout() {
+                Layout::Equirectangular => (map.width(), map.height()),
+                Layout::Cubemap => (4 * map.height(), 2 * map.height()),
+            },
+            _ => (TABLE_WIDTH, TABLE_HEIGHT),
+        };
+
+        let table = (0..width * height)
+            .map(|i| {
+                let (u, v) = (
+                    ((i % width) as Float + 0.5) / width as Float,
+                    ((i / width) as Float + 0.5) / height as Float,
+                );
+                color::luminance(&background.radiance(&table_direction(u, v).0))
+            })
+            .collect::<Vec<_>>();
+        // brightest neighbour, as bilinear lookups spread texels half a texel further
+        let mut weights = (0..width * height)
+            .map(|i| {
+                let (x, y) = (i % width, i / width);
+                let brightest = (y.saturating_sub(1)..(y + 2).min(height))
+                    .flat_map(|ny| [width - 1, 0, 1].map(|dx| table[ny * width + (x + dx) % width]))
+                    .fold(0., Float::max);
+                let sin_theta = (consts::PI * (y as Float + 0.5) / height as Float).sin();
+                brightest * sin_theta
+            })
+            .collect::<Vec<_>>();
+        // keeps every direction possible
+        let floor = weights.iter().sum::<Float>() / weights.len() as Float * 1e-3;
+        weights
+            .iter_mut()
+            .for_each(|w| *w = w.max(0.) + floor.max(Float::MIN_POSITIVE));
+
+        Self {
+            background,
+            rotation: nalgebra::UnitQuaternion::identity(),
+            distribution: Distribution2d::new(&weights, width, height),
+        }
+    }
+
+    /// orientation of the background in the world, e.g. a heading around down for a map
+    pub fn with_rotation(mut self, rotation: nalgebra::UnitQuaternion<Float>) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn background(&self) -> &Background {
+        &self.background
+    }
+
+    pub fn rotation(&self) -> &nalgebra::UnitQuaternion<Float> {
+        &self.rotation
+    }
+
+    /// radiance arriving from a world direction, e.g. along a ray that missed everything
+    pub fn radiance(&self, direction: &nalgebra::UnitVector3<Float>) -> nalgebra::Vector3<Float> {
+        self.background
+            .radiance(&(self.rotation.inverse() * direction))
+    }
+
+    /// Picks a direction by brightness.
+    ///
+    /// # Arguments
+    /// - `sample`: uniform in [0, 1)²
+    pub fn sample(&self, sample: &nalgebra::Vector2<Float>) -> Option<EnvironmentSample> {
+        let (uv, pdf) = self.distribution.sample(sample);
+        let (local, sin_theta) = table_direction(uv.x, uv.y);
+        if pdf == 0. || sin_theta == 0. {
+            return None;
+        }
+        Some(EnvironmentSample {
+            direction: self.rotation * local,
+            radiance: self.background.radiance(&local),
+            pdf: pdf / (2. * consts::PI * consts::PI * sin_theta),
+        })
+    }
+
+    /// solid angle density of [`Environment::sample`] picking a world direction
+    pub fn pdf(&self, direction: &nalgebra::UnitVector3<Float>) -> Float {
+        let local = self.rotation.inverse() * direction;
+        let sin_theta = local.x.hypot(local.y);
+        if sin_theta == 0. {
+            return 0.;
+        }
+        let u = (local.y.atan2(local.x) / (2. * consts::PI)).rem_euclid(1.);
+        let v = (-local.z).clamp(-1., 1.).acos() / consts::PI;
+        self.distribution.pdf(&nalgebra::Vector2::new(u, v))
+            / (2. * consts::PI * consts::PI * sin_theta)
+    }
+
+    pub(crate) fn light_sample(
+        &self,
+        sample: &nalgebra::Vector2<Float>,
+    ) -> Option<light::LightSample> {
+        let sample = self.sample(sample)?;
+        Some(light::LightSample {
+            direction: sample.direction,
+            distance: Float::INFINITY,
+            irradiance: sample.radiance / sample.pdf,
+            pdf: Some(sample.pdf),
+        })
+    }
+}
+
+/// Direction of a point of the sampling table, azimuth along u and angle
+/// from the zenith along v, with the sine of that angle.
+fn table_direction(u: Float, v: Float) -> (nalgebra::UnitVector3<Float>, Float) {
+    let (sin_phi, cos_phi) = (2. * consts::PI * u).sin_cos();
+    let (sin_theta, cos_theta) = (consts::PI * v).sin_cos();
+    (
+        nalgebra::UnitVector3::new_unchecked(nalgebra::Vector3::new(
+            sin_theta * cos_phi,
+            sin_theta * sin_phi,
+            -cos_theta,
+        )),
+        sin_theta,
+    )
+}
+
+/// Piecewise constant density over [0, 1)
+struct Distribution1d {
+    weights: Vec<Float>,
+    // running total, normalized to end at 1
+    cdf: Vec<Float>,
+    total: Float,
+}
+
+impl Distribution1d {
+    fn new(weights: &[Float]) -> Self {
+        let total = weights.iter().sum::<Float>();
+        let cdf = weights
+            .iter()
+            .scan(0., |sum, w| {
+                *sum += w;
+                Some(*sum / total)
+            })
+            .collect();
+        Self {
+            weights: weights.to_vec(),
+            cdf,
+            total,
+        }
+    }
+
+    /// (value in [0, 1), density, bin)
+    fn sample(&self, u: Float) -> (Float, Float, usize) {
+        let bin = self
+            .cdf
+            .partition_point(|c| *c <= u)
+            .min(self.weights.len() - 1);
+        let start = bin.checked_sub(1).map_or(0., |i| self.cdf[i]);
+        let width = self.cdf[bin] - start;
+        let offset = match width > 0. {
+            true => ((u - start) / width).clamp(0., 1.),
+            false => 0.5,
+        };
+        (
+            ((bin as Float + offset) / self.weights.len() as Float).min(1. - Float::EPSILON),
+            self.density(bin),
+            bin,
+        )
+    }
+
+    fn density(&self, bin: usize) -> Float {
+        self.weights[bin] * self.weights.len() as Float / self.total
+    }
+
+    fn bin(&self, x: Float) -> usize {
+        ((x * self.weights.len() as Float) as usize).min(self.weights.len() - 1)
+    }
+}
+
+/// Piecewise constant density over [0, 1)², a row by its total then a column within it
+struct Distribution2d {
+    rows: Vec<Distribution1d>,
+    marginal: Distribution1d,
+}
+
+impl Distribution2d {
+    fn new(weights: &[Float], width: usize, height: usize) -> Self {
+        let rows = weights
+            .chunks_exact(width)
+            .map(Distribution1d::new)
+            .collect::<Vec<_>>();
+        let marginal = Distribution1d::new(&rows.iter().map(|r| r.total).collect::<Vec<_>>());
+        debug_assert_eq!(rows.len(), height, "weights do not match table size");
+        Self { rows, marginal }
+    }
+
+    fn sample(&self, sample: &nalgebra::Vector2<Float>) -> (nalgebra::Vector2<Float>, Float) {
+        let (v, pdf_v, row) = self.marginal.sample(sample.y);
+        let (u, pdf_u, _) = self.rows[row].sample(sample.x);
+        (nalgebra::Vector2::new(u, v), pdf_u * pdf_v)
+    }
+
+    fn pdf(&self, uv: &nalgebra::Vector2<Float>) -> Float {
+        let row = self.marginal.bin(uv.y);
+        let row_distribution = &self.rows[row];
+        self.marginal.density(row) * row_distribution.density(row_distribution.bin(uv.x))
+    }
+}
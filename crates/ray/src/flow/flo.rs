@@ -0,0 +1,71 @@
+//! [Middlebury .flo](https://vision.middlebury.edu/flow/code/flow-code/README.txt) export
+
+use std::io;
+
+use crate::Float;
+
+// "PIEH" as a little endian float, checks the byte order
+const MAGIC: f32 = 202021.25;
+// readers treat anything above 1e9 as unknown
+const UNKNOWN: f32 = 1e10;
+
+/// Writes optical flow (u, v) in pixels, row major; `None` pixels are unknown.
+pub fn write(
+    width: u32,
+    height: u32,
+    flow: &[Option<nalgebra::Vector2<Float>>],
+    writer: &mut impl io::Write,
+) -> io::Result<()> {
+    debug_assert_eq!(
+        flow.len(),
+        (width * height) as usize,
+        "flow does not match image size"
+    );
+
+    writer.write_all(&MAGIC.to_le_bytes())?;
+    writer.write_all(&(width as i32).to_le_bytes())?;
+    writer.write_all(&(height as i32).to_le_bytes())?;
+    for uv in flow {
+        let [u, v] = uv.map_or([UNKNOWN; 2], |uv| uv.cast::<f32>().into());
+        writer.write_all(&u.to_le_bytes())?;
+        writer.write_all(&v.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let flow = [
+            Some(nalgebra::Vector2::new(1.5, -2.)),
+            None,
+            Some(nalgebra::Vector2::new(0., 0.25)),
+            Some(nalgebra::Vector2::new(-3., 4.)),
+            None,
+            Some(nalgebra::Vector2::new(10., -0.5)),
+        ];
+        let mut bytes = Vec::new();
+        write(3, 2, &flow, &mut bytes).unwrap();
+
+        assert_eq!(&bytes[..4], b"PIEH");
+        assert_eq!(bytes.len(), 12 + flow.len() * 8);
+        let values = bytes
+            .chunks_exact(4)
+            .map(|b| [b[0], b[1], b[2], b[3]])
+            .collect::<Vec<_>>();
+        assert_eq!(i32::from_le_bytes(values[1]), 3);
+        assert_eq!(i32::from_le_bytes(values[2]), 2);
+
+        let read = values[3..]
+            .chunks_exact(2)
+            .map(|uv| {
+                let (u, v) = (f32::from_le_bytes(uv[0]), f32::from_le_bytes(uv[1]));
+                (u <= 1e9 && v <= 1e9).then(|| nalgebra::Vector2::new(u, v).cast::<Float>())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(read, flow);
+    }
+}
@@ -0,0 +1,128 @@
+//! Ground truth motion between two frames of an animation.
+//!
+//! Each pixel of the first frame remembers which object it hit and where in
+//! that object's own coordinates ([`surface_points`]). Moving those points
+//! with the object poses of both frames gives the scene flow, projecting
+//! them into the second frame gives the optical flow ([`flow`]).
+
+use crate::Float;
+use crate::motion;
+use crate::projection;
+use crate::ray;
+
+pub mod flo;
+pub mod pfm;
+
+/// Camera and object poses at one instant, all to world
+#[derive(Clone, Debug)]
+pub struct Frame {
+    pub camera: nalgebra::Isometry3<Float>,
+    /// indexed like the objects returned by the hit function of [`surface_points`]
+    pub objects: Vec<nalgebra::Isometry3<Float>>,
+}
+
+impl Frame {
+    pub fn new(
+        camera: nalgebra::Isometry3<Float>,
+        objects: Vec<nalgebra::Isometry3<Float>>,
+    ) -> Self {
+        Self { camera, objects }
+    }
+
+    /// poses of moving cameras and objects at `time`
+    pub fn at_time(
+        camera: &motion::Trajectory,
+        objects: &[&motion::Trajectory],
+        time: Float,
+    ) -> Self {
+        Self {
+            camera: camera.pose_at(time),
+            objects: objects.iter().map(|o| o.pose_at(time)).collect(),
+        }
+    }
+}
+
+/// A point on an object, in the object's coordinates
+#[derive(Copy, Clone, Debug)]
+pub struct SurfacePoint {
+    pub object: usize,
+    pub point: nalgebra::Vector3<Float>,
+}
+
+/// Motion of a pixel's surface point from one frame to the next
+#[derive(Copy, Clone, Debug)]
+pub struct Flow {
+    /// pixel displacement in the image
+    pub optical: nalgebra::Vector2<Float>,
+    /// displacement in the world
+    pub scene: nalgebra::Vector3<Float>,
+    /// false when the point is occluded or out of view in the next frame
+    pub visible: bool,
+}
+
+/// Surface point seen by every pixel, row major.
+///
+/// # Arguments
+/// - `hit`: object index and distance along a world ray to the closest hit
+pub fn surface_points<P: projection::Projection>(
+    projection: &P,
+    frame: &Frame,
+    hit: impl Fn(&ray::Ray) -> Option<(usize, Float)>,
+) -> Vec<Option<SurfacePoint>> {
+    projection::pixel_rays(projection)
+        .map(|ray| {
+            let ray = ray?.transform(&frame.camera);
+            let (object, distance) = hit(&ray)?;
+            let point = frame.objects[object].inverse_transform_point(&ray.at(distance).into());
+            Some(SurfacePoint {
+                object,
+                point: point.coords,
+            })
+        })
+        .collect()
+}
+
+/// Flow of every pixel's surface point from frame `from` to frame `to`, row major.
+///
+/// # Arguments
+/// - `points`: from [`surface_points`] in the `from` frame
+/// - `trace_to`: distance along a world ray to the closest hit in the `to` frame, for occlusion
+pub fn flow<P: projection::Projection>(
+    projection: &P,
+    from: &Frame,
+    to: &Frame,
+    points: &[Option<SurfacePoint>],
+    trace_to: impl Fn(&ray::Ray) -> Option<Float>,
+) -> Vec<Option<Flow>> {
+    let width = projection.width_px();
+    let (w, h) = (width as Float, projection.height_px() as Float);
+    points
+        .iter()
+        .enumerate()
+        .map(|(i, point)| {
+            let SurfacePoint { object, point } = (*point)?;
+            let start = from.objects[object].transform_point(&point.into()).coords;
+            let end = to.objects[object].transform_point(&point.into()).coords;
+            let pixel = projection.world_to_pixel(&to.camera, &end)?;
+
+            let in_view = (0.0..w).contains(&pixel.x) && (0.0..h).contains(&pixel.y);
+            // occluded when something else is hit first along the ray through the new pixel
+            let visible = in_view
+                && projection
+                    .pixel_ray(pixel.x, pixel.y)
+                    .map(|ray| ray.transform(&to.camera))
+                    .is_some_and(|ray| {
+                        let distance = (end - ray.origin()).dot(ray.direction());
+                        trace_to(&ray).is_none_or(|t| t >= distance * (1. - 1e-4))
+                    });
+
+            let x = (i as u32 % width) as Float;
+            let y = (i as u32 / width) as Float;
+            Some(Flow {
+                optical: pixel - nalgebra::Vector2::new(x, y),
+                scene: end - start,
+                visible,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,101 @@
+//! [Portable float map](https://www.pauldebevec.com/Research/HDR/PFM/) export,
+//! for scene flow, depth and disparity
+
+use std::io;
+
+use crate::Float;
+
+/// Writes a 3 channel map, row major from the top; `None` pixels are infinite.
+pub fn write(
+    width: u32,
+    height: u32,
+    pixels: &[Option<nalgebra::Vector3<Float>>],
+    writer: &mut impl io::Write,
+) -> io::Result<()> {
+    write_channels(width, height, "PF", pixels, writer, |pixel| {
+        pixel
+            .map_or([f32::INFINITY; 3], |p| p.cast::<f32>().into())
+            .to_vec()
+    })
+}
+
+/// Writes a single channel map, row major from the top; `None` pixels are infinite.
+pub fn write_mono(
+    width: u32,
+    height: u32,
+    pixels: &[Option<Float>],
+    writer: &mut impl io::Write,
+) -> io::Result<()> {
+    write_channels(width, height, "Pf", pixels, writer, |pixel| {
+        vec![pixel.map_or(f32::INFINITY, nalgebra::convert::<Float, f32>)]
+    })
+}
+
+fn write_channels<T>(
+    width: u32,
+    height: u32,
+    identifier: &str,
+    pixels: &[T],
+    writer: &mut impl io::Write,
+    channels: impl Fn(&T) -> Vec<f32>,
+) -> io::Result<()> {
+    debug_assert_eq!(
+        pixels.len(),
+        (width * height) as usize,
+        "pixels do not match image size"
+    );
+
+    // a negative scale marks little endian data
+    write!(writer, "{identifier}\n{width} {height}\n-1.0\n")?;
+    // rows are stored bottom to top
+    for row in pixels.chunks(width as usize).rev() {
+        for value in row.iter().flat_map(&channels) {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::hdr;
+
+    #[test]
+    fn round_trip() {
+        let pixels = [
+            Some(nalgebra::Vector3::new(1., 2., 3.)),
+            Some(nalgebra::Vector3::new(4., 5., 6.)),
+            Some(nalgebra::Vector3::new(-1., -2., -3.)),
+            None,
+        ];
+        let mut bytes = Vec::new();
+        write(2, 2, &pixels, &mut bytes).unwrap();
+
+        assert!(bytes.starts_with(b"PF\n2 2\n-1.0\n"));
+        // the bottom row comes first
+        let first = f32::from_le_bytes(bytes[12..16].try_into().unwrap());
+        assert_eq!(first, -1.);
+
+        let image = hdr::read(&mut bytes.as_slice()).unwrap();
+        assert_eq!((image.width, image.height), (2, 2));
+        assert_eq!(
+            &image.pixels[..3],
+            &pixels[..3].iter().flatten().copied().collect::<Vec<_>>()
+        );
+        assert_eq!(image.pixels[3], nalgebra::Vector3::repeat(Float::INFINITY));
+    }
+
+    #[test]
+    fn mono_round_trip() {
+        let pixels = [Some(0.5), Some(1.), Some(2.), Some(4.), None, Some(8.)];
+        let mut bytes = Vec::new();
+        write_mono(3, 2, &pixels, &mut bytes).unwrap();
+
+        assert!(bytes.starts_with(b"Pf\n3 2\n-1.0\n"));
+        let image = hdr::read(&mut bytes.as_slice()).unwrap();
+        assert_eq!((image.width, image.height), (3, 2));
+        let read = image.pixels.iter().map(|p| p.x).collect::<Vec<_>>();
+        assert_eq!(read, [0.5, 1., 2., 4., Float::INFINITY, 8.]);
+    }
+}
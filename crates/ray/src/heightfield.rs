@@ -0,0 +1,129 @@
+//! Terrain as a regular grid of elevations, in north-east-down.
+
+use crate::Float;
+use crate::aabb;
+use crate::shapes::triangle;
+
+/// Elevations on posts spaced evenly along north (rows) and east (columns),
+/// the surface between them being the triangles of [`Heightfield::triangles`].
+pub struct Heightfield {
+    // north, east of the first post
+    origin: nalgebra::Vector2<Float>,
+    spacing: Float,
+    columns: usize,
+    // row major, up from z = 0
+    elevations: Vec<Float>,
+}
+
+impl Heightfield {
+    /// # Arguments
+    /// - `origin`: north and east of the first post
+    /// - `spacing`: between neighbouring posts, in meters
+    /// - `columns`: posts per row, rows run east and follow each other north
+    /// - `elevations`: up, i.e. -z, row by row
+    pub fn new(
+        origin: nalgebra::Vector2<Float>,
+        spacing: Float,
+        columns: usize,
+        elevations: Vec<Float>,
+    ) -> Self {
+        debug_assert!(spacing > 0., "spacing can not be <= 0.0");
+        debug_assert!(columns >= 2, "columns can not be < 2");
+        debug_assert!(
+            elevations.len().is_multiple_of(columns) && elevations.len() / columns >= 2,
+            "elevations must fill at least two rows"
+        );
+        Self {
+            origin,
+            spacing,
+            columns,
+            elevations,
+        }
+    }
+
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    pub fn rows(&self) -> usize {
+        self.elevations.len() / self.columns
+    }
+
+    pub fn spacing(&self) -> Float {
+        self.spacing
+    }
+
+    pub fn elevation(&self, column: usize, row: usize) -> Float {
+        self.elevations[row * self.columns + column]
+    }
+
+    /// position of a post
+    pub fn point(&self, column: usize, row: usize) -> nalgebra::Vector3<Float> {
+        nalgebra::Vector3::new(
+            self.origin.x + row as Float * self.spacing,
+            self.origin.y + column as Float * self.spacing,
+            -self.elevation(column, row),
+        )
+    }
+
+    /// Elevation anywhere on the surface, held at the edge beyond it.
+    ///
+    /// Interpolated on the same triangles [`Heightfield::triangles`] makes.
+    pub fn elevation_at(&self, north: Float, east: Float) -> Float {
+        let row = ((north - self.origin.x) / self.spacing).clamp(0., (self.rows() - 1) as Float);
+        let column = ((east - self.origin.y) / self.spacing).clamp(0., (self.columns - 1) as Float);
+        let (r, c) = (
+            (row.floor() as usize).min(self.rows() - 2),
+            (column.floor() as usize).min(self.columns - 2),
+        );
+        let (fr, fc) = (row - r as Float, column - c as Float);
+
+        let h00 = self.elevation(c, r);
+        let h01 = self.elevation(c + 1, r);
+        let h10 = self.elevation(c, r + 1);
+        let h11 = self.elevation(c + 1, r + 1);
+        // each cell splits along its diagonal from (c, r) to (c + 1, r + 1)
+        match fc >= fr {
+            true => h00 + fc * (h01 - h00) + fr * (h11 - h01),
+            false => h00 + fr * (h10 - h00) + fc * (h11 - h10),
+        }
+    }
+
+    /// surface point below or above a position
+    pub fn surface_at(&self, north: Float, east: Float) -> nalgebra::Vector3<Float> {
+        nalgebra::Vector3::new(north, east, -self.elevation_at(north, east))
+    }
+
+    /// two triangles per cell between posts, facing up
+    pub fn triangles(&self) -> Vec<triangle::Triangle> {
+        (0..self.rows() - 1)
+            .flat_map(|r| (0..self.columns - 1).map(move |c| (c, r)))
+            .flat_map(|(c, r)| {
+                // east x north is up in north-east-down
+                triangle::quad_to_triangles(
+                    self.point(c, r),
+                    self.point(c + 1, r),
+                    self.point(c + 1, r + 1),
+                    self.point(c, r + 1),
+                )
+            })
+            .collect()
+    }
+
+    pub fn bounds(&self) -> aabb::Aabb {
+        let (low, high) = self
+            .elevations
+            .iter()
+            .fold((Float::INFINITY, Float::NEG_INFINITY), |(low, high), e| {
+                (low.min(*e), high.max(*e))
+            });
+        aabb::Aabb::new(
+            nalgebra::Vector3::new(self.origin.x, self.origin.y, -high),
+            nalgebra::Vector3::new(
+                self.origin.x + (self.rows() - 1) as Float * self.spacing,
+                self.origin.y + (self.columns - 1) as Float * self.spacing,
+                -low,
+            ),
+        )
+    }
+}
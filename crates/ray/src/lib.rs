@@ -2,32 +2,98 @@ use angle::Angle;
 use ray::Ray;
 use shapes::triangle::Triangle;
 
+pub mod aabb;
 pub mod angle;
+pub mod annotation;
+pub mod bvh;
+pub mod calibration;
 pub mod camera;
+pub mod color;
 pub mod distance;
+pub mod environment;
+pub mod flow;
+pub mod heightfield;
+pub mod light;
+pub mod material;
+pub mod medium;
+pub mod motion;
 pub mod object;
+pub mod packet;
+pub mod pointcloud;
+pub mod polarization;
+pub mod projection;
+pub mod random;
 pub mod ray;
+pub mod rig;
+pub mod sensor;
 pub mod shapes;
+pub mod sky;
+pub mod solar;
+pub mod spectral;
+pub mod texture;
+pub mod visibility;
 
-pub fn ray_mesh_intersections(
-    mesh: &[Triangle],
-    ray: &Ray,
-    epsilon: f32,
-) -> impl Iterator<Item = f32> {
-    mesh.iter()
-        .filter_map(move |triangle| triangle.ray_intersection(ray, epsilon))
+/// Floating point type of everything that is not generic over [`Real`].
+///
+/// Rays, shapes, cameras, distances and angles default to it as well.
+pub type Float = f32;
+
+pub use std::f32::consts;
+
+/// Floating point types rays, shapes, cameras, distances and angles are
+/// generic over, `f64` for long range scenes where `f32` runs out of
+/// precision.
+pub trait Real: nalgebra::RealField + Copy {
+    const INFINITY: Self;
+    const NEG_INFINITY: Self;
+
+    /// the float `ulps` representable values further from 0, closer for negative `ulps`
+    fn add_ulps(self, ulps: i64) -> Self;
+
+    fn total_cmp(&self, other: &Self) -> std::cmp::Ordering;
+
+    fn is_nan(self) -> bool;
 }
 
-#[inline(always)]
-pub(crate) fn is_zero(float: f32, epsilon: f32) -> bool {
-    debug_assert!(epsilon > 0.0, "epsilon can not be negative");
-    float.abs() <= epsilon
+macro_rules! impl_real {
+    ($float:ty) => {
+        impl Real for $float {
+            const INFINITY: Self = <$float>::INFINITY;
+            const NEG_INFINITY: Self = <$float>::NEG_INFINITY;
+
+            #[inline(always)]
+            fn add_ulps(self, ulps: i64) -> Self {
+                <$float>::from_bits(self.to_bits().wrapping_add_signed(ulps as _))
+            }
+
+            #[inline(always)]
+            fn total_cmp(&self, other: &Self) -> std::cmp::Ordering {
+                <$float>::total_cmp(self, other)
+            }
+
+            #[inline(always)]
+            fn is_nan(self) -> bool {
+                <$float>::is_nan(self)
+            }
+        }
+    };
+}
+
+impl_real!(f32);
+impl_real!(f64);
+
+pub fn ray_mesh_intersections<T: Real>(
+    mesh: &[Triangle<T>],
+    ray: &Ray<T>,
+) -> impl Iterator<Item = T> {
+    mesh.iter()
+        .filter_map(move |triangle| triangle.ray_intersection(ray))
 }
 
 pub fn reflection(
-    direction: &nalgebra::Vector3<f32>,
-    norm: &nalgebra::UnitVector3<f32>,
-) -> nalgebra::Vector3<f32> {
+    direction: &nalgebra::Vector3<Float>,
+    norm: &nalgebra::UnitVector3<Float>,
+) -> nalgebra::Vector3<Float> {
     let perpendicular_component = direction.dot(norm) * norm.into_inner();
     direction - 2. * perpendicular_component
 }
@@ -40,9 +106,9 @@ pub fn reflection(
 pub fn reflectance_s_polarized(
     incident_angle: Angle,
     transmitted_angle: Angle,
-    incident_refractive_index: f32,
-    transmitted_refractive_index: f32,
-) -> f32 {
+    incident_refractive_index: Float,
+    transmitted_refractive_index: Float,
+) -> Float {
     let a = incident_refractive_index * incident_angle.cos();
     let b = transmitted_refractive_index * transmitted_angle.cos();
 
@@ -57,11 +123,68 @@ pub fn reflectance_s_polarized(
 pub fn reflectance_p_polarized(
     incident_angle: Angle,
     transmitted_angle: Angle,
-    incident_refractive_index: f32,
-    transmitted_refractive_index: f32,
-) -> f32 {
+    incident_refractive_index: Float,
+    transmitted_refractive_index: Float,
+) -> Float {
     let a = incident_refractive_index * transmitted_angle.cos();
     let b = transmitted_refractive_index * incident_angle.cos();
 
     ((a - b) / (a + b)).powi(2)
 }
+
+/// Snell's law, the angle of the transmitted light, `None` on total internal reflection
+///
+/// Angles are measured as in [`reflectance_s_polarized`].
+pub fn transmitted_angle(
+    incident_angle: Angle,
+    incident_refractive_index: Float,
+    transmitted_refractive_index: Float,
+) -> Option<Angle> {
+    let sin_t = incident_refractive_index * incident_angle.sin() / transmitted_refractive_index;
+    (sin_t.abs() <= 1.).then(|| Angle::from_rads(sin_t.asin()))
+}
+
+/// Fresnel reflection amplitude coefficients (r_s, r_p) for light arriving
+/// from a medium of index 1 onto a surface of complex `refractive_index`
+/// (n + ik), assuming non-magnetic materials.
+///
+/// # Arguments
+/// - `cos_incident`: cosine of the angle between the incident ray and the normal, [0, 1]
+pub fn fresnel_amplitudes(
+    cos_incident: Float,
+    refractive_index: nalgebra::Complex<Float>,
+) -> (nalgebra::Complex<Float>, nalgebra::Complex<Float>) {
+    let cos_i = nalgebra::Complex::from(cos_incident.clamp(0., 1.));
+    let sin2_i = nalgebra::Complex::from(1. - cos_incident * cos_incident);
+    let sin2_t = sin2_i / (refractive_index * refractive_index);
+    let cos_t = (nalgebra::Complex::from(1.) - sin2_t).sqrt();
+
+    let s = (cos_i - refractive_index * cos_t) / (cos_i + refractive_index * cos_t);
+    let p = (refractive_index * cos_i - cos_t) / (refractive_index * cos_i + cos_t);
+    (s, p)
+}
+
+/// Unpolarized Fresnel reflectance at an interface between two media.
+///
+/// Handles total internal reflection for dielectrics (`extinction_coefficient`
+/// of 0) when `cos_incident` is negative, i.e. arriving from inside.
+///
+/// # Arguments
+/// - `cos_incident`: cosine of the angle between the incident ray and the normal
+/// - `refractive_index`: ratio of transmitted to incident refractive index
+pub fn fresnel_reflectance(
+    cos_incident: Float,
+    refractive_index: Float,
+    extinction_coefficient: Float,
+) -> Float {
+    let (cos_incident, refractive_index) = match cos_incident < 0. && extinction_coefficient == 0. {
+        true => (-cos_incident, 1. / refractive_index),
+        false => (cos_incident.abs(), refractive_index),
+    };
+
+    let (s, p) = fresnel_amplitudes(
+        cos_incident,
+        nalgebra::Complex::new(refractive_index, extinction_coefficient),
+    );
+    0.5 * (s.norm_sqr() + p.norm_sqr())
+}
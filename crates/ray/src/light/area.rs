@@ -0,0 +1,161 @@
+//! Lights with a surface of constant radiance.
+
+use crate::Float;
+use crate::consts;
+use crate::shapes;
+
+#[derive(Copy, Clone, Debug)]
+pub enum AreaShape {
+    /// parallelogram `center ± u ± v`, emitting along u x v
+    Rectangle {
+        center: nalgebra::Vector3<Float>,
+        u: nalgebra::Vector3<Float>,
+        v: nalgebra::Vector3<Float>,
+    },
+    /// emitting along the normal
+    Disk {
+        center: nalgebra::Vector3<Float>,
+        normal: nalgebra::UnitVector3<Float>,
+        radius: Float,
+    },
+    /// emitting outwards
+    Sphere {
+        center: nalgebra::Vector3<Float>,
+        radius: Float,
+    },
+}
+
+impl AreaShape {
+    pub fn area(&self) -> Float {
+        match self {
+            AreaShape::Rectangle { u, v, .. } => 4. * u.cross(v).norm(),
+            AreaShape::Disk { radius, .. } => consts::PI * radius * radius,
+            AreaShape::Sphere { radius, .. } => 4. * consts::PI * radius * radius,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct AreaLight {
+    shape: AreaShape,
+    // W/(sr m²) per channel
+    radiance: nalgebra::Vector3<Float>,
+    two_sided: bool,
+}
+
+impl AreaLight {
+    /// # Arguments
+    /// - `radiance`: W/(sr m²) per channel, the same across the surface and in every direction
+    pub fn new(shape: AreaShape, radiance: nalgebra::Vector3<Float>) -> Self {
+        if let AreaShape::Disk { radius, .. } | AreaShape::Sphere { radius, .. } = shape {
+            debug_assert!(radius > 0., "radius can not be <= 0.0");
+        }
+        Self {
+            shape,
+            radiance,
+            two_sided: false,
+        }
+    }
+
+    /// rectangles and disks emitting from both sides
+    pub fn with_two_sided(mut self, two_sided: bool) -> Self {
+        self.two_sided = two_sided;
+        self
+    }
+
+    pub fn shape(&self) -> &AreaShape {
+        &self.shape
+    }
+
+    pub fn radiance(&self) -> &nalgebra::Vector3<Float> {
+        &self.radiance
+    }
+
+    pub fn is_two_sided(&self) -> bool {
+        self.two_sided
+    }
+
+    /// emitted power, W per channel
+    pub fn power(&self) -> nalgebra::Vector3<Float> {
+        let sides = if self.two_sided { 2. } else { 1. };
+        self.radiance * consts::PI * self.shape.area() * sides
+    }
+
+    pub(super) fn sample(
+        &self,
+        point: &nalgebra::Vector3<Float>,
+        sample: &nalgebra::Vector2<Float>,
+    ) -> Option<super::LightSample> {
+        let (on_light, normal) = match self.shape {
+            AreaShape::Rectangle { center, u, v } => (
+                center + u * (2. * sample.x - 1.) + v * (2. * sample.y - 1.),
+                nalgebra::UnitVector3::new_normalize(u.cross(&v)),
+            ),
+            AreaShape::Disk {
+                center,
+                normal,
+                radius,
+            } => {
+                let tangent = shapes::perpendicular(&normal);
+                let bitangent = normal.cross(&tangent);
+                let r = radius * sample.x.sqrt();
+                let (sin, cos) = (2. * consts::PI * sample.y).sin_cos();
+                (
+                    center + (tangent.into_inner() * cos + bitangent * sin) * r,
+                    normal,
+                )
+            }
+            AreaShape::Sphere { center, radius } => {
+                return self.sample_sphere(point, &center, radius, sample);
+            }
+        };
+        super::sample_surface_point(
+            point,
+            &on_light,
+            &normal,
+            self.shape.area(),
+            self.two_sided,
+            &self.radiance,
+        )
+    }
+
+    /// samples the cone the sphere covers, nothing reaches points inside
+    fn sample_sphere(
+        &self,
+        point: &nalgebra::Vector3<Float>,
+        center: &nalgebra::Vector3<Float>,
+        radius: Float,
+        sample: &nalgebra::Vector2<Float>,
+    ) -> Option<super::LightSample> {
+        let (axis, center_distance) =
+            nalgebra::UnitVector3::try_new_and_get(center - point, Float::EPSILON)?;
+        if center_distance <= radius {
+            return None;
+        }
+
+        let sin2_max = (radius / center_distance).powi(2);
+        let cos_max = (1. - sin2_max).sqrt();
+        let cos = 1. - sample.x * (1. - cos_max);
+        let sin = (1. - cos * cos).max(0.).sqrt();
+        let (sin_phi, cos_phi) = (2. * consts::PI * sample.y).sin_cos();
+
+        let tangent = shapes::perpendicular(&axis);
+        let bitangent = axis.cross(&tangent);
+        let direction = nalgebra::UnitVector3::new_normalize(
+            axis.into_inner() * cos + (tangent.into_inner() * cos_phi + bitangent * sin_phi) * sin,
+        );
+        // nearest intersection along the sampled direction
+        let distance = center_distance * cos
+            - (radius * radius - center_distance * center_distance * sin * sin)
+                .max(0.)
+                .sqrt();
+
+        let solid_angle = 2. * consts::PI * (1. - cos_max);
+        Some(super::LightSample {
+            direction,
+            distance,
+            irradiance: self.radiance * solid_angle,
+            pdf: Some(1. / solid_angle),
+        })
+    }
+}
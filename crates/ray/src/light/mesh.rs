@@ -0,0 +1,112 @@
+//! Emissive triangle meshes as lights.
+
+use crate::Float;
+use crate::material;
+use crate::shapes::composite;
+use crate::shapes::triangle;
+
+/// Emits from the front (right hand rule) of every triangle of a mesh
+#[derive(Clone, Debug)]
+pub struct MeshLight {
+    mesh: Vec<triangle::Triangle>,
+    // running total of the triangle areas, to pick triangles by area
+    cumulative_area: Vec<Float>,
+    // W/(sr m²) per channel
+    radiance: nalgebra::Vector3<Float>,
+    two_sided: bool,
+}
+
+impl MeshLight {
+    /// # Arguments
+    /// - `radiance`: W/(sr m²) per channel
+    pub fn new(object: &composite::CompositeObject, radiance: nalgebra::Vector3<Float>) -> Self {
+        let mesh = object.mesh().to_vec();
+        let cumulative_area = mesh
+            .iter()
+            .scan(0., |total, t| {
+                *total += t.area();
+                Some(*total)
+            })
+            .collect();
+        Self {
+            mesh,
+            cumulative_area,
+            radiance,
+            two_sided: false,
+        }
+    }
+
+    /// light from the emission of the object's material, `None` when it does not emit
+    pub fn from_material(
+        object: &composite::CompositeObject,
+        materials: &material::MaterialLibrary,
+    ) -> Option<Self> {
+        let material = materials.get(object.material())?;
+        material
+            .is_emissive()
+            .then(|| Self::new(object, *material.emission()))
+    }
+
+    /// emitting from both sides of the triangles
+    pub fn with_two_sided(mut self, two_sided: bool) -> Self {
+        self.two_sided = two_sided;
+        self
+    }
+
+    pub fn radiance(&self) -> &nalgebra::Vector3<Float> {
+        &self.radiance
+    }
+
+    pub fn is_two_sided(&self) -> bool {
+        self.two_sided
+    }
+
+    pub fn area(&self) -> Float {
+        self.cumulative_area.last().copied().unwrap_or(0.)
+    }
+
+    /// emitted power, W per channel
+    pub fn power(&self) -> nalgebra::Vector3<Float> {
+        let sides = if self.two_sided { 2. } else { 1. };
+        self.radiance * crate::consts::PI * self.area() * sides
+    }
+
+    pub(super) fn sample(
+        &self,
+        point: &nalgebra::Vector3<Float>,
+        sample: &nalgebra::Vector2<Float>,
+    ) -> Option<super::LightSample> {
+        let area = self.area();
+        if area == 0. {
+            return None;
+        }
+
+        // pick a triangle by area, reusing the rest of sample.x within it
+        let target = sample.x * area;
+        let index = self
+            .cumulative_area
+            .partition_point(|total| *total <= target)
+            .min(self.mesh.len() - 1);
+        let start = index.checked_sub(1).map_or(0., |i| self.cumulative_area[i]);
+        let width = self.cumulative_area[index] - start;
+        let u = match width > 0. {
+            true => ((target - start) / width).clamp(0., 1.),
+            false => 0.,
+        };
+
+        // uniform on the triangle
+        let t = &self.mesh[index];
+        let r = u.sqrt();
+        let (wb, wc) = (r * (1. - sample.y), r * sample.y);
+        let on_light = t.a() * (1. - wb - wc) + t.b() * wb + t.c() * wc;
+
+        super::sample_surface_point(
+            point,
+            &on_light,
+            &t.normal(),
+            area,
+            self.two_sided,
+            &self.radiance,
+        )
+    }
+}
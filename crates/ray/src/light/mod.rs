@@ -0,0 +1,417 @@
+//! Light sources in radiometric units, sampled for direct lighting.
+//!
+//! Point and spot lights have an intensity in W/sr, directional lights an
+//! irradiance in W/m² and area lights a radiance in W/(sr m²), all per
+//! linear RGB channel. Sampling a light from a surface point gives the
+//! irradiance it contributes along one direction, checked for occluders
+//! with a shadow ray.
+
+use crate::Float;
+use crate::angle;
+use crate::environment;
+use crate::material;
+use crate::medium;
+use crate::random;
+use crate::ray;
+use crate::shapes;
+
+pub mod area;
+pub mod mesh;
+
+// shadow rays stop this fraction short of the light, so they do not hit its own surface
+pub(crate) const SHADOW_RAY_SHORTENING: Float = 1e-4;
+
+pub enum Light {
+    Point(PointLight),
+    Spot(SpotLight),
+    Directional(DirectionalLight),
+    Area(area::AreaLight),
+    Mesh(mesh::MeshLight),
+    /// image based lighting from the surroundings
+    Environment(environment::Environment),
+}
+
+impl Light {
+    /// Picks a point on the light as seen from `point`, `None` when the
+    /// light does not reach it.
+    ///
+    /// # Arguments
+    /// - `sample`: uniform in [0, 1)², ignored by lights without extent
+    pub fn sample(
+        &self,
+        point: &nalgebra::Vector3<Float>,
+        sample: &nalgebra::Vector2<Float>,
+    ) -> Option<LightSample> {
+        match self {
+            Light::Point(l) => l.sample(point),
+            Light::Spot(l) => l.sample(point),
+            Light::Directional(l) => Some(l.sample()),
+            Light::Area(l) => l.sample(point, sample),
+            Light::Mesh(l) => l.sample(point, sample),
+            Light::Environment(e) => e.light_sample(sample),
+        }
+    }
+
+    /// whether the light is a single point or direction, only reachable by sampling it
+    pub fn is_delta(&self) -> bool {
+        matches!(
+            self,
+            Light::Point(_) | Light::Spot(_) | Light::Directional(_)
+        )
+    }
+}
+
+/// Light arriving at a point from one sampled direction
+#[derive(Copy, Clone, Debug)]
+pub struct LightSample {
+    /// from the lit point towards the light
+    pub direction: nalgebra::UnitVector3<Float>,
+    /// to the sampled point on the light, infinite for directional lights
+    pub distance: Float,
+    /// irradiance on a surface facing the light, W/m² per channel, already
+    /// divided by the probability of the sample
+    pub irradiance: nalgebra::Vector3<Float>,
+    /// solid angle density of the sample, `None` for delta lights
+    pub pdf: Option<Float>,
+}
+
+impl LightSample {
+    /// Ray from a surface point to the light, stopping just before it.
+    ///
+    /// # Arguments
+    /// - `normal`: geometric normal at `point`, either side
+    pub fn shadow_ray(
+        &self,
+        point: &nalgebra::Vector3<Float>,
+        normal: &nalgebra::UnitVector3<Float>,
+    ) -> ray::Ray {
+        let ray = ray::Ray::spawn(point, normal, self.direction);
+        ray.with_interval(0., self.distance * (1. - SHADOW_RAY_SHORTENING))
+    }
+
+    /// whether nothing blocks the light, `occluded` being any hit query like [`shapes::Traceable::occluded`]
+    pub fn visible(
+        &self,
+        point: &nalgebra::Vector3<Float>,
+        normal: &nalgebra::UnitVector3<Float>,
+        occluded: impl Fn(&ray::Ray) -> bool,
+    ) -> bool {
+        !occluded(&self.shadow_ray(point, normal))
+    }
+}
+
+/// Radiance reflected towards `wo` from one sample of every light, checking
+/// each with a shadow ray.
+///
+/// # Arguments
+/// - `wo`: from the surface towards the viewer
+/// - `occluded`: whether anything blocks a ray within its interval
+pub fn direct_lighting(
+    lights: &[Light],
+    hit: &shapes::Hit,
+    wo: &nalgebra::UnitVector3<Float>,
+    material: &material::Material,
+    rng: &mut random::Rng,
+    occluded: impl Fn(&ray::Ray) -> bool,
+) -> nalgebra::Vector3<Float> {
+    sampled_lighting(
+        lights,
+        hit,
+        rng,
+        |ray, _| unblocked(ray, &occluded),
+        |wi| material.evaluate(&hit.normal, wo, wi),
+    )
+}
+
+/// [`direct_lighting`] of a surface inside a participating medium, the
+/// light reaching it attenuated along every shadow ray.
+///
+/// # Arguments
+/// - `wo`: from the surface towards the viewer
+/// - `occluded`: whether anything blocks a ray within its interval
+pub fn direct_lighting_in_medium(
+    lights: &[Light],
+    hit: &shapes::Hit,
+    wo: &nalgebra::UnitVector3<Float>,
+    material: &material::Material,
+    medium: &medium::Medium,
+    rng: &mut random::Rng,
+    occluded: impl Fn(&ray::Ray) -> bool,
+) -> nalgebra::Vector3<Float> {
+    sampled_lighting(
+        lights,
+        hit,
+        rng,
+        |ray, rng| unblocked(ray, &occluded).component_mul(&medium.transmittance(ray, rng)),
+        |wi| material.evaluate(&hit.normal, wo, wi),
+    )
+}
+
+/// transmittance of a shadow ray through nothing but surfaces
+fn unblocked(ray: &ray::Ray, occluded: impl Fn(&ray::Ray) -> bool) -> nalgebra::Vector3<Float> {
+    match occluded(ray) {
+        true => nalgebra::Vector3::zeros(),
+        false => nalgebra::Vector3::repeat(1.),
+    }
+}
+
+/// [`direct_lighting`] through any BSDF, given the direction towards the
+/// light, and any transmittance of the shadow rays
+pub(crate) fn sampled_lighting(
+    lights: &[Light],
+    hit: &shapes::Hit,
+    rng: &mut random::Rng,
+    transmittance: impl Fn(&ray::Ray, &mut random::Rng) -> nalgebra::Vector3<Float>,
+    bsdf: impl Fn(&nalgebra::UnitVector3<Float>) -> nalgebra::Vector3<Float>,
+) -> nalgebra::Vector3<Float> {
+    lights
+        .iter()
+        .filter_map(|light| {
+            let sample = nalgebra::Vector2::new(rng.uniform(), rng.uniform());
+            let light_sample = light.sample(&hit.point, &sample)?;
+            let cos = hit.normal.dot(&light_sample.direction).abs();
+            let bsdf = bsdf(&light_sample.direction);
+            if cos == 0. || bsdf == nalgebra::Vector3::zeros() {
+                return None;
+            }
+            let transmittance =
+                transmittance(&light_sample.shadow_ray(&hit.point, &hit.normal), rng);
+            Some(
+                bsdf.component_mul(&light_sample.irradiance)
+                    .component_mul(&transmittance)
+                    * cos,
+            )
+        })
+        .sum()
+}
+
+/// Radiance arriving along a ray that missed the scene, from every environment light
+///
+/// # Arguments
+/// - `direction`: of the ray, world frame
+pub fn environment_radiance(
+    lights: &[Light],
+    direction: &nalgebra::UnitVector3<Float>,
+) -> nalgebra::Vector3<Float> {
+    lights
+        .iter()
+        .filter_map(|light| match light {
+            Light::Environment(e) => Some(e.radiance(direction)),
+            _ => None,
+        })
+        .sum()
+}
+
+/// Emits equally in every direction
+#[derive(Copy, Clone, Debug)]
+pub struct PointLight {
+    position: nalgebra::Vector3<Float>,
+    // W/sr per channel
+    intensity: nalgebra::Vector3<Float>,
+}
+
+impl PointLight {
+    /// # Arguments
+    /// - `intensity`: W/sr per channel
+    pub fn new(position: nalgebra::Vector3<Float>, intensity: nalgebra::Vector3<Float>) -> Self {
+        Self {
+            position,
+            intensity,
+        }
+    }
+
+    pub fn position(&self) -> &nalgebra::Vector3<Float> {
+        &self.position
+    }
+
+    pub fn intensity(&self) -> &nalgebra::Vector3<Float> {
+        &self.intensity
+    }
+
+    /// emitted power, W per channel
+    pub fn power(&self) -> nalgebra::Vector3<Float> {
+        self.intensity * 4. * crate::consts::PI
+    }
+
+    fn sample(&self, point: &nalgebra::Vector3<Float>) -> Option<LightSample> {
+        let (direction, distance) =
+            nalgebra::UnitVector3::try_new_and_get(self.position - point, Float::EPSILON)?;
+        Some(LightSample {
+            direction,
+            distance,
+            irradiance: self.intensity / (distance * distance),
+            pdf: None,
+        })
+    }
+}
+
+/// Point light limited to a cone, fading out between the falloff start and the cone angle
+#[derive(Copy, Clone, Debug)]
+pub struct SpotLight {
+    position: nalgebra::Vector3<Float>,
+    direction: nalgebra::UnitVector3<Float>,
+    // W/sr per channel, along the axis
+    intensity: nalgebra::Vector3<Float>,
+    // half angles
+    cone: angle::Angle,
+    falloff_start: angle::Angle,
+}
+
+impl SpotLight {
+    /// Spot with a hard edge.
+    ///
+    /// # Arguments
+    /// - `direction`: axis of the cone, pointing away from the light
+    /// - `intensity`: W/sr per channel, inside the cone
+    /// - `cone`: half angle of the cone, (0, 180°]
+    pub fn new(
+        position: nalgebra::Vector3<Float>,
+        direction: nalgebra::UnitVector3<Float>,
+        intensity: nalgebra::Vector3<Float>,
+        cone: angle::Angle,
+    ) -> Self {
+        debug_assert!(
+            cone.rads() > 0. && cone.rads() <= crate::consts::PI,
+            "cone angle is within (0, 180°]"
+        );
+        Self {
+            position,
+            direction,
+            intensity,
+            cone,
+            falloff_start: cone,
+        }
+    }
+
+    /// half angle where the intensity starts fading smoothly to 0 at the cone angle
+    pub fn with_falloff_start(mut self, falloff_start: angle::Angle) -> Self {
+        debug_assert!(
+            falloff_start.rads() >= 0. && falloff_start.rads() <= self.cone.rads(),
+            "falloff start is within [0, cone angle]"
+        );
+        self.falloff_start = falloff_start;
+        self
+    }
+
+    pub fn position(&self) -> &nalgebra::Vector3<Float> {
+        &self.position
+    }
+
+    pub fn direction(&self) -> &nalgebra::UnitVector3<Float> {
+        &self.direction
+    }
+
+    pub fn intensity(&self) -> &nalgebra::Vector3<Float> {
+        &self.intensity
+    }
+
+    pub fn cone(&self) -> angle::Angle {
+        self.cone
+    }
+
+    pub fn falloff_start(&self) -> angle::Angle {
+        self.falloff_start
+    }
+
+    /// fraction of the intensity leaving in a direction, smoothstep in cosine across the falloff
+    pub fn falloff(&self, direction: &nalgebra::UnitVector3<Float>) -> Float {
+        let cos = self.direction.dot(direction);
+        let (cos_cone, cos_start) = (self.cone.cos(), self.falloff_start.cos());
+        if cos >= cos_start {
+            return 1.;
+        }
+        if cos <= cos_cone {
+            return 0.;
+        }
+        let t = (cos - cos_cone) / (cos_start - cos_cone);
+        t * t * (3. - 2. * t)
+    }
+
+    /// emitted power, W per channel, approximating the falloff as linear in cosine
+    pub fn power(&self) -> nalgebra::Vector3<Float> {
+        self.intensity
+            * 2.
+            * crate::consts::PI
+            * (1. - 0.5 * (self.cone.cos() + self.falloff_start.cos()))
+    }
+
+    fn sample(&self, point: &nalgebra::Vector3<Float>) -> Option<LightSample> {
+        let (direction, distance) =
+            nalgebra::UnitVector3::try_new_and_get(self.position - point, Float::EPSILON)?;
+        let falloff = self.falloff(&-direction);
+        (falloff > 0.).then(|| LightSample {
+            direction,
+            distance,
+            irradiance: self.intensity * falloff / (distance * distance),
+            pdf: None,
+        })
+    }
+}
+
+/// Parallel light from infinitely far away, e.g. the sun
+#[derive(Copy, Clone, Debug)]
+pub struct DirectionalLight {
+    // direction the light travels in
+    direction: nalgebra::UnitVector3<Float>,
+    // W/m² per channel, on a surface facing the light
+    irradiance: nalgebra::Vector3<Float>,
+}
+
+impl DirectionalLight {
+    /// # Arguments
+    /// - `direction`: the light travels in, e.g. down for a sun at the zenith
+    /// - `irradiance`: W/m² per channel, on a surface facing the light
+    pub fn new(
+        direction: nalgebra::UnitVector3<Float>,
+        irradiance: nalgebra::Vector3<Float>,
+    ) -> Self {
+        Self {
+            direction,
+            irradiance,
+        }
+    }
+
+    pub fn direction(&self) -> &nalgebra::UnitVector3<Float> {
+        &self.direction
+    }
+
+    pub fn irradiance(&self) -> &nalgebra::Vector3<Float> {
+        &self.irradiance
+    }
+
+    fn sample(&self) -> LightSample {
+        LightSample {
+            direction: -self.direction,
+            distance: Float::INFINITY,
+            irradiance: self.irradiance,
+            pdf: None,
+        }
+    }
+}
+
+/// Sample of a point picked uniformly by area on a light of total `area`.
+fn sample_surface_point(
+    point: &nalgebra::Vector3<Float>,
+    on_light: &nalgebra::Vector3<Float>,
+    normal: &nalgebra::UnitVector3<Float>,
+    area: Float,
+    two_sided: bool,
+    radiance: &nalgebra::Vector3<Float>,
+) -> Option<LightSample> {
+    let (direction, distance) =
+        nalgebra::UnitVector3::try_new_and_get(on_light - point, Float::EPSILON)?;
+    let cos = -normal.dot(&direction);
+    let cos = if two_sided { cos.abs() } else { cos };
+    if cos <= 0. {
+        return None;
+    }
+
+    // area density converted to solid angle
+    let pdf = distance * distance / (cos * area);
+    Some(LightSample {
+        direction,
+        distance,
+        irradiance: radiance / pdf,
+        pdf: Some(pdf),
+    })
+}
@@ -0,0 +1,386 @@
+//! Surface materials, shared between objects by [`MaterialId`].
+//!
+//! Colors are linear RGB, convert 8 bit sRGB with [`crate::color`].
+
+use crate::Float;
+use crate::consts;
+use crate::distance;
+use crate::shapes;
+use crate::spectral;
+use crate::texture;
+
+#[derive(Clone, Debug)]
+pub struct Material {
+    albedo: nalgebra::Vector3<Float>,
+    roughness: Float,
+    metallic: Float,
+    refractive_index: Float,
+    extinction_coefficient: Float,
+    dispersion: Option<spectral::Dispersion>,
+    emission: nalgebra::Vector3<Float>,
+    transmission: Float,
+    albedo_texture: Option<TextureId>,
+    roughness_texture: Option<TextureId>,
+    normal_texture: Option<TextureId>,
+}
+
+impl Default for Material {
+    /// mid grey, rough dielectric
+    fn default() -> Self {
+        Self::diffuse(nalgebra::Vector3::repeat(0.5))
+    }
+}
+
+impl Material {
+    /// rough, non metallic, opaque
+    pub fn diffuse(albedo: nalgebra::Vector3<Float>) -> Self {
+        Self {
+            albedo,
+            roughness: 1.,
+            metallic: 0.,
+            refractive_index: 1.5,
+            extinction_coefficient: 0.,
+            dispersion: None,
+            emission: nalgebra::Vector3::zeros(),
+            transmission: 0.,
+            albedo_texture: None,
+            roughness_texture: None,
+            normal_texture: None,
+        }
+    }
+
+    /// albedo looked up by uv, replacing the constant albedo
+    pub fn with_albedo_texture(mut self, texture: TextureId) -> Self {
+        self.albedo_texture = Some(texture);
+        self
+    }
+
+    /// roughness looked up by uv from the first channel, replacing the constant roughness
+    pub fn with_roughness_texture(mut self, texture: TextureId) -> Self {
+        self.roughness_texture = Some(texture);
+        self
+    }
+
+    /// tangent space normal map, channels in [0, 1] mapping to [-1, 1]
+    pub fn with_normal_texture(mut self, texture: TextureId) -> Self {
+        self.normal_texture = Some(texture);
+        self
+    }
+
+    /// perceptual roughness, [0, 1]
+    pub fn with_roughness(mut self, roughness: Float) -> Self {
+        debug_assert!(
+            (0.0..=1.).contains(&roughness),
+            "roughness is within [0, 1]"
+        );
+        self.roughness = roughness;
+        self
+    }
+
+    /// blend between dielectric (0) and metal (1), tinting the specular by the albedo
+    pub fn with_metallic(mut self, metallic: Float) -> Self {
+        debug_assert!((0.0..=1.).contains(&metallic), "metallic is within [0, 1]");
+        self.metallic = metallic;
+        self
+    }
+
+    /// complex refractive index n + ik of the surface
+    pub fn with_refractive_index(
+        mut self,
+        refractive_index: Float,
+        extinction_coefficient: Float,
+    ) -> Self {
+        debug_assert!(refractive_index > 0., "refractive index can not be <= 0.0");
+        self.refractive_index = refractive_index;
+        self.extinction_coefficient = extinction_coefficient;
+        self
+    }
+
+    /// wavelength dependent refractive index, used by [`Material::at_wavelength`]
+    pub fn with_dispersion(mut self, dispersion: spectral::Dispersion) -> Self {
+        self.dispersion = Some(dispersion);
+        self
+    }
+
+    /// emitted radiance, W/(sr m²) per channel
+    pub fn with_emission(mut self, emission: nalgebra::Vector3<Float>) -> Self {
+        self.emission = emission;
+        self
+    }
+
+    /// fraction of the non metallic part that is transmitted rather than diffusely reflected, [0, 1]
+    pub fn with_transmission(mut self, transmission: Float) -> Self {
+        debug_assert!(
+            (0.0..=1.).contains(&transmission),
+            "transmission is within [0, 1]"
+        );
+        self.transmission = transmission;
+        self
+    }
+
+    pub fn albedo(&self) -> &nalgebra::Vector3<Float> {
+        &self.albedo
+    }
+
+    pub fn roughness(&self) -> Float {
+        self.roughness
+    }
+
+    pub fn metallic(&self) -> Float {
+        self.metallic
+    }
+
+    pub fn refractive_index(&self) -> Float {
+        self.refractive_index
+    }
+
+    pub fn extinction_coefficient(&self) -> Float {
+        self.extinction_coefficient
+    }
+
+    pub fn dispersion(&self) -> Option<&spectral::Dispersion> {
+        self.dispersion.as_ref()
+    }
+
+    /// refractive index at a wavelength, the constant index without dispersion
+    pub fn refractive_index_at(&self, wavelength: distance::Distance) -> Float {
+        self.dispersion
+            .map_or(self.refractive_index, |d| d.refractive_index(wavelength))
+    }
+
+    /// The material as seen by a single wavelength, for spectral rendering.
+    pub fn at_wavelength(&self, wavelength: distance::Distance) -> Self {
+        Self {
+            refractive_index: self.refractive_index_at(wavelength),
+            ..self.clone()
+        }
+    }
+
+    pub fn emission(&self) -> &nalgebra::Vector3<Float> {
+        &self.emission
+    }
+
+    pub fn transmission(&self) -> Float {
+        self.transmission
+    }
+
+    pub fn is_emissive(&self) -> bool {
+        self.emission.iter().any(|e| *e > 0.)
+    }
+
+    /// Evaluates the BSDF, the fraction of radiance arriving from `wi`
+    /// scattered towards `wo`, per steradian (without the cosine term).
+    ///
+    /// Lambertian diffuse plus GGX microfacet reflection and transmission.
+    /// All directions point away from the surface, the normal points out
+    /// of the object.
+    pub fn evaluate(
+        &self,
+        normal: &nalgebra::UnitVector3<Float>,
+        wo: &nalgebra::UnitVector3<Float>,
+        wi: &nalgebra::UnitVector3<Float>,
+    ) -> nalgebra::Vector3<Float> {
+        let (specular, diffuse) = self.lobes(normal, wo, wi);
+        specular + diffuse
+    }
+
+    /// [`Material::evaluate`] without the microfacet lobes, only the
+    /// Lambertian part, e.g. when reflection and transmission are traced
+    /// as mirror paths.
+    pub fn evaluate_diffuse(
+        &self,
+        normal: &nalgebra::UnitVector3<Float>,
+        wo: &nalgebra::UnitVector3<Float>,
+        wi: &nalgebra::UnitVector3<Float>,
+    ) -> nalgebra::Vector3<Float> {
+        self.lobes(normal, wo, wi).1
+    }
+
+    /// microfacet (reflection and transmission) and diffuse parts of the BSDF
+    fn lobes(
+        &self,
+        normal: &nalgebra::UnitVector3<Float>,
+        wo: &nalgebra::UnitVector3<Float>,
+        wi: &nalgebra::UnitVector3<Float>,
+    ) -> (nalgebra::Vector3<Float>, nalgebra::Vector3<Float>) {
+        let none = nalgebra::Vector3::zeros();
+        let cos_o = normal.dot(wo);
+        let cos_i = normal.dot(wi);
+        if cos_o == 0. || cos_i == 0. {
+            return (none, none);
+        }
+
+        let alpha = (self.roughness * self.roughness).max(1e-3);
+        let specular_tint =
+            nalgebra::Vector3::repeat(1. - self.metallic) + self.albedo * self.metallic;
+        let dielectric = (1. - self.metallic) * self.albedo;
+
+        if cos_o * cos_i > 0. {
+            // reflection, on either side of the surface
+            let half = match cos_o > 0. {
+                true => nalgebra::UnitVector3::new_normalize(wo.into_inner() + wi.into_inner()),
+                false => nalgebra::UnitVector3::new_normalize(-(wo.into_inner() + wi.into_inner())),
+            };
+            let fresnel = crate::fresnel_reflectance(
+                wo.dot(&half).abs() * cos_o.signum(),
+                self.refractive_index,
+                self.extinction_coefficient,
+            );
+
+            let specular = ggx_distribution(normal.dot(&half), alpha)
+                * smith_masking(cos_o, alpha)
+                * smith_masking(cos_i, alpha)
+                / (4. * cos_o.abs() * cos_i.abs());
+            let diffuse = (1. - self.transmission) * (1. - fresnel) / consts::PI;
+
+            (specular_tint * fresnel * specular, dielectric * diffuse)
+        } else {
+            // transmission, generalized half vector from Walter et al. 2007
+            if self.transmission == 0. || self.extinction_coefficient > 0. {
+                return (none, none);
+            }
+            let (eta_o, eta_i) = match cos_o > 0. {
+                true => (1., self.refractive_index),
+                false => (self.refractive_index, 1.),
+            };
+            let half = -(eta_o * wo.into_inner() + eta_i * wi.into_inner());
+            let half = nalgebra::UnitVector3::new_normalize(half * normal.dot(&half).signum());
+            let o_dot_h = wo.dot(&half);
+            let i_dot_h = wi.dot(&half);
+
+            // the half vector faces out of the object, so this is negative from inside
+            let fresnel = crate::fresnel_reflectance(o_dot_h, self.refractive_index, 0.);
+            let denominator = eta_o * o_dot_h + eta_i * i_dot_h;
+            // radiance, not importance, so scaled by the index on the viewer side
+            let transmitted = (i_dot_h * o_dot_h).abs() / (cos_i * cos_o).abs()
+                * eta_o
+                * eta_o
+                * (1. - fresnel)
+                * ggx_distribution(normal.dot(&half), alpha)
+                * smith_masking(cos_o, alpha)
+                * smith_masking(cos_i, alpha)
+                / (denominator * denominator);
+
+            (dielectric * self.transmission * transmitted, none)
+        }
+    }
+}
+
+/// GGX / Trowbridge-Reitz normal distribution
+fn ggx_distribution(cos_half: Float, alpha: Float) -> Float {
+    if cos_half <= 0. {
+        return 0.;
+    }
+    let alpha2 = alpha * alpha;
+    let d = cos_half * cos_half * (alpha2 - 1.) + 1.;
+    alpha2 / (consts::PI * d * d)
+}
+
+/// Smith masking for GGX, one direction
+fn smith_masking(cos: Float, alpha: Float) -> Float {
+    let cos = cos.abs();
+    let alpha2 = alpha * alpha;
+    2. * cos / (cos + (alpha2 + (1. - alpha2) * cos * cos).sqrt())
+}
+
+/// handle to a material in a [`MaterialLibrary`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MaterialId(usize);
+
+impl MaterialId {
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+/// handle to a texture in a [`MaterialLibrary`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TextureId(usize);
+
+#[derive(Default)]
+pub struct MaterialLibrary {
+    materials: Vec<Material>,
+    textures: Vec<texture::Texture>,
+}
+
+impl MaterialLibrary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, material: Material) -> MaterialId {
+        self.materials.push(material);
+        MaterialId(self.materials.len() - 1)
+    }
+
+    pub fn get(&self, id: MaterialId) -> Option<&Material> {
+        self.materials.get(id.0)
+    }
+
+    pub fn get_mut(&mut self, id: MaterialId) -> Option<&mut Material> {
+        self.materials.get_mut(id.0)
+    }
+
+    pub fn add_texture(&mut self, texture: texture::Texture) -> TextureId {
+        self.textures.push(texture);
+        TextureId(self.textures.len() - 1)
+    }
+
+    pub fn texture(&self, id: TextureId) -> Option<&texture::Texture> {
+        self.textures.get(id.0)
+    }
+
+    /// The material at a hit, with its albedo and roughness textures looked up.
+    pub fn evaluate_textures(&self, id: MaterialId, hit: &shapes::Hit) -> Material {
+        let mut material = self[id].clone();
+        let sample =
+            |texture: TextureId| self.textures[texture.0].sample(&hit.uv, hit.uv_footprint);
+
+        if let Some(texture) = material.albedo_texture {
+            material.albedo = sample(texture);
+        }
+        if let Some(texture) = material.roughness_texture {
+            material.roughness = sample(texture).x.clamp(0., 1.);
+        }
+        material
+    }
+
+    /// The normal to shade a hit with, the geometric normal perturbed by
+    /// the normal map of the material, if it has one.
+    pub fn shading_normal(
+        &self,
+        id: MaterialId,
+        hit: &shapes::Hit,
+    ) -> nalgebra::UnitVector3<Float> {
+        let Some(texture) = self[id].normal_texture else {
+            return hit.normal;
+        };
+
+        let local = self.textures[texture.0].sample(&hit.uv, hit.uv_footprint) * 2.
+            - nalgebra::Vector3::repeat(1.);
+        let bitangent = hit.normal.cross(&hit.tangent);
+        nalgebra::UnitVector3::try_new(
+            hit.tangent.into_inner() * local.x
+                + bitangent * local.y
+                + hit.normal.into_inner() * local.z,
+            Float::EPSILON,
+        )
+        .unwrap_or(hit.normal)
+    }
+
+    pub fn len(&self) -> usize {
+        self.materials.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.materials.is_empty()
+    }
+}
+
+impl std::ops::Index<MaterialId> for MaterialLibrary {
+    type Output = Material;
+
+    fn index(&self, id: MaterialId) -> &Self::Output {
+        &self.materials[id.0]
+    }
+}
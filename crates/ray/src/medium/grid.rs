@@ -0,0 +1,82 @@
+use crate::Float;
+use crate::aabb;
+
+/// Density on a regular grid over a box, trilinear between the cell centers, 0 outside
+pub struct DensityGrid {
+    bounds: aabb::Aabb,
+    resolution: [usize; 3],
+    densities: Vec<Float>,
+    max_density: Float,
+}
+
+impl DensityGrid {
+    /// `densities` x fastest, then y, then z, all >= 0
+    pub fn new(bounds: aabb::Aabb, resolution: [usize; 3], densities: Vec<Float>) -> Self {
+        debug_assert!(
+            resolution.iter().all(|r| *r > 0),
+            "grid resolution can not be 0"
+        );
+        debug_assert_eq!(
+            densities.len(),
+            resolution.iter().product::<usize>(),
+            "densities do not match the grid resolution"
+        );
+        debug_assert!(
+            densities.iter().all(|d| *d >= 0.),
+            "density can not be negative"
+        );
+        let max_density = densities.iter().copied().fold(0., Float::max);
+        Self {
+            bounds,
+            resolution,
+            densities,
+            max_density,
+        }
+    }
+
+    pub fn bounds(&self) -> &aabb::Aabb {
+        &self.bounds
+    }
+
+    pub fn resolution(&self) -> [usize; 3] {
+        self.resolution
+    }
+
+    pub fn max_density(&self) -> Float {
+        self.max_density
+    }
+
+    pub fn density(&self, point: &nalgebra::Vector3<Float>) -> Float {
+        let local = (point - self.bounds.min()).component_div(&self.bounds.extent());
+        if local.iter().any(|l| !(0.0..=1.).contains(l)) {
+            return 0.;
+        }
+
+        // continuous index with cell centers on integers
+        let index: [Float; 3] =
+            std::array::from_fn(|axis| local[axis] * self.resolution[axis] as Float - 0.5);
+        let base = index.map(|i| i.floor());
+        let fraction: [Float; 3] = std::array::from_fn(|axis| index[axis] - base[axis]);
+
+        (0..8)
+            .map(|corner| {
+                let mut weight = 1.;
+                let mut offset = 0;
+                let mut stride = 1;
+                for axis in 0..3 {
+                    let upper = corner >> axis & 1 == 1;
+                    weight *= match upper {
+                        true => fraction[axis],
+                        false => 1. - fraction[axis],
+                    };
+                    let i = (base[axis] as isize + isize::from(upper))
+                        .clamp(0, self.resolution[axis] as isize - 1)
+                        as usize;
+                    offset += i * stride;
+                    stride *= self.resolution[axis];
+                }
+                weight * self.densities[offset]
+            })
+            .sum()
+    }
+}
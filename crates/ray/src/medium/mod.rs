@@ -0,0 +1,452 @@
+//! Participating media: fog, haze and smoke absorbing and scattering light
+//! along rays.
+//!
+//! Coefficients are per meter and per linear RGB channel at a density of 1,
+//! scaled by the density of the medium at every point. Media with a closed
+//! form optical depth (height fog and homogeneous volumes) are sampled
+//! exactly, grids use delta tracking to sample interactions and ratio
+//! tracking for transmittance, with a majorant from the densest cell.
+
+use crate::Float;
+use crate::light;
+use crate::random;
+use crate::ray;
+use crate::shapes::composite;
+use crate::shapes::sphere;
+
+pub mod grid;
+pub mod phase;
+
+/// Per meter, per channel
+#[derive(Copy, Clone, Debug)]
+pub struct Coefficients {
+    pub absorption: nalgebra::Vector3<Float>,
+    pub scattering: nalgebra::Vector3<Float>,
+}
+
+impl Coefficients {
+    pub fn new(absorption: nalgebra::Vector3<Float>, scattering: nalgebra::Vector3<Float>) -> Self {
+        debug_assert!(
+            absorption.iter().chain(scattering.iter()).all(|c| *c >= 0.),
+            "coefficients can not be negative"
+        );
+        Self {
+            absorption,
+            scattering,
+        }
+    }
+
+    /// absorption plus scattering
+    pub fn extinction(&self) -> nalgebra::Vector3<Float> {
+        self.absorption + self.scattering
+    }
+
+    /// fraction of the extinction that scatters
+    pub fn albedo(&self) -> nalgebra::Vector3<Float> {
+        self.scattering
+            .zip_map(&self.extinction(), |s, t| if t > 0. { s / t } else { 0. })
+    }
+}
+
+/// Closed surface holding a homogeneous medium
+pub enum Boundary {
+    Sphere(sphere::Sphere),
+    /// any winding, only needs to be closed
+    Mesh(composite::CompositeObject),
+}
+
+impl Boundary {
+    /// parts of the line of the ray inside, in increasing order, before clipping to its interval
+    fn inside(&self, ray: &ray::Ray) -> Vec<(Float, Float)> {
+        let line = ray.with_interval(Float::NEG_INFINITY, Float::INFINITY);
+        match self {
+            Boundary::Sphere(s) => match s.ray_intersection(&line) {
+                sphere::SphereIntersection::Two(a, b) => vec![(a.min(b), a.max(b))],
+                _ => Vec::new(),
+            },
+            Boundary::Mesh(m) => {
+                let mut crossings = m.ray_intersection(&line).collect::<Vec<_>>();
+                crossings.sort_unstable_by(Float::total_cmp);
+                crossings
+                    .chunks_exact(2)
+                    .map(|pair| (pair[0], pair[1]))
+                    .collect()
+            }
+        }
+    }
+
+    /// parts of the interval of the ray inside
+    fn intervals(&self, ray: &ray::Ray) -> Vec<(Float, Float)> {
+        self.inside(ray)
+            .into_iter()
+            .map(|(start, end)| (start.max(ray.t_min()), end.min(ray.t_max())))
+            .filter(|(start, end)| start < end)
+            .collect()
+    }
+
+    pub fn contains(&self, point: &nalgebra::Vector3<Float>) -> bool {
+        let ray = ray::Ray::new(*point, nalgebra::Vector3::x_axis());
+        self.inside(&ray)
+            .iter()
+            .any(|(start, end)| *start <= 0. && 0. <= *end)
+    }
+}
+
+pub enum Region {
+    /// everywhere, density `exp(-(height - reference_height) / scale_height)`
+    HeightFog {
+        up: nalgebra::UnitVector3<Float>,
+        reference_height: Float,
+        scale_height: Float,
+    },
+    /// density 1 inside the boundary
+    Bounded(Boundary),
+    Grid(grid::DensityGrid),
+}
+
+/// What happened to light travelling along a ray through a medium
+#[derive(Copy, Clone, Debug)]
+pub enum Interaction {
+    /// scattered at a distance, carrying `weight` of the light so far
+    Scattered {
+        distance: Float,
+        weight: nalgebra::Vector3<Float>,
+    },
+    Absorbed,
+    /// left the interval of the ray, carrying `weight` of the light so far
+    Escaped {
+        weight: nalgebra::Vector3<Float>,
+    },
+}
+
+pub struct Medium {
+    coefficients: Coefficients,
+    phase: phase::HenyeyGreenstein,
+    region: Region,
+}
+
+impl Medium {
+    /// Fog thinning out exponentially with height, e.g. haze over the ground.
+    ///
+    /// # Arguments
+    /// - `coefficients`: at the reference height
+    /// - `up`: direction of increasing height, -z in north-east-down
+    /// - `scale_height`: rise over which the density drops by e, in meters
+    pub fn height_fog(
+        coefficients: Coefficients,
+        up: nalgebra::UnitVector3<Float>,
+        reference_height: Float,
+        scale_height: Float,
+    ) -> Self {
+        debug_assert!(scale_height > 0., "scale height can not be <= 0.0");
+        Self::new(
+            coefficients,
+            Region::HeightFog {
+                up,
+                reference_height,
+                scale_height,
+            },
+        )
+    }
+
+    pub fn homogeneous(coefficients: Coefficients, boundary: Boundary) -> Self {
+        Self::new(coefficients, Region::Bounded(boundary))
+    }
+
+    /// # Arguments
+    /// - `coefficients`: at a density of 1
+    pub fn grid(coefficients: Coefficients, grid: grid::DensityGrid) -> Self {
+        Self::new(coefficients, Region::Grid(grid))
+    }
+
+    fn new(coefficients: Coefficients, region: Region) -> Self {
+        Self {
+            coefficients,
+            phase: phase::HenyeyGreenstein::new(0.),
+            region,
+        }
+    }
+
+    /// Henyey-Greenstein scattering, isotropic without
+    pub fn with_phase(mut self, phase: phase::HenyeyGreenstein) -> Self {
+        self.phase = phase;
+        self
+    }
+
+    pub fn coefficients(&self) -> &Coefficients {
+        &self.coefficients
+    }
+
+    pub fn phase(&self) -> &phase::HenyeyGreenstein {
+        &self.phase
+    }
+
+    pub fn region(&self) -> &Region {
+        &self.region
+    }
+
+    pub fn density(&self, point: &nalgebra::Vector3<Float>) -> Float {
+        match &self.region {
+            Region::HeightFog {
+                up,
+                reference_height,
+                scale_height,
+            } => (-(up.dot(point) - reference_height) / scale_height).exp(),
+            Region::Bounded(boundary) => match boundary.contains(point) {
+                true => 1.,
+                false => 0.,
+            },
+            Region::Grid(grid) => grid.density(point),
+        }
+    }
+
+    /// Fraction of light surviving the interval of the ray, exact for
+    /// fog and homogeneous media, an unbiased estimate for grids.
+    pub fn transmittance(&self, ray: &ray::Ray, rng: &mut random::Rng) -> nalgebra::Vector3<Float> {
+        let extinction = self.coefficients.extinction();
+        let Region::Grid(grid) = &self.region else {
+            // rays to infinitely far lights can have an infinite density length
+            let density_length = self.density_length(ray);
+            return extinction.map(|e| match e > 0. {
+                true => (-e * density_length).exp(),
+                false => 1.,
+            });
+        };
+
+        // ratio tracking
+        let Some((start, end, majorant)) = self.tracking_interval(grid, ray) else {
+            return nalgebra::Vector3::repeat(1.);
+        };
+        let mut transmittance = nalgebra::Vector3::repeat(1.);
+        let mut t = start;
+        loop {
+            t -= (1. - rng.uniform()).ln() / majorant;
+            if t >= end {
+                return transmittance;
+            }
+            let density = grid.density(&ray.at(t));
+            transmittance.component_mul_assign(&extinction.map(|e| 1. - density * e / majorant));
+        }
+    }
+
+    /// Samples where light along the ray first interacts with the medium.
+    ///
+    /// Distances are picked by the mean extinction of the channels, the
+    /// weights correct for the channels differing.
+    pub fn sample_interaction(&self, ray: &ray::Ray, rng: &mut random::Rng) -> Interaction {
+        let extinction = self.coefficients.extinction();
+        let mean = extinction.mean();
+        if mean == 0. {
+            return Interaction::Escaped {
+                weight: nalgebra::Vector3::repeat(1.),
+            };
+        }
+
+        let Region::Grid(grid) = &self.region else {
+            let target = -(1. - rng.uniform()).ln() / mean;
+            let reweight =
+                |density_length: Float| extinction.map(|e| (-(e - mean) * density_length).exp());
+            return match self.distance_at(ray, target) {
+                Some(distance) => Interaction::Scattered {
+                    distance,
+                    weight: (self.coefficients.scattering / mean).component_mul(&reweight(target)),
+                },
+                None => Interaction::Escaped {
+                    weight: reweight(self.density_length(ray)),
+                },
+            };
+        };
+
+        // delta tracking with null collisions, Kutz et al. 2017
+        let mut weight = nalgebra::Vector3::repeat(1.);
+        let Some((start, end, majorant)) = self.tracking_interval(grid, ray) else {
+            return Interaction::Escaped { weight };
+        };
+        let mut t = start;
+        loop {
+            t -= (1. - rng.uniform()).ln() / majorant;
+            if t >= end {
+                return Interaction::Escaped { weight };
+            }
+            let density = grid.density(&ray.at(t));
+            let absorption = self.coefficients.absorption * density;
+            let scattering = self.coefficients.scattering * density;
+            let null = nalgebra::Vector3::repeat(majorant) - absorption - scattering;
+
+            let p_absorption = absorption.mean() / majorant;
+            let p_scattering = scattering.mean() / majorant;
+            let xi = rng.uniform();
+            if xi < p_absorption {
+                return Interaction::Absorbed;
+            }
+            if xi < p_absorption + p_scattering {
+                return Interaction::Scattered {
+                    distance: t,
+                    weight: weight.component_mul(&(scattering / (majorant * p_scattering))),
+                };
+            }
+            weight.component_mul_assign(&(null / (majorant * (1. - p_absorption - p_scattering))));
+        }
+    }
+
+    /// Light scattered once towards the origin of the ray from anywhere in
+    /// its interval, one sample of every light, and the weight of the light
+    /// from the end of the interval (the surface hit or the background).
+    ///
+    /// # Arguments
+    /// - `occluded`: whether anything blocks a ray within its interval
+    pub fn single_scattering(
+        &self,
+        ray: &ray::Ray,
+        lights: &[light::Light],
+        rng: &mut random::Rng,
+        occluded: impl Fn(&ray::Ray) -> bool,
+    ) -> (nalgebra::Vector3<Float>, nalgebra::Vector3<Float>) {
+        let (distance, weight) = match self.sample_interaction(ray, rng) {
+            Interaction::Scattered { distance, weight } => (distance, weight),
+            Interaction::Absorbed => {
+                return (nalgebra::Vector3::zeros(), nalgebra::Vector3::zeros());
+            }
+            Interaction::Escaped { weight } => return (nalgebra::Vector3::zeros(), weight),
+        };
+
+        let point = ray.at(distance);
+        let radiance = lights
+            .iter()
+            .filter_map(|light| {
+                let sample = nalgebra::Vector2::new(rng.uniform(), rng.uniform());
+                let light_sample = light.sample(&point, &sample)?;
+                let shadow_ray = ray::Ray::new(point, light_sample.direction)
+                    .with_interval(
+                        0.,
+                        light_sample.distance * (1. - light::SHADOW_RAY_SHORTENING),
+                    )
+                    .with_time(ray.time());
+                if occluded(&shadow_ray) {
+                    return None;
+                }
+                let phase = self
+                    .phase
+                    .value(light_sample.direction.dot(ray.unit_direction()));
+                Some(
+                    light_sample
+                        .irradiance
+                        .component_mul(&self.transmittance(&shadow_ray, rng))
+                        * phase,
+                )
+            })
+            .sum::<nalgebra::Vector3<Float>>();
+        (radiance.component_mul(&weight), nalgebra::Vector3::zeros())
+    }
+
+    /// Radiance arriving at the origin of the ray through the medium, one
+    /// [`Medium::single_scattering`] estimate.
+    ///
+    /// # Arguments
+    /// - `occluded`: whether anything blocks a ray within its interval
+    /// - `behind`: radiance from the end of the interval (the surface hit or
+    ///   the background), only evaluated when some of it gets through
+    pub fn radiance(
+        &self,
+        ray: &ray::Ray,
+        lights: &[light::Light],
+        rng: &mut random::Rng,
+        occluded: impl Fn(&ray::Ray) -> bool,
+        behind: impl FnOnce(&mut random::Rng) -> nalgebra::Vector3<Float>,
+    ) -> nalgebra::Vector3<Float> {
+        let (scattered, weight) = self.single_scattering(ray, lights, rng, occluded);
+        match weight == nalgebra::Vector3::zeros() {
+            true => scattered,
+            false => scattered + weight.component_mul(&behind(rng)),
+        }
+    }
+
+    /// (start, end, majorant) of the part of the ray in the grid
+    fn tracking_interval(
+        &self,
+        grid: &grid::DensityGrid,
+        ray: &ray::Ray,
+    ) -> Option<(Float, Float, Float)> {
+        let (near, far) = grid.bounds().ray_intersection(ray)?;
+        let (start, end) = (near.max(ray.t_min()), far.min(ray.t_max()));
+        let majorant = grid.max_density() * self.coefficients.extinction().max();
+        (start < end && majorant > 0.).then_some((start, end, majorant))
+    }
+
+    /// integral of the density over the interval of the ray, fog and bounded media
+    fn density_length(&self, ray: &ray::Ray) -> Float {
+        match &self.region {
+            Region::HeightFog { .. } => {
+                let (origin_density, decay) = self.fog_along(ray);
+                fog_integral(origin_density, decay, ray.t_min(), ray.t_max())
+            }
+            Region::Bounded(boundary) => boundary
+                .intervals(ray)
+                .iter()
+                .map(|(start, end)| end - start)
+                .sum(),
+            Region::Grid(_) => unreachable!("grids are tracked"),
+        }
+    }
+
+    /// distance along the ray where the density integral reaches `target`, fog and bounded media
+    fn distance_at(&self, ray: &ray::Ray, target: Float) -> Option<Float> {
+        match &self.region {
+            Region::HeightFog { .. } => {
+                let (origin_density, decay) = self.fog_along(ray);
+                if origin_density == 0. {
+                    return None;
+                }
+                let t0 = ray.t_min();
+                let t = match decay.abs() < Float::EPSILON {
+                    true => t0 + target / origin_density,
+                    false => {
+                        let remaining = (-decay * t0).exp() - target * decay / origin_density;
+                        if remaining <= 0. {
+                            return None;
+                        }
+                        -remaining.ln() / decay
+                    }
+                };
+                (t < ray.t_max()).then_some(t)
+            }
+            Region::Bounded(boundary) => {
+                let mut remaining = target;
+                for (start, end) in boundary.intervals(ray) {
+                    if remaining < end - start {
+                        return Some(start + remaining);
+                    }
+                    remaining -= end - start;
+                }
+                None
+            }
+            Region::Grid(_) => unreachable!("grids are tracked"),
+        }
+    }
+
+    /// fog density at the ray origin and its decay rate along the ray,
+    /// the density at t being `origin_density * exp(-decay * t)`
+    fn fog_along(&self, ray: &ray::Ray) -> (Float, Float) {
+        let Region::HeightFog {
+            up, scale_height, ..
+        } = &self.region
+        else {
+            unreachable!("only fog has a height profile");
+        };
+        (
+            self.density(ray.origin()),
+            up.dot(ray.unit_direction()) / scale_height,
+        )
+    }
+}
+
+/// integral of `origin_density * exp(-decay * t)` over [t0, t1]
+fn fog_integral(origin_density: Float, decay: Float, t0: Float, t1: Float) -> Float {
+    if origin_density == 0. {
+        return 0.;
+    }
+    match decay.abs() < Float::EPSILON {
+        true => origin_density * (t1 - t0),
+        false => origin_density / decay * ((-decay * t0).exp() - (-decay * t1).exp()),
+    }
+}
@@ -0,0 +1,61 @@
+use crate::Float;
+use crate::consts;
+use crate::shapes;
+
+/// [Henyey-Greenstein](https://doi.org/10.1086/144246) phase function,
+/// forward scattering for positive asymmetry
+#[derive(Copy, Clone, Debug)]
+pub struct HenyeyGreenstein {
+    g: Float,
+}
+
+impl HenyeyGreenstein {
+    /// # Arguments
+    /// - `g`: mean cosine of the scattering angle, (-1, 1), 0 is isotropic
+    pub fn new(g: Float) -> Self {
+        debug_assert!(g > -1. && g < 1., "asymmetry is within (-1, 1)");
+        Self { g }
+    }
+
+    pub fn g(&self) -> Float {
+        self.g
+    }
+
+    /// Density per steradian of scattering by an angle.
+    ///
+    /// # Arguments
+    /// - `cos`: between the directions the light travels in before and after scattering
+    pub fn value(&self, cos: Float) -> Float {
+        let g = self.g;
+        let denominator = 1. + g * g - 2. * g * cos;
+        (1. - g * g) / (4. * consts::PI * denominator * denominator.sqrt())
+    }
+
+    /// New direction of light travelling along `direction`, distributed as [`HenyeyGreenstein::value`].
+    ///
+    /// # Arguments
+    /// - `sample`: uniform in [0, 1)²
+    pub fn sample(
+        &self,
+        direction: &nalgebra::UnitVector3<Float>,
+        sample: &nalgebra::Vector2<Float>,
+    ) -> nalgebra::UnitVector3<Float> {
+        let g = self.g;
+        let cos = match g.abs() < 1e-3 {
+            true => 1. - 2. * sample.x,
+            false => {
+                let s = (1. - g * g) / (1. - g + 2. * g * sample.x);
+                ((1. + g * g - s * s) / (2. * g)).clamp(-1., 1.)
+            }
+        };
+        let sin = (1. - cos * cos).max(0.).sqrt();
+        let (sin_phi, cos_phi) = (2. * consts::PI * sample.y).sin_cos();
+
+        let tangent = shapes::perpendicular(direction);
+        let bitangent = direction.cross(&tangent);
+        nalgebra::UnitVector3::new_normalize(
+            direction.into_inner() * cos
+                + (tangent.into_inner() * cos_phi + bitangent * sin_phi) * sin,
+        )
+    }
+}
@@ -0,0 +1,79 @@
+//! Time varying poses, for motion blur and rolling shutter.
+//!
+//! Rays carry the time they are cast at (see [`ray::Ray::with_time`]),
+//! moving cameras and objects look up their pose at that time.
+
+use crate::Float;
+use crate::ray;
+
+/// Pose over time, interpolated between keyframes and held before the first
+/// and after the last one.
+#[derive(Clone, Debug)]
+pub struct Trajectory {
+    // sorted by time
+    keyframes: Vec<(Float, nalgebra::Isometry3<Float>)>,
+}
+
+impl Trajectory {
+    /// `keyframes` as (time in s, pose), in increasing time
+    pub fn new(keyframes: Vec<(Float, nalgebra::Isometry3<Float>)>) -> Self {
+        debug_assert!(!keyframes.is_empty(), "trajectory needs at least one pose");
+        debug_assert!(
+            keyframes.windows(2).all(|k| k[0].0 < k[1].0),
+            "keyframe times must be increasing"
+        );
+        Self { keyframes }
+    }
+
+    pub fn fixed(pose: nalgebra::Isometry3<Float>) -> Self {
+        Self::new(vec![(0., pose)])
+    }
+
+    /// Constant linear and angular velocity from `pose` at time 0, up to `duration` s.
+    ///
+    /// # Arguments
+    /// - `velocity`: m/s, in the frame the pose is expressed in
+    /// - `angular_velocity`: axis times rad/s, in the same frame
+    pub fn constant_velocity(
+        pose: nalgebra::Isometry3<Float>,
+        velocity: &nalgebra::Vector3<Float>,
+        angular_velocity: &nalgebra::Vector3<Float>,
+        duration: Float,
+    ) -> Self {
+        debug_assert!(duration > 0., "duration can not be <= 0.0");
+        let end = nalgebra::Isometry3::from_parts(
+            (pose.translation.vector + velocity * duration).into(),
+            nalgebra::UnitQuaternion::new(angular_velocity * duration) * pose.rotation,
+        );
+        Self::new(vec![(0., pose), (duration, end)])
+    }
+
+    pub fn keyframes(&self) -> &[(Float, nalgebra::Isometry3<Float>)] {
+        &self.keyframes
+    }
+
+    /// whether the pose never changes
+    pub fn is_fixed(&self) -> bool {
+        self.keyframes.len() == 1
+    }
+
+    /// linear translation and spherical rotation interpolation between the keyframes around `time`
+    pub fn pose_at(&self, time: Float) -> nalgebra::Isometry3<Float> {
+        let upper = self.keyframes.partition_point(|(t, _)| *t < time);
+        if upper == 0 {
+            return self.keyframes[0].1;
+        }
+        if upper == self.keyframes.len() {
+            return self.keyframes[upper - 1].1;
+        }
+
+        let (t0, pose0) = &self.keyframes[upper - 1];
+        let (t1, pose1) = &self.keyframes[upper];
+        pose0.lerp_slerp(pose1, (time - t0) / (t1 - t0))
+    }
+
+    /// ray from the local frame into the parent frame, at the time of the ray
+    pub fn transform_ray(&self, ray: &ray::Ray) -> ray::Ray {
+        ray.transform(&self.pose_at(ray.time()))
+    }
+}
@@ -1,21 +1,63 @@
+use crate::Float;
+use crate::material;
+use crate::motion;
 use crate::ray;
 use crate::shapes;
 
 pub struct Object<T: shapes::Traceable> {
-    color: [u8; 3],
+    material: material::MaterialId,
     shape: T,
+    // object to world over time, the shape is in world coordinates without
+    motion: Option<motion::Trajectory>,
 }
 
 impl<T: shapes::Traceable> Object<T> {
-    pub fn new(shape: T, color: [u8; 3]) -> Self {
-        Self { color, shape }
+    pub fn new(shape: T, material: material::MaterialId) -> Self {
+        Self {
+            material,
+            shape,
+            motion: None,
+        }
     }
 
-    pub fn closest_ray_intersection(&self, ray: &ray::Ray, epsilon: f32) -> Option<f32> {
-        self.shape.trace(ray, epsilon)
+    /// Moves the object over time, the shape is then in object coordinates
+    /// and placed in the world by the pose at the time of each ray.
+    pub fn with_motion(mut self, trajectory: motion::Trajectory) -> Self {
+        self.motion = Some(trajectory);
+        self
     }
 
-    pub fn color(&self) -> [u8; 3] {
-        self.color
+    pub fn closest_ray_intersection(&self, ray: &ray::Ray) -> Option<Float> {
+        match self.motion.as_ref() {
+            // isometries keep distances, so the local distance is the world distance
+            Some(motion) => self
+                .shape
+                .trace(&ray.transform(&motion.pose_at(ray.time()).inverse())),
+            None => self.shape.trace(ray),
+        }
+    }
+
+    pub fn hit(&self, ray: &ray::Ray) -> Option<shapes::Hit> {
+        match self.motion.as_ref() {
+            Some(motion) => {
+                let pose = motion.pose_at(ray.time());
+                self.shape
+                    .hit(&ray.transform(&pose.inverse()))
+                    .map(|hit| hit.transform(&pose))
+            }
+            None => self.shape.hit(ray),
+        }
+    }
+
+    pub fn motion(&self) -> Option<&motion::Trajectory> {
+        self.motion.as_ref()
+    }
+
+    pub fn shape(&self) -> &T {
+        &self.shape
+    }
+
+    pub fn material(&self) -> material::MaterialId {
+        self.material
     }
 }
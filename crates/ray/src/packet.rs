@@ -0,0 +1,121 @@
+//! Packets of coherent rays, traced together.
+//!
+//! Rays are stored structure of arrays, one `[T; N]` lane array per
+//! component. The slab and triangle tests are branch free arithmetic loops
+//! over the lanes, masking misses instead of returning early, which leaves
+//! the compiler free to vectorize them for whatever the target offers
+//! (SSE/AVX on x86_64) on stable rust. Each lane computes exactly what the
+//! single ray path computes.
+
+use crate::Float;
+use crate::Real;
+use crate::ray;
+use crate::shapes::triangle;
+
+pub type RayPacket4 = RayPacket<4>;
+pub type RayPacket8 = RayPacket<8>;
+pub type RayPacket16 = RayPacket<16>;
+
+/// One value per ray in a packet
+pub type Lanes<T, const N: usize> = [T; N];
+
+pub struct RayPacket<const N: usize, T = Float> {
+    origin: [Lanes<T, N>; 3],
+    direction: [Lanes<T, N>; 3],
+    inv_direction: [Lanes<T, N>; 3],
+    // [row][column] of every lane's watertight triangle test transform
+    watertight_transform: [[Lanes<T, N>; 3]; 3],
+    t_min: Lanes<T, N>,
+    t_max: Lanes<T, N>,
+}
+
+impl<const N: usize, T: Real> RayPacket<N, T> {
+    pub fn new(rays: &[ray::Ray<T>; N]) -> Self {
+        let mut origin = [[T::zero(); N]; 3];
+        let mut direction = [[T::zero(); N]; 3];
+        let mut inv_direction = [[T::zero(); N]; 3];
+        let mut watertight_transform = [[[T::zero(); N]; 3]; 3];
+        let mut t_min = [T::zero(); N];
+        let mut t_max = [T::zero(); N];
+
+        for (lane, ray) in rays.iter().enumerate() {
+            for axis in 0..3 {
+                origin[axis][lane] = ray.origin()[axis];
+                direction[axis][lane] = ray.direction()[axis];
+                inv_direction[axis][lane] = T::one() / ray.direction()[axis];
+            }
+            let transform = triangle::watertight_transform(ray.direction());
+            for (row, coefficients) in transform.iter().enumerate() {
+                for (column, coefficient) in coefficients.iter().enumerate() {
+                    watertight_transform[row][column][lane] = *coefficient;
+                }
+            }
+            t_min[lane] = ray.t_min();
+            t_max[lane] = ray.t_max();
+        }
+
+        Self {
+            origin,
+            direction,
+            inv_direction,
+            watertight_transform,
+            t_min,
+            t_max,
+        }
+    }
+
+    /// Packs up to `N` rays, unused lanes repeat the last ray.
+    ///
+    /// Useful for the remainder when chunking an image into packets.
+    pub fn from_slice(rays: &[ray::Ray<T>]) -> Self {
+        debug_assert!(!rays.is_empty(), "packet needs at least one ray");
+        debug_assert!(rays.len() <= N, "too many rays for packet");
+
+        Self::new(&std::array::from_fn(|lane| rays[lane.min(rays.len() - 1)]))
+    }
+
+    /// every lane's ray, without differentials
+    pub fn ray(&self, lane: usize) -> ray::Ray<T> {
+        ray::Ray::new(
+            nalgebra::Vector3::new(
+                self.origin[0][lane],
+                self.origin[1][lane],
+                self.origin[2][lane],
+            ),
+            nalgebra::UnitVector3::new_unchecked(nalgebra::Vector3::new(
+                self.direction[0][lane],
+                self.direction[1][lane],
+                self.direction[2][lane],
+            )),
+        )
+        .with_interval(self.t_min[lane], self.t_max[lane])
+    }
+
+    /// origin components, `[x, y, z]` lanes
+    pub fn origin(&self) -> &[Lanes<T, N>; 3] {
+        &self.origin
+    }
+
+    /// direction components, `[x, y, z]` lanes
+    pub fn direction(&self) -> &[Lanes<T, N>; 3] {
+        &self.direction
+    }
+
+    /// reciprocal direction components, `[x, y, z]` lanes
+    pub fn inv_direction(&self) -> &[Lanes<T, N>; 3] {
+        &self.inv_direction
+    }
+
+    /// see [`triangle::watertight_transform`]
+    pub(crate) fn watertight_transform(&self) -> &[[Lanes<T, N>; 3]; 3] {
+        &self.watertight_transform
+    }
+
+    pub fn t_min(&self) -> &Lanes<T, N> {
+        &self.t_min
+    }
+
+    pub fn t_max(&self) -> &Lanes<T, N> {
+        &self.t_max
+    }
+}
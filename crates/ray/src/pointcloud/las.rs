@@ -0,0 +1,160 @@
+//! [LAS 1.2](https://www.asprs.org/wp-content/uploads/2010/12/asprs_las_format_v12.pdf) export
+//!
+//! Points are written as point data format 0, or 2 when the cloud has colors.
+//! Object ids are stored in the point source id (saturating at `u16::MAX`),
+//! normals are not representable and are dropped.
+
+use std::io;
+
+const HEADER_SIZE: u16 = 227;
+
+/// # Arguments
+/// - `scale`: resolution of the stored coordinates in meters, e.g. `0.001`
+pub fn write(cloud: &super::PointCloud, writer: &mut impl io::Write, scale: f64) -> io::Result<()> {
+    debug_assert!(scale > 0.0, "scale can not be <= 0.0");
+
+    let (point_format, record_length): (u8, u16) = match cloud.colors() {
+        Some(_) => (2, 26),
+        None => (0, 20),
+    };
+    let (min, max) = cloud
+        .bounds()
+        .map(|(min, max)| (min.cast::<f64>(), max.cast::<f64>()))
+        .unwrap_or_default();
+    let offset = min;
+
+    // header
+    writer.write_all(b"LASF")?;
+    writer.write_all(&0u16.to_le_bytes())?; // file source id
+    writer.write_all(&0u16.to_le_bytes())?; // global encoding
+    writer.write_all(&[0; 16])?; // project id
+    writer.write_all(&[1, 2])?; // version
+    writer.write_all(&fixed_str::<32>("OTHER"))?;
+    writer.write_all(&fixed_str::<32>("ray"))?;
+    writer.write_all(&0u16.to_le_bytes())?; // creation day of year
+    writer.write_all(&0u16.to_le_bytes())?; // creation year
+    writer.write_all(&HEADER_SIZE.to_le_bytes())?;
+    writer.write_all(&(HEADER_SIZE as u32).to_le_bytes())?; // offset to point data
+    writer.write_all(&0u32.to_le_bytes())?; // number of variable length records
+    writer.write_all(&[point_format])?;
+    writer.write_all(&record_length.to_le_bytes())?;
+    writer.write_all(&(cloud.len() as u32).to_le_bytes())?;
+    writer.write_all(&(cloud.len() as u32).to_le_bytes())?; // points by return, all first
+    writer.write_all(&[0; 16])?;
+    for _ in 0..3 {
+        writer.write_all(&scale.to_le_bytes())?;
+    }
+    for v in offset.iter() {
+        writer.write_all(&v.to_le_bytes())?;
+    }
+    for (max, min) in max.iter().zip(min.iter()) {
+        writer.write_all(&max.to_le_bytes())?;
+        writer.write_all(&min.to_le_bytes())?;
+    }
+
+    // points
+    for i in 0..cloud.len() {
+        let position = cloud.positions()[i].cast::<f64>();
+        for (v, offset) in position.iter().zip(offset.iter()) {
+            let scaled = ((v - offset) / scale).round() as i32;
+            writer.write_all(&scaled.to_le_bytes())?;
+        }
+        writer.write_all(&0u16.to_le_bytes())?; // intensity
+        writer.write_all(&[0b001_001])?; // return 1 of 1
+        writer.write_all(&[0])?; // classification: created, never classified
+        writer.write_all(&[0])?; // scan angle rank
+        writer.write_all(&[0])?; // user data
+        let source_id = cloud
+            .object_ids()
+            .map(|ids| u16::try_from(ids[i]).unwrap_or(u16::MAX))
+            .unwrap_or(0);
+        writer.write_all(&source_id.to_le_bytes())?;
+
+        if let Some(colors) = cloud.colors() {
+            for channel in colors[i] {
+                writer.write_all(&(channel as u16 * 257).to_le_bytes())?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn fixed_str<const N: usize>(s: &str) -> [u8; N] {
+    let mut bytes = [0; N];
+    let len = s.len().min(N);
+    bytes[..len].copy_from_slice(&s.as_bytes()[..len]);
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_cloud(cloud: &super::super::PointCloud) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        write(cloud, &mut bytes, 0.001).unwrap();
+        bytes
+    }
+
+    fn u16_at(bytes: &[u8], offset: usize) -> u16 {
+        u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+    }
+
+    fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    fn f64_at(bytes: &[u8], offset: usize) -> f64 {
+        f64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+    }
+
+    #[test]
+    fn header_and_records() {
+        let cloud = super::super::PointCloud::new(vec![
+            nalgebra::Vector3::new(1., 2., 3.),
+            nalgebra::Vector3::new(-1., 0.5, 4.25),
+        ])
+        .with_object_ids(vec![7, 70_000]);
+        let bytes = write_cloud(&cloud);
+
+        assert_eq!(&bytes[..4], b"LASF");
+        assert_eq!(&bytes[24..26], &[1, 2]);
+        assert_eq!(u16_at(&bytes, 94), 227);
+        assert_eq!(u32_at(&bytes, 96), 227);
+        assert_eq!(bytes[104], 0);
+        assert_eq!(u16_at(&bytes, 105), 20);
+        assert_eq!(u32_at(&bytes, 107), 2);
+        assert_eq!(bytes.len(), 227 + 2 * 20);
+
+        // scale, offset at the minimum, then max and min per axis
+        assert_eq!(f64_at(&bytes, 131), 0.001);
+        assert_eq!(f64_at(&bytes, 155), -1.);
+        assert_eq!(f64_at(&bytes, 179), 1.);
+        assert_eq!(f64_at(&bytes, 211), 4.25);
+        assert_eq!(f64_at(&bytes, 219), 3.);
+
+        let second = &bytes[227 + 20..];
+        let coordinate =
+            |i: usize| i32::from_le_bytes(second[4 * i..4 * i + 4].try_into().unwrap());
+        assert_eq!([coordinate(0), coordinate(1), coordinate(2)], [0, 0, 1250]);
+        assert_eq!(u16_at(second, 18), u16::MAX);
+        assert_eq!(u16_at(&bytes[227..], 18), 7);
+    }
+
+    #[test]
+    fn colors_use_point_format_2() {
+        let cloud = super::super::PointCloud::new(vec![nalgebra::Vector3::zeros()])
+            .with_colors(vec![[255, 128, 0]]);
+        let bytes = write_cloud(&cloud);
+
+        assert_eq!(bytes[104], 2);
+        assert_eq!(u16_at(&bytes, 105), 26);
+        assert_eq!(bytes.len(), 227 + 26);
+        let point = &bytes[227..];
+        assert_eq!(
+            [u16_at(point, 20), u16_at(point, 22), u16_at(point, 24)],
+            [u16::MAX, 128 * 257, 0]
+        );
+    }
+}
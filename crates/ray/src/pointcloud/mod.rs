@@ -0,0 +1,214 @@
+use crate::Float;
+use crate::projection;
+use crate::ray;
+
+pub mod las;
+pub mod pcd;
+pub mod ply;
+
+/// A set of 3D points with optional per point attributes.
+///
+/// Points are stored in whatever frame they were created in; depth renders
+/// come out in the camera frame (x right, y down, z away from the camera)
+/// and can be moved into the world frame with [`PointCloud::transform_in_place`].
+pub struct PointCloud {
+    positions: Vec<nalgebra::Vector3<Float>>,
+    // index of the pixel / beam each point was created from
+    samples: Vec<usize>,
+    colors: Option<Vec<[u8; 3]>>,
+    normals: Option<Vec<nalgebra::UnitVector3<Float>>>,
+    object_ids: Option<Vec<u32>>,
+}
+
+impl PointCloud {
+    pub fn new(positions: Vec<nalgebra::Vector3<Float>>) -> Self {
+        let samples = (0..positions.len()).collect();
+        Self {
+            positions,
+            samples,
+            colors: None,
+            normals: None,
+            object_ids: None,
+        }
+    }
+
+    /// Creates a point cloud in the camera frame from per pixel distances
+    /// along each pixel's ray, as produced by the tracing examples.
+    ///
+    /// `ranges` is row major, `None` pixels are skipped.
+    pub fn from_range_image<P: projection::Projection>(
+        projection: &P,
+        ranges: &[Option<Float>],
+    ) -> Self {
+        Self::from_image(projection, ranges, |ray, range| ray.at(range))
+    }
+
+    /// Creates a point cloud in the camera frame from per pixel depths
+    /// along the optical axis (z).
+    ///
+    /// `depths` is row major, `None` pixels are skipped.
+    pub fn from_depth_image<P: projection::Projection>(
+        projection: &P,
+        depths: &[Option<Float>],
+    ) -> Self {
+        Self::from_image(projection, depths, |ray, depth| {
+            ray.at((depth - ray.origin().z) / ray.direction().z)
+        })
+    }
+
+    fn from_image<P: projection::Projection>(
+        projection: &P,
+        values: &[Option<Float>],
+        to_point: impl Fn(&ray::Ray, Float) -> nalgebra::Vector3<Float>,
+    ) -> Self {
+        debug_assert_eq!(
+            values.len(),
+            (projection.width_px() * projection.height_px()) as usize,
+            "image does not match camera size"
+        );
+
+        let (samples, positions) = values
+            .iter()
+            .enumerate()
+            .filter_map(|(i, value)| value.map(|value| (i, value)))
+            .filter_map(|(i, value)| {
+                let row = i as u32 / projection.width_px();
+                let col = i as u32 % projection.width_px();
+                let ray = projection.pixel_ray(col as Float, row as Float)?;
+                Some((i, to_point(&ray, value)))
+            })
+            .unzip();
+
+        Self {
+            positions,
+            samples,
+            colors: None,
+            normals: None,
+            object_ids: None,
+        }
+    }
+
+    /// Creates a point cloud from a scan of beams leaving `origin`.
+    ///
+    /// `None` ranges (no return) are skipped.
+    pub fn from_scan(
+        origin: &nalgebra::Vector3<Float>,
+        directions: &[nalgebra::UnitVector3<Float>],
+        ranges: &[Option<Float>],
+    ) -> Self {
+        debug_assert_eq!(directions.len(), ranges.len(), "every beam needs a range");
+
+        let (samples, positions) = directions
+            .iter()
+            .zip(ranges)
+            .enumerate()
+            .filter_map(|(i, (direction, range))| {
+                range.map(|range| (i, origin + direction.into_inner() * range))
+            })
+            .unzip();
+
+        Self {
+            positions,
+            samples,
+            colors: None,
+            normals: None,
+            object_ids: None,
+        }
+    }
+
+    /// one color per point
+    pub fn with_colors(mut self, colors: Vec<[u8; 3]>) -> Self {
+        debug_assert_eq!(colors.len(), self.len(), "one color per point");
+        self.colors = Some(colors);
+        self
+    }
+
+    /// one normal per point
+    pub fn with_normals(mut self, normals: Vec<nalgebra::UnitVector3<Float>>) -> Self {
+        debug_assert_eq!(normals.len(), self.len(), "one normal per point");
+        self.normals = Some(normals);
+        self
+    }
+
+    /// one object id per point
+    pub fn with_object_ids(mut self, object_ids: Vec<u32>) -> Self {
+        debug_assert_eq!(object_ids.len(), self.len(), "one object id per point");
+        self.object_ids = Some(object_ids);
+        self
+    }
+
+    /// colors indexed by the pixel / beam the points were created from
+    pub fn with_sample_colors(self, colors: &[[u8; 3]]) -> Self {
+        let colors = self.gather(colors);
+        self.with_colors(colors)
+    }
+
+    /// normals indexed by the pixel / beam the points were created from
+    pub fn with_sample_normals(self, normals: &[nalgebra::UnitVector3<Float>]) -> Self {
+        let normals = self.gather(normals);
+        self.with_normals(normals)
+    }
+
+    /// object ids indexed by the pixel / beam the points were created from
+    pub fn with_sample_object_ids(self, object_ids: &[u32]) -> Self {
+        let object_ids = self.gather(object_ids);
+        self.with_object_ids(object_ids)
+    }
+
+    fn gather<T: Copy>(&self, values: &[T]) -> Vec<T> {
+        self.samples.iter().map(|i| values[*i]).collect()
+    }
+
+    /// moves the points (and normals) into another frame,
+    /// e.g. camera to world
+    pub fn transform_in_place(&mut self, transform: &nalgebra::Isometry3<Float>) {
+        self.positions
+            .iter_mut()
+            .for_each(|p| *p = transform.transform_point(&(*p).into()).coords);
+
+        if let Some(normals) = self.normals.as_mut() {
+            normals
+                .iter_mut()
+                .for_each(|n| *n = transform.rotation * *n);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    pub fn positions(&self) -> &[nalgebra::Vector3<Float>] {
+        &self.positions
+    }
+
+    /// index of the pixel / beam each point was created from
+    pub fn samples(&self) -> &[usize] {
+        &self.samples
+    }
+
+    pub fn colors(&self) -> Option<&[[u8; 3]]> {
+        self.colors.as_deref()
+    }
+
+    pub fn normals(&self) -> Option<&[nalgebra::UnitVector3<Float>]> {
+        self.normals.as_deref()
+    }
+
+    pub fn object_ids(&self) -> Option<&[u32]> {
+        self.object_ids.as_deref()
+    }
+
+    /// axis aligned (min, max) of the points
+    pub fn bounds(&self) -> Option<(nalgebra::Vector3<Float>, nalgebra::Vector3<Float>)> {
+        let first = self.positions.first()?;
+        Some(
+            self.positions
+                .iter()
+                .fold((*first, *first), |(min, max), p| (min.inf(p), max.sup(p))),
+        )
+    }
+}
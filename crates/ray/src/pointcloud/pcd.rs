@@ -0,0 +1,89 @@
+//! [PCD v0.7](https://pointclouds.org/documentation/tutorials/pcd_file_format.html) export
+
+use std::io;
+
+#[derive(Copy, Clone, Debug)]
+pub enum Data {
+    Ascii,
+    Binary,
+}
+
+pub fn write(cloud: &super::PointCloud, writer: &mut impl io::Write, data: Data) -> io::Result<()> {
+    let mut fields = vec!["x", "y", "z"];
+    let mut sizes = vec!["4", "4", "4"];
+    let mut types = vec!["F", "F", "F"];
+    if cloud.normals().is_some() {
+        fields.extend(["normal_x", "normal_y", "normal_z"]);
+        sizes.extend(["4", "4", "4"]);
+        types.extend(["F", "F", "F"]);
+    }
+    if cloud.colors().is_some() {
+        // pcl packs rgb into the bits of a single 4 byte field
+        fields.push("rgb");
+        sizes.push("4");
+        types.push("U");
+    }
+    if cloud.object_ids().is_some() {
+        fields.push("label");
+        sizes.push("4");
+        types.push("U");
+    }
+
+    writeln!(writer, "# .PCD v0.7 - Point Cloud Data file format")?;
+    writeln!(writer, "VERSION 0.7")?;
+    writeln!(writer, "FIELDS {}", fields.join(" "))?;
+    writeln!(writer, "SIZE {}", sizes.join(" "))?;
+    writeln!(writer, "TYPE {}", types.join(" "))?;
+    writeln!(writer, "COUNT {}", vec!["1"; fields.len()].join(" "))?;
+    writeln!(writer, "WIDTH {}", cloud.len())?;
+    writeln!(writer, "HEIGHT 1")?;
+    writeln!(writer, "VIEWPOINT 0 0 0 1 0 0 0")?;
+    writeln!(writer, "POINTS {}", cloud.len())?;
+    match data {
+        Data::Ascii => writeln!(writer, "DATA ascii")?,
+        Data::Binary => writeln!(writer, "DATA binary")?,
+    }
+
+    for i in 0..cloud.len() {
+        let position = cloud.positions()[i];
+        let normal = cloud.normals().map(|normals| normals[i]);
+        let rgb = cloud
+            .colors()
+            .map(|colors| u32::from_be_bytes([0, colors[i][0], colors[i][1], colors[i][2]]));
+        let label = cloud.object_ids().map(|ids| ids[i]);
+
+        match data {
+            Data::Ascii => {
+                write!(writer, "{} {} {}", position.x, position.y, position.z)?;
+                if let Some(n) = normal {
+                    write!(writer, " {} {} {}", n.x, n.y, n.z)?;
+                }
+                if let Some(rgb) = rgb {
+                    write!(writer, " {rgb}")?;
+                }
+                if let Some(label) = label {
+                    write!(writer, " {label}")?;
+                }
+                writeln!(writer)?;
+            }
+            Data::Binary => {
+                for v in position.iter() {
+                    writer.write_all(&v.to_le_bytes())?;
+                }
+                if let Some(n) = normal {
+                    for v in n.iter() {
+                        writer.write_all(&v.to_le_bytes())?;
+                    }
+                }
+                if let Some(rgb) = rgb {
+                    writer.write_all(&rgb.to_le_bytes())?;
+                }
+                if let Some(label) = label {
+                    writer.write_all(&label.to_le_bytes())?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,82 @@
+//! [PLY](https://paulbourke.net/dataformats/ply/) export
+
+use std::io;
+
+#[derive(Copy, Clone, Debug)]
+pub enum Encoding {
+    Ascii,
+    BinaryLittleEndian,
+}
+
+pub fn write(
+    cloud: &super::PointCloud,
+    writer: &mut impl io::Write,
+    encoding: Encoding,
+) -> io::Result<()> {
+    let format = match encoding {
+        Encoding::Ascii => "ascii",
+        Encoding::BinaryLittleEndian => "binary_little_endian",
+    };
+
+    writeln!(writer, "ply")?;
+    writeln!(writer, "format {format} 1.0")?;
+    writeln!(writer, "element vertex {}", cloud.len())?;
+    writeln!(writer, "property float x")?;
+    writeln!(writer, "property float y")?;
+    writeln!(writer, "property float z")?;
+    if cloud.normals().is_some() {
+        writeln!(writer, "property float nx")?;
+        writeln!(writer, "property float ny")?;
+        writeln!(writer, "property float nz")?;
+    }
+    if cloud.colors().is_some() {
+        writeln!(writer, "property uchar red")?;
+        writeln!(writer, "property uchar green")?;
+        writeln!(writer, "property uchar blue")?;
+    }
+    if cloud.object_ids().is_some() {
+        writeln!(writer, "property uint object_id")?;
+    }
+    writeln!(writer, "end_header")?;
+
+    for i in 0..cloud.len() {
+        let position = cloud.positions()[i];
+        let normal = cloud.normals().map(|normals| normals[i]);
+        let color = cloud.colors().map(|colors| colors[i]);
+        let object_id = cloud.object_ids().map(|ids| ids[i]);
+
+        match encoding {
+            Encoding::Ascii => {
+                write!(writer, "{} {} {}", position.x, position.y, position.z)?;
+                if let Some(n) = normal {
+                    write!(writer, " {} {} {}", n.x, n.y, n.z)?;
+                }
+                if let Some([r, g, b]) = color {
+                    write!(writer, " {r} {g} {b}")?;
+                }
+                if let Some(id) = object_id {
+                    write!(writer, " {id}")?;
+                }
+                writeln!(writer)?;
+            }
+            Encoding::BinaryLittleEndian => {
+                for v in position.iter() {
+                    writer.write_all(&v.to_le_bytes())?;
+                }
+                if let Some(n) = normal {
+                    for v in n.iter() {
+                        writer.write_all(&v.to_le_bytes())?;
+                    }
+                }
+                if let Some(color) = color {
+                    writer.write_all(&color)?;
+                }
+                if let Some(id) = object_id {
+                    writer.write_all(&id.to_le_bytes())?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,531 @@
+//! Polarization state of light along a ray.
+//!
+//! A [`Stokes`] vector is only meaningful together with the reference axis
+//! it is measured against, so it carries its own x axis perpendicular to the
+//! direction of propagation. [`Mueller`] matrices act on Stokes vectors in a
+//! given frame; [`reflect`] and [`refract`] rotate into the s/p frame of the
+//! interface before applying the Fresnel equations. Rays carrying light
+//! continue through surfaces with [`reflect_ray`] and [`refract_ray`], and
+//! [`render`] gives the degree and angle of linear polarization seen by a
+//! polarimetric camera.
+
+use crate::Float;
+use crate::angle::Angle;
+use crate::color;
+use crate::light;
+use crate::material;
+use crate::projection;
+use crate::random;
+use crate::ray;
+use crate::shapes;
+
+/// Stokes parameters (I, Q, U, V), Q and U relative to `x_axis`
+#[derive(Copy, Clone, Debug)]
+pub struct Stokes {
+    parameters: nalgebra::Vector4<Float>,
+    x_axis: nalgebra::UnitVector3<Float>,
+}
+
+impl Stokes {
+    /// # Arguments
+    /// - `x_axis`: reference axis, perpendicular to the direction of propagation
+    pub fn new(parameters: nalgebra::Vector4<Float>, x_axis: nalgebra::UnitVector3<Float>) -> Self {
+        debug_assert!(
+            parameters.fixed_rows::<3>(1).norm() <= parameters[0] * (1. + 1e-4),
+            "polarized intensity can not exceed the total intensity"
+        );
+        Self { parameters, x_axis }
+    }
+
+    pub fn unpolarized(intensity: Float, direction: &nalgebra::UnitVector3<Float>) -> Self {
+        Self::new(
+            nalgebra::Vector4::new(intensity, 0., 0., 0.),
+            shapes::perpendicular(direction),
+        )
+    }
+
+    /// fully linearly polarized light, `angle` from `x_axis`
+    pub fn linear(intensity: Float, angle: Angle, x_axis: nalgebra::UnitVector3<Float>) -> Self {
+        let (sin, cos) = (2. * angle.rads()).sin_cos();
+        Self::new(
+            nalgebra::Vector4::new(intensity, intensity * cos, intensity * sin, 0.),
+            x_axis,
+        )
+    }
+
+    pub fn parameters(&self) -> &nalgebra::Vector4<Float> {
+        &self.parameters
+    }
+
+    pub fn x_axis(&self) -> &nalgebra::UnitVector3<Float> {
+        &self.x_axis
+    }
+
+    pub fn intensity(&self) -> Float {
+        self.parameters[0]
+    }
+
+    pub fn degree_of_polarization(&self) -> Float {
+        self.ratio(self.parameters.fixed_rows::<3>(1).norm())
+    }
+
+    /// DoLP, [0, 1]
+    pub fn degree_of_linear_polarization(&self) -> Float {
+        self.ratio(self.parameters[1].hypot(self.parameters[2]))
+    }
+
+    /// AoLP, [-90°, 90°] from `x_axis`, rotate with [`Stokes::in_frame`] for a camera's axis
+    pub fn angle_of_linear_polarization(&self) -> Angle {
+        Angle::from_rads(0.5 * self.parameters[2].atan2(self.parameters[1]))
+    }
+
+    fn ratio(&self, polarized: Float) -> Float {
+        match self.parameters[0] > 0. {
+            true => (polarized / self.parameters[0]).min(1.),
+            false => 0.,
+        }
+    }
+
+    /// The same light, measured against another reference axis.
+    ///
+    /// # Arguments
+    /// - `x_axis`: new reference axis, perpendicular to `direction`
+    /// - `direction`: direction of propagation
+    pub fn in_frame(
+        &self,
+        x_axis: &nalgebra::UnitVector3<Float>,
+        direction: &nalgebra::UnitVector3<Float>,
+    ) -> Self {
+        let angle = Angle::from_rads(
+            self.x_axis
+                .cross(x_axis)
+                .dot(direction)
+                .atan2(self.x_axis.dot(x_axis)),
+        );
+        Self {
+            parameters: Mueller::rotation(angle).matrix() * self.parameters,
+            x_axis: *x_axis,
+        }
+    }
+
+    /// apply an optical element defined in the frame of this vector
+    pub fn transformed(&self, mueller: &Mueller) -> Self {
+        Self {
+            parameters: mueller.matrix() * self.parameters,
+            x_axis: self.x_axis,
+        }
+    }
+
+    pub fn scaled(&self, factor: Float) -> Self {
+        Self {
+            parameters: self.parameters * factor,
+            x_axis: self.x_axis,
+        }
+    }
+
+    /// Adds light measured against any frame, expressed in the frame of `self`.
+    pub fn add(&self, other: &Self, direction: &nalgebra::UnitVector3<Float>) -> Self {
+        Self {
+            parameters: self.parameters + other.in_frame(&self.x_axis, direction).parameters,
+            x_axis: self.x_axis,
+        }
+    }
+
+    pub(crate) fn transform(&self, rotation: &nalgebra::UnitQuaternion<Float>) -> Self {
+        Self {
+            parameters: self.parameters,
+            x_axis: rotation * self.x_axis,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Mueller(nalgebra::Matrix4<Float>);
+
+impl Mueller {
+    pub fn new(matrix: nalgebra::Matrix4<Float>) -> Self {
+        Self(matrix)
+    }
+
+    pub fn identity() -> Self {
+        Self(nalgebra::Matrix4::identity())
+    }
+
+    pub fn matrix(&self) -> &nalgebra::Matrix4<Float> {
+        &self.0
+    }
+
+    /// rotates the reference axis by `angle`, counter clockwise looking against the direction of propagation
+    pub fn rotation(angle: Angle) -> Self {
+        let (sin, cos) = (2. * angle.rads()).sin_cos();
+        #[rustfmt::skip]
+        let m = nalgebra::Matrix4::new(
+            1., 0., 0., 0.,
+            0., cos, sin, 0.,
+            0., -sin, cos, 0.,
+            0., 0., 0., 1.,
+        );
+        Self(m)
+    }
+
+    /// ideal linear polarizer with its transmission axis `angle` from the x axis
+    pub fn linear_polarizer(angle: Angle) -> Self {
+        let (sin, cos) = (2. * angle.rads()).sin_cos();
+        #[rustfmt::skip]
+        let m = nalgebra::Matrix4::new(
+            1., cos, sin, 0.,
+            cos, cos * cos, cos * sin, 0.,
+            sin, sin * cos, sin * sin, 0.,
+            0., 0., 0., 0.,
+        ) * 0.5;
+        Self(m)
+    }
+
+    /// Fresnel reflection in the s/p frame, x along s (perpendicular to the plane of incidence).
+    ///
+    /// # Arguments
+    /// - `cos_incident`: cosine of the angle between the incident ray and the normal, [0, 1]
+    /// - `refractive_index`: complex ratio of transmitted to incident refractive index
+    pub fn fresnel_reflection(
+        cos_incident: Float,
+        refractive_index: nalgebra::Complex<Float>,
+    ) -> Self {
+        let (s, p) = crate::fresnel_amplitudes(cos_incident, refractive_index);
+        Self::from_amplitudes(s, p, 1.)
+    }
+
+    /// Fresnel transmission into a dielectric in the s/p frame, x along s,
+    /// `None` on total internal reflection.
+    ///
+    /// Scaled by the transmitted power, not the radiance; the n² change in
+    /// radiance across the interface is left to the caller.
+    ///
+    /// # Arguments
+    /// - `cos_incident`: cosine of the angle between the incident ray and the normal, [0, 1]
+    /// - `refractive_index`: ratio of transmitted to incident refractive index
+    pub fn fresnel_transmission(cos_incident: Float, refractive_index: Float) -> Option<Self> {
+        let cos_i = cos_incident.clamp(0., 1.);
+        let sin2_t = (1. - cos_i * cos_i) / (refractive_index * refractive_index);
+        if sin2_t > 1. {
+            return None;
+        }
+        let cos_t = (1. - sin2_t).sqrt();
+
+        let s = 2. * cos_i / (cos_i + refractive_index * cos_t);
+        let p = 2. * cos_i / (refractive_index * cos_i + cos_t);
+        let power = match cos_i > 0. {
+            true => refractive_index * cos_t / cos_i,
+            false => 0.,
+        };
+        Some(Self::from_amplitudes(s.into(), p.into(), power))
+    }
+
+    fn from_amplitudes(
+        s: nalgebra::Complex<Float>,
+        p: nalgebra::Complex<Float>,
+        scale: Float,
+    ) -> Self {
+        let (ss, pp) = (s.norm_sqr(), p.norm_sqr());
+        let sp = s * p.conj();
+        #[rustfmt::skip]
+        let m = nalgebra::Matrix4::new(
+            ss + pp, ss - pp, 0., 0.,
+            ss - pp, ss + pp, 0., 0.,
+            0., 0., 2. * sp.re, 2. * sp.im,
+            0., 0., -2. * sp.im, 2. * sp.re,
+        ) * (0.5 * scale);
+        Self(m)
+    }
+}
+
+impl std::ops::Mul for Mueller {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        Self(self.0 * rhs.0)
+    }
+}
+
+/// s axis of the plane of incidence, falls back to any perpendicular axis at normal incidence
+fn s_axis(
+    direction: &nalgebra::UnitVector3<Float>,
+    normal: &nalgebra::UnitVector3<Float>,
+) -> nalgebra::UnitVector3<Float> {
+    nalgebra::UnitVector3::try_new(direction.cross(normal), Float::EPSILON)
+        .unwrap_or_else(|| shapes::perpendicular(direction))
+}
+
+/// Polarization of light specularly reflected off a surface, with the
+/// reflected direction.
+///
+/// # Arguments
+/// - `direction`: direction the light travels in, towards the surface
+/// - `normal`: surface normal, either side
+/// - `refractive_index`: complex ratio of the index behind the surface to the index in front
+pub fn reflect(
+    stokes: &Stokes,
+    direction: &nalgebra::UnitVector3<Float>,
+    normal: &nalgebra::UnitVector3<Float>,
+    refractive_index: nalgebra::Complex<Float>,
+) -> (Stokes, nalgebra::UnitVector3<Float>) {
+    let s = s_axis(direction, normal);
+    let cos_incident = direction.dot(normal).abs();
+    let reflected = nalgebra::UnitVector3::new_normalize(crate::reflection(direction, normal));
+
+    let stokes = stokes
+        .in_frame(&s, direction)
+        .transformed(&Mueller::fresnel_reflection(cos_incident, refractive_index));
+    (stokes, reflected)
+}
+
+/// Polarization of light refracted into a dielectric, with the transmitted
+/// direction, `None` on total internal reflection.
+///
+/// # Arguments
+/// - `direction`: direction the light travels in, towards the surface
+/// - `normal`: outward surface normal
+/// - `refractive_index`: ratio of the index inside to the index outside
+pub fn refract(
+    stokes: &Stokes,
+    direction: &nalgebra::UnitVector3<Float>,
+    normal: &nalgebra::UnitVector3<Float>,
+    refractive_index: Float,
+) -> Option<(Stokes, nalgebra::UnitVector3<Float>)> {
+    // leaving the object, flip the interface around
+    let (normal, eta) = match direction.dot(normal) > 0. {
+        true => (-*normal, 1. / refractive_index),
+        false => (*normal, refractive_index),
+    };
+    let cos_incident = -direction.dot(&normal);
+    let mueller = Mueller::fresnel_transmission(cos_incident, eta)?;
+
+    let cos_t = (1. - (1. - cos_incident * cos_incident) / (eta * eta)).sqrt();
+    let transmitted = nalgebra::UnitVector3::new_normalize(
+        direction.into_inner() / eta + normal.into_inner() * (cos_incident / eta - cos_t),
+    );
+
+    let s = s_axis(direction, &normal);
+    Some((
+        stokes.in_frame(&s, direction).transformed(&mueller),
+        transmitted,
+    ))
+}
+
+/// The ray continuing from a mirror reflection at `hit`, carrying the
+/// reflected light when `ray` carries any.
+///
+/// # Arguments
+/// - `refractive_index`: complex ratio of the index behind the surface to the index in front
+pub fn reflect_ray(
+    ray: &ray::Ray,
+    hit: &shapes::Hit,
+    refractive_index: nalgebra::Complex<Float>,
+) -> ray::Ray {
+    let direction = ray.unit_direction();
+    let reflected = nalgebra::UnitVector3::new_normalize(crate::reflection(direction, &hit.normal));
+    let continued = ray::Ray::spawn(&hit.point, &hit.normal, reflected).with_time(ray.time());
+    match ray.polarization() {
+        Some(stokes) => {
+            continued.with_polarization(reflect(stokes, direction, &hit.normal, refractive_index).0)
+        }
+        None => continued,
+    }
+}
+
+/// The ray continuing through a dielectric surface at `hit`, carrying the
+/// transmitted light when `ray` carries any, `None` on total internal
+/// reflection.
+///
+/// # Arguments
+/// - `refractive_index`: ratio of the index inside to the index outside
+pub fn refract_ray(ray: &ray::Ray, hit: &shapes::Hit, refractive_index: Float) -> Option<ray::Ray> {
+    let direction = ray.unit_direction();
+    let stokes = ray
+        .polarization()
+        .copied()
+        .unwrap_or_else(|| Stokes::unpolarized(0., direction));
+    let (stokes, transmitted) = refract(&stokes, direction, &hit.normal, refractive_index)?;
+    let continued = ray::Ray::spawn(&hit.point, &hit.normal, transmitted).with_time(ray.time());
+    Some(match ray.polarization() {
+        Some(_) => continued.with_polarization(stokes),
+        None => continued,
+    })
+}
+
+/// Light reaching a polarimetric camera, one Stokes vector per pixel, row
+/// major, measured against the x axis of the camera.
+pub struct PolarizationImage {
+    width: u32,
+    height: u32,
+    // none where the projection covers nothing
+    stokes: Vec<Option<Stokes>>,
+}
+
+impl PolarizationImage {
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn stokes(&self) -> &[Option<Stokes>] {
+        &self.stokes
+    }
+
+    /// luminance per pixel, 0 where the projection covers nothing
+    pub fn intensity(&self) -> Vec<Float> {
+        self.map(Stokes::intensity)
+    }
+
+    /// DoLP per pixel, [0, 1], 0 where the projection covers nothing
+    pub fn degree_of_linear_polarization(&self) -> Vec<Float> {
+        self.map(Stokes::degree_of_linear_polarization)
+    }
+
+    /// AoLP per pixel in radians, [-π/2, π/2] from the x axis of the camera,
+    /// 0 where the projection covers nothing
+    pub fn angle_of_linear_polarization(&self) -> Vec<Float> {
+        self.map(|stokes| stokes.angle_of_linear_polarization().rads())
+    }
+
+    fn map(&self, f: impl Fn(&Stokes) -> Float) -> Vec<Float> {
+        self.stokes
+            .iter()
+            .map(|stokes| stokes.as_ref().map_or(0., &f))
+            .collect()
+    }
+}
+
+/// Polarimetric render of a scene under unpolarized lights.
+///
+/// Follows mirror reflection and refraction through dielectrics up to
+/// `max_bounces` deep, applying the Fresnel equations as Mueller matrices
+/// in the plane of incidence of every surface. Surfaces are taken as smooth
+/// for these paths, diffusely scattered light and emission are unpolarized.
+/// Intensities are luminance.
+///
+/// # Arguments
+/// - `camera_pose`: camera to world
+/// - `closest_hit`: first surface a world ray hits, with its material, also used for shadow rays
+pub fn render<P: projection::Projection>(
+    projection: &P,
+    camera_pose: &nalgebra::Isometry3<Float>,
+    materials: &material::MaterialLibrary,
+    lights: &[light::Light],
+    max_bounces: u32,
+    rng: &mut random::Rng,
+    closest_hit: impl Fn(&ray::Ray) -> Option<(shapes::Hit, material::MaterialId)>,
+) -> PolarizationImage {
+    let tracer = Tracer {
+        materials,
+        lights,
+        max_bounces,
+        closest_hit,
+    };
+    let camera_x = camera_pose.rotation * nalgebra::Vector3::x_axis();
+    let stokes = projection::pixel_rays(projection)
+        .map(|ray| {
+            let ray = ray?.transform(camera_pose);
+            let towards_camera = -*ray.unit_direction();
+            // the camera's x axis, tilted to be perpendicular to off axis rays
+            let x_axis = nalgebra::UnitVector3::try_new(
+                camera_x.into_inner() - towards_camera.into_inner() * towards_camera.dot(&camera_x),
+                Float::EPSILON,
+            )
+            .unwrap_or_else(|| shapes::perpendicular(&towards_camera));
+            Some(
+                tracer
+                    .arriving(&ray, 0, rng)
+                    .in_frame(&x_axis, &towards_camera),
+            )
+        })
+        .collect();
+
+    PolarizationImage {
+        width: projection.width_px(),
+        height: projection.height_px(),
+        stokes,
+    }
+}
+
+struct Tracer<'a, F> {
+    materials: &'a material::MaterialLibrary,
+    lights: &'a [light::Light],
+    max_bounces: u32,
+    closest_hit: F,
+}
+
+impl<F: Fn(&ray::Ray) -> Option<(shapes::Hit, material::MaterialId)>> Tracer<'_, F> {
+    /// light arriving at the origin of `ray`, travelling against its direction
+    fn arriving(&self, ray: &ray::Ray, bounces: u32, rng: &mut random::Rng) -> Stokes {
+        let direction = ray.unit_direction();
+        let towards = -*direction;
+        let Some((hit, material)) = (self.closest_hit)(ray) else {
+            let radiance = light::environment_radiance(self.lights, direction);
+            return Stokes::unpolarized(color::luminance(&radiance), &towards);
+        };
+        let material = &self.materials[material];
+
+        // the microfacet lobes are traced as mirror paths below
+        let unblocked = |r: &ray::Ray, _: &mut random::Rng| match (self.closest_hit)(r) {
+            Some(_) => nalgebra::Vector3::zeros(),
+            None => nalgebra::Vector3::repeat(1.),
+        };
+        let scattered = light::sampled_lighting(self.lights, &hit, rng, unblocked, |wi| {
+            material.evaluate_diffuse(&hit.normal, &towards, wi)
+        });
+        let stokes = Stokes::unpolarized(
+            color::luminance(&(material.emission() + scattered)),
+            &towards,
+        );
+        if bounces == self.max_bounces {
+            return stokes;
+        }
+
+        // light from the mirror direction, reflected towards the ray origin
+        let n = material.refractive_index();
+        let k = material.extinction_coefficient();
+        let inside = direction.dot(&hit.normal) > 0. && k == 0.;
+        let index = match inside {
+            true => nalgebra::Complex::new(1. / n, 0.),
+            false => nalgebra::Complex::new(n, k),
+        };
+        let mirror = reflect_ray(ray, &hit, index);
+        let incoming = -*mirror.unit_direction();
+        let (reflected, _) = reflect(
+            &self.arriving(&mirror, bounces + 1, rng),
+            &incoming,
+            &hit.normal,
+            index,
+        );
+        let stokes = stokes.add(&reflected, &towards);
+
+        let transmission = material.transmission()
+            * (1. - material.metallic())
+            * color::luminance(material.albedo());
+        if transmission == 0. || k > 0. {
+            return stokes;
+        }
+        // light from the other side takes the refracted path of the ray in reverse
+        let Some(through) = refract_ray(ray, &hit, n) else {
+            return stokes;
+        };
+        let incoming = -*through.unit_direction();
+        let Some((transmitted, _)) = refract(
+            &self.arriving(&through, bounces + 1, rng),
+            &incoming,
+            &hit.normal,
+            n,
+        ) else {
+            return stokes;
+        };
+        // radiance changes with the square of the index ratio, the Mueller matrix only keeps power
+        let eta = match incoming.dot(&hit.normal) > 0. {
+            true => 1. / n,
+            false => n,
+        };
+        stokes.add(&transmitted.scaled(transmission * eta * eta), &towards)
+    }
+}
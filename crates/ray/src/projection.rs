@@ -0,0 +1,328 @@
+//! Mappings between pixels and rays in the camera frame.
+//!
+//! Every camera model implements [`Projection`], so rendering ([`pixel_rays`])
+//! and projecting points back into an image ([`Projection::world_to_pixel`])
+//! work the same for pinhole, orthographic and panoramic cameras. The camera
+//! frame is x right, y down, z forward, as for [`crate::camera::Camera`].
+
+use crate::Float;
+use crate::Real;
+use crate::angle;
+use crate::consts;
+use crate::distance;
+use crate::ray;
+
+pub trait Projection<T: Real = Float> {
+    fn width_px(&self) -> u32;
+
+    fn height_px(&self) -> u32;
+
+    /// ray in the camera frame through a pixel location, `None` where the projection covers nothing
+    fn pixel_ray(&self, x: T, y: T) -> Option<ray::Ray<T>>;
+
+    /// pixel location of a point in the camera frame, `None` when it can not be seen
+    fn camera_to_pixel(&self, point: &nalgebra::Vector3<T>) -> Option<nalgebra::Vector2<T>>;
+
+    /// pixel location of a point in the world, seen from a camera at `camera_pose` (camera to world)
+    fn world_to_pixel(
+        &self,
+        camera_pose: &nalgebra::Isometry3<T>,
+        point: &nalgebra::Vector3<T>,
+    ) -> Option<nalgebra::Vector2<T>> {
+        self.camera_to_pixel(&camera_pose.inverse_transform_point(&(*point).into()).coords)
+    }
+}
+
+/// rays of every pixel, row major, carrying differentials to the neighbouring pixels
+pub fn pixel_rays<T: Real, P: Projection<T>>(
+    projection: &P,
+) -> impl Iterator<Item = Option<ray::Ray<T>>> + '_ {
+    let width = projection.width_px();
+    (0..width * projection.height_px()).map(move |i| {
+        let [x, y] = [i % width, i / width].map(|c| nalgebra::convert::<f64, T>(c.into()));
+        let ray = projection.pixel_ray(x, y)?;
+        Some(with_differentials(
+            ray,
+            projection.pixel_ray(x + T::one(), y),
+            projection.pixel_ray(x, y + T::one()),
+        ))
+    })
+}
+
+/// differentials from the rays of the neighbouring pixels, none at the border of the projection
+fn with_differentials<T: Real>(
+    ray: ray::Ray<T>,
+    rx: Option<ray::Ray<T>>,
+    ry: Option<ray::Ray<T>>,
+) -> ray::Ray<T> {
+    let (Some(rx), Some(ry)) = (rx, ry) else {
+        return ray;
+    };
+    ray.with_differentials(ray::RayDifferentials {
+        rx_origin: *rx.origin(),
+        rx_direction: *rx.unit_direction(),
+        ry_origin: *ry.origin(),
+        ry_direction: *ry.unit_direction(),
+    })
+}
+
+/// Parallel rays along z, for top down maps
+#[derive(Copy, Clone, Debug)]
+pub struct Orthographic {
+    width_px: u32,
+    height_px: u32,
+    // footprint of a pixel
+    pixel_size: distance::Distance,
+}
+
+impl Orthographic {
+    pub fn new(width_px: u32, height_px: u32, pixel_size: distance::Distance) -> Self {
+        debug_assert!(width_px > 0, "width of a camera cannot be <= 0");
+        debug_assert!(height_px > 0, "height of a camera cannot be <= 0");
+        debug_assert!(pixel_size.m() > 0., "pixel size cannot be <= 0.0");
+        Self {
+            width_px,
+            height_px,
+            pixel_size,
+        }
+    }
+
+    pub fn pixel_size(&self) -> distance::Distance {
+        self.pixel_size
+    }
+}
+
+impl Projection for Orthographic {
+    fn width_px(&self) -> u32 {
+        self.width_px
+    }
+
+    fn height_px(&self) -> u32 {
+        self.height_px
+    }
+
+    fn pixel_ray(&self, x: Float, y: Float) -> Option<ray::Ray> {
+        let origin = nalgebra::Vector3::new(
+            self.pixel_size.m() * (x - self.width_px as Float / 2.),
+            self.pixel_size.m() * (y - self.height_px as Float / 2.),
+            0.,
+        );
+        Some(ray::Ray::new(origin, nalgebra::Vector3::z_axis()))
+    }
+
+    fn camera_to_pixel(
+        &self,
+        point: &nalgebra::Vector3<Float>,
+    ) -> Option<nalgebra::Vector2<Float>> {
+        (point.z >= 0.).then(|| {
+            nalgebra::Vector2::new(
+                point.x / self.pixel_size.m() + self.width_px as Float / 2.,
+                point.y / self.pixel_size.m() + self.height_px as Float / 2.,
+            )
+        })
+    }
+}
+
+/// Full sphere panorama, longitude along x and latitude along y, forward
+/// (z) in the center and up (-y) at the top.
+#[derive(Copy, Clone, Debug)]
+pub struct Equirectangular {
+    width_px: u32,
+    height_px: u32,
+}
+
+impl Equirectangular {
+    pub fn new(width_px: u32, height_px: u32) -> Self {
+        debug_assert!(width_px > 0, "width of a camera cannot be <= 0");
+        debug_assert!(height_px > 0, "height of a camera cannot be <= 0");
+        Self {
+            width_px,
+            height_px,
+        }
+    }
+}
+
+impl Projection for Equirectangular {
+    fn width_px(&self) -> u32 {
+        self.width_px
+    }
+
+    fn height_px(&self) -> u32 {
+        self.height_px
+    }
+
+    fn pixel_ray(&self, x: Float, y: Float) -> Option<ray::Ray> {
+        let longitude = (x / self.width_px as Float - 0.5) * 2. * consts::PI;
+        let latitude = (0.5 - y / self.height_px as Float) * consts::PI;
+        let direction = nalgebra::Vector3::new(
+            latitude.cos() * longitude.sin(),
+            -latitude.sin(),
+            latitude.cos() * longitude.cos(),
+        );
+        Some(ray::Ray::new(
+            nalgebra::Vector3::zeros(),
+            nalgebra::UnitVector3::new_normalize(direction),
+        ))
+    }
+
+    fn camera_to_pixel(
+        &self,
+        point: &nalgebra::Vector3<Float>,
+    ) -> Option<nalgebra::Vector2<Float>> {
+        let direction = nalgebra::UnitVector3::try_new(*point, Float::EPSILON)?;
+        let longitude = direction.x.atan2(direction.z);
+        let latitude = (-direction.y).clamp(-1., 1.).asin();
+        Some(nalgebra::Vector2::new(
+            (longitude / (2. * consts::PI) + 0.5) * self.width_px as Float,
+            (0.5 - latitude / consts::PI) * self.height_px as Float,
+        ))
+    }
+}
+
+/// Panorama on a cylinder around y, angle along x and height along y
+#[derive(Copy, Clone, Debug)]
+pub struct Cylindrical {
+    width_px: u32,
+    height_px: u32,
+    // radians per pixel along x
+    angle_per_px: Float,
+    // height on the unit cylinder per pixel along y
+    height_per_px: Float,
+}
+
+impl Cylindrical {
+    /// # Arguments
+    /// - `hfov`: horizontal field of view, up to 360°
+    /// - `vfov`: vertical field of view, below 180°
+    pub fn new(width_px: u32, height_px: u32, hfov: angle::Angle, vfov: angle::Angle) -> Self {
+        debug_assert!(width_px > 0, "width of a camera cannot be <= 0");
+        debug_assert!(height_px > 0, "height of a camera cannot be <= 0");
+        debug_assert!(
+            hfov.rads() > 0. && hfov.rads() <= 2. * consts::PI,
+            "horizontal field of view is within (0, 360°]"
+        );
+        debug_assert!(
+            vfov.rads() > 0. && vfov.rads() < consts::PI,
+            "vertical field of view is within (0, 180°)"
+        );
+        Self {
+            width_px,
+            height_px,
+            angle_per_px: hfov.rads() / width_px as Float,
+            height_per_px: 2. * (vfov * 0.5).tan() / height_px as Float,
+        }
+    }
+}
+
+impl Projection for Cylindrical {
+    fn width_px(&self) -> u32 {
+        self.width_px
+    }
+
+    fn height_px(&self) -> u32 {
+        self.height_px
+    }
+
+    fn pixel_ray(&self, x: Float, y: Float) -> Option<ray::Ray> {
+        let angle = (x - self.width_px as Float / 2.) * self.angle_per_px;
+        let height = (y - self.height_px as Float / 2.) * self.height_per_px;
+        let direction = nalgebra::Vector3::new(angle.sin(), height, angle.cos());
+        Some(ray::Ray::new(
+            nalgebra::Vector3::zeros(),
+            nalgebra::UnitVector3::new_normalize(direction),
+        ))
+    }
+
+    fn camera_to_pixel(
+        &self,
+        point: &nalgebra::Vector3<Float>,
+    ) -> Option<nalgebra::Vector2<Float>> {
+        let radius = point.x.hypot(point.z);
+        if radius == 0. {
+            return None;
+        }
+        let angle = point.x.atan2(point.z);
+        let pixel = nalgebra::Vector2::new(
+            angle / self.angle_per_px + self.width_px as Float / 2.,
+            point.y / radius / self.height_per_px + self.height_px as Float / 2.,
+        );
+        (0.0..=self.width_px as Float)
+            .contains(&pixel.x)
+            .then_some(pixel)
+    }
+}
+
+/// Six 90° faces side by side, in +x, -x, +y, -y, +z, -z order
+#[derive(Copy, Clone, Debug)]
+pub struct Cubemap {
+    face_size_px: u32,
+}
+
+impl Cubemap {
+    pub fn new(face_size_px: u32) -> Self {
+        debug_assert!(face_size_px > 0, "face size cannot be <= 0");
+        Self { face_size_px }
+    }
+
+    pub fn face_size_px(&self) -> u32 {
+        self.face_size_px
+    }
+
+    /// (forward, right, down) of a face, right x down = forward like the camera frame
+    pub fn face_axes(face: usize) -> [nalgebra::Vector3<Float>; 3] {
+        let v = nalgebra::Vector3::new;
+        match face {
+            0 => [v(1., 0., 0.), v(0., 0., -1.), v(0., 1., 0.)],
+            1 => [v(-1., 0., 0.), v(0., 0., 1.), v(0., 1., 0.)],
+            2 => [v(0., 1., 0.), v(1., 0., 0.), v(0., 0., -1.)],
+            3 => [v(0., -1., 0.), v(1., 0., 0.), v(0., 0., 1.)],
+            4 => [v(0., 0., 1.), v(1., 0., 0.), v(0., 1., 0.)],
+            _ => [v(0., 0., -1.), v(-1., 0., 0.), v(0., 1., 0.)],
+        }
+    }
+}
+
+impl Projection for Cubemap {
+    fn width_px(&self) -> u32 {
+        6 * self.face_size_px
+    }
+
+    fn height_px(&self) -> u32 {
+        self.face_size_px
+    }
+
+    fn pixel_ray(&self, x: Float, y: Float) -> Option<ray::Ray> {
+        let size = self.face_size_px as Float;
+        if x < 0. || y < 0. || x >= 6. * size || y >= size {
+            return None;
+        }
+
+        let face = (x / size).floor();
+        let u = (x - face * size) / size * 2. - 1.;
+        let v = y / size * 2. - 1.;
+        let [forward, right, down] = Self::face_axes(face as usize);
+        Some(ray::Ray::new(
+            nalgebra::Vector3::zeros(),
+            nalgebra::UnitVector3::new_normalize(forward + right * u + down * v),
+        ))
+    }
+
+    fn camera_to_pixel(
+        &self,
+        point: &nalgebra::Vector3<Float>,
+    ) -> Option<nalgebra::Vector2<Float>> {
+        let axis = point.iamax();
+        if point[axis] == 0. {
+            return None;
+        }
+        let face = 2 * axis + usize::from(point[axis] < 0.);
+        let [forward, right, down] = Self::face_axes(face);
+
+        let depth = point.dot(&forward);
+        let size = self.face_size_px as Float;
+        Some(nalgebra::Vector2::new(
+            (face as Float + (point.dot(&right) / depth + 1.) / 2.) * size,
+            (point.dot(&down) / depth + 1.) / 2. * size,
+        ))
+    }
+}
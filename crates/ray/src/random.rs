@@ -0,0 +1,73 @@
+//! Small seedable random number generator for sampling and noise.
+//!
+//! Seeded explicitly so renders and simulated sensor frames are reproducible.
+
+use crate::Float;
+use crate::consts;
+
+/// PCG32, taken from [O'Neill 2014](https://www.pcg-random.org/)
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+    increment: u64,
+}
+
+impl Rng {
+    const MULTIPLIER: u64 = 6364136223846793005;
+
+    pub fn new(seed: u64) -> Self {
+        Self::with_stream(seed, 0)
+    }
+
+    /// independent sequences for the same seed, e.g. one per pixel or thread
+    pub fn with_stream(seed: u64, stream: u64) -> Self {
+        let mut rng = Self {
+            state: 0,
+            increment: (stream << 1) | 1,
+        };
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.next_u32();
+        rng
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old
+            .wrapping_mul(Self::MULTIPLIER)
+            .wrapping_add(self.increment);
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        xorshifted.rotate_right((old >> 59) as u32)
+    }
+
+    /// uniform in [0, 1)
+    pub fn uniform(&mut self) -> Float {
+        // 24 bits, exactly representable in f32 and never rounds up to 1
+        (self.next_u32() >> 8) as Float / (1u32 << 24) as Float
+    }
+
+    /// standard normal, Box-Muller
+    pub fn normal(&mut self) -> Float {
+        let u1 = 1. - self.uniform();
+        let u2 = self.uniform();
+        (-2. * u1.ln()).sqrt() * (2. * consts::PI * u2).cos()
+    }
+
+    /// Poisson distributed count with the given mean, normal approximation above 30
+    pub fn poisson(&mut self, mean: Float) -> Float {
+        debug_assert!(mean >= 0., "poisson mean can not be negative");
+        if mean > 30. {
+            return (mean + mean.sqrt() * self.normal()).round().max(0.);
+        }
+
+        // Knuth, multiply uniforms until below e^-mean
+        let limit = (-mean).exp();
+        let mut count = 0.;
+        let mut product = self.uniform();
+        while product > limit {
+            count += 1.;
+            product *= self.uniform();
+        }
+        count
+    }
+}
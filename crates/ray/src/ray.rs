@@ -1,19 +1,221 @@
-pub struct Ray {
-    origin: nalgebra::Vector3<f32>,
+use crate::Float;
+use crate::Real;
+use crate::polarization;
+
+#[derive(Copy, Clone, Debug)]
+pub struct Ray<T = Float> {
+    origin: nalgebra::Vector3<T>,
     // assume normalized
-    direction: nalgebra::UnitVector3<f32>,
+    direction: nalgebra::UnitVector3<T>,
+    // only hits within [t_min, t_max] count
+    t_min: T,
+    t_max: T,
+    differentials: Option<RayDifferentials<T>>,
+    // carried light, for polarization aware tracing
+    polarization: Option<polarization::Stokes>,
+    // seconds, for moving cameras and objects
+    time: Float,
+}
+
+/// Rays through the neighbouring pixels (one right, one down),
+/// used to estimate the footprint of a pixel on a surface.
+#[derive(Copy, Clone, Debug)]
+pub struct RayDifferentials<T = Float> {
+    pub rx_origin: nalgebra::Vector3<T>,
+    pub rx_direction: nalgebra::UnitVector3<T>,
+    pub ry_origin: nalgebra::Vector3<T>,
+    pub ry_direction: nalgebra::UnitVector3<T>,
 }
 
-impl Ray {
-    pub fn new(origin: nalgebra::Vector3<f32>, direction: nalgebra::UnitVector3<f32>) -> Self {
-        Self { origin, direction }
+impl<T: Real> Ray<T> {
+    /// unbounded ray, hits anywhere in [0, inf)
+    pub fn new(origin: nalgebra::Vector3<T>, direction: nalgebra::UnitVector3<T>) -> Self {
+        Self {
+            origin,
+            direction,
+            t_min: T::zero(),
+            t_max: T::INFINITY,
+            differentials: None,
+            polarization: None,
+            time: 0.,
+        }
+    }
+
+    /// Ray from `from` towards `to`, only hitting things in between,
+    /// e.g. shadow rays to a light.
+    pub fn segment(from: nalgebra::Vector3<T>, to: &nalgebra::Vector3<T>) -> Self {
+        let (direction, distance) = nalgebra::UnitVector3::new_and_get(to - from);
+        Self::new(from, direction).with_interval(T::zero(), distance)
+    }
+
+    pub fn with_interval(mut self, t_min: T, t_max: T) -> Self {
+        debug_assert!(t_min <= t_max, "ray interval can not be empty");
+        self.t_min = t_min;
+        self.t_max = t_max;
+        self
+    }
+
+    pub fn with_differentials(mut self, differentials: RayDifferentials<T>) -> Self {
+        self.differentials = Some(differentials);
+        self
+    }
+
+    /// Stokes vector of the light along the ray, its x axis perpendicular to the direction
+    pub fn with_polarization(mut self, stokes: polarization::Stokes) -> Self {
+        debug_assert!(
+            stokes
+                .x_axis()
+                .dot(&nalgebra::convert_unchecked::<_, nalgebra::Vector3<Float>>(
+                    self.direction.into_inner()
+                ))
+                .abs()
+                < 1e-3,
+            "stokes reference axis must be perpendicular to the ray"
+        );
+        self.polarization = Some(stokes);
+        self
     }
 
-    pub fn origin(&self) -> &nalgebra::Vector3<f32> {
+    /// time the ray is cast at, in seconds
+    pub fn with_time(mut self, time: Float) -> Self {
+        self.time = time;
+        self
+    }
+
+    pub fn origin(&self) -> &nalgebra::Vector3<T> {
         &self.origin
     }
 
-    pub fn direction(&self) -> &nalgebra::Vector3<f32> {
+    pub fn direction(&self) -> &nalgebra::Vector3<T> {
+        &self.direction
+    }
+
+    pub fn unit_direction(&self) -> &nalgebra::UnitVector3<T> {
         &self.direction
     }
+
+    pub fn t_min(&self) -> T {
+        self.t_min
+    }
+
+    pub fn t_max(&self) -> T {
+        self.t_max
+    }
+
+    /// whether a hit at distance `t` lies within the interval of the ray
+    #[inline(always)]
+    pub fn contains(&self, t: T) -> bool {
+        self.t_min <= t && t <= self.t_max
+    }
+
+    pub fn differentials(&self) -> Option<&RayDifferentials<T>> {
+        self.differentials.as_ref()
+    }
+
+    pub fn polarization(&self) -> Option<&polarization::Stokes> {
+        self.polarization.as_ref()
+    }
+
+    pub fn time(&self) -> Float {
+        self.time
+    }
+
+    pub fn at(&self, t: T) -> nalgebra::Vector3<T> {
+        self.origin + self.direction.into_inner() * t
+    }
+
+    /// moves the ray (and its differentials) into another frame
+    pub fn transform(&self, transform: &nalgebra::Isometry3<T>) -> Self {
+        let point = |p: &nalgebra::Vector3<T>| transform.transform_point(&(*p).into()).coords;
+        Self {
+            origin: point(&self.origin),
+            direction: transform.rotation * self.direction,
+            t_min: self.t_min,
+            t_max: self.t_max,
+            differentials: self.differentials.map(|d| RayDifferentials {
+                rx_origin: point(&d.rx_origin),
+                rx_direction: transform.rotation * d.rx_direction,
+                ry_origin: point(&d.ry_origin),
+                ry_direction: transform.rotation * d.ry_direction,
+            }),
+            polarization: self
+                .polarization
+                .map(|p| p.transform(&nalgebra::convert_unchecked(transform.rotation))),
+            time: self.time,
+        }
+    }
+
+    /// Offsets (dp/dx, dp/dy) from `point` to where the differential rays
+    /// hit the tangent plane of the surface at `point`.
+    ///
+    /// `None` without differentials, or when they run parallel to the surface.
+    pub fn surface_differentials(
+        &self,
+        point: &nalgebra::Vector3<T>,
+        normal: &nalgebra::UnitVector3<T>,
+    ) -> Option<(nalgebra::Vector3<T>, nalgebra::Vector3<T>)> {
+        let differentials = self.differentials.as_ref()?;
+        let plane_offset = normal.dot(point);
+        let on_plane = |origin: &nalgebra::Vector3<T>, direction: &nalgebra::UnitVector3<T>| {
+            let denominator = normal.dot(direction);
+            if denominator == T::zero() {
+                return None;
+            }
+            let t = (plane_offset - normal.dot(origin)) / denominator;
+            Some(origin + direction.into_inner() * t - point)
+        };
+
+        Some((
+            on_plane(&differentials.rx_origin, &differentials.rx_direction)?,
+            on_plane(&differentials.ry_origin, &differentials.ry_direction)?,
+        ))
+    }
+
+    /// Ray leaving a surface point in `direction`, with the origin offset
+    /// to the side of the surface the direction points to.
+    ///
+    /// # Arguments
+    /// - `normal`: geometric normal of the surface at `point`, either side
+    pub fn spawn(
+        point: &nalgebra::Vector3<T>,
+        normal: &nalgebra::UnitVector3<T>,
+        direction: nalgebra::UnitVector3<T>,
+    ) -> Self {
+        let normal = match direction.dot(normal) < T::zero() {
+            true => -*normal,
+            false => *normal,
+        };
+        Self::new(offset_origin(point, &normal), direction)
+    }
+}
+
+/// Moves a surface point off the surface along the normal, just far enough
+/// that rays spawned from it do not hit the surface they started on.
+///
+/// The offset is a fixed number of ulps, so it scales with the magnitude of
+/// the point instead of relying on an epsilon tuned for a given scene.
+/// Offsets towards the side of the surface `normal` points to, flip the
+/// normal to leave from the other side.
+///
+/// taken from [Wächter and Binder 2019](https://link.springer.com/chapter/10.1007/978-1-4842-4427-2_6)
+pub fn offset_origin<T: Real>(
+    point: &nalgebra::Vector3<T>,
+    normal: &nalgebra::UnitVector3<T>,
+) -> nalgebra::Vector3<T> {
+    // below this the ulp spacing becomes too fine, so fall back to a fixed offset
+    let origin: T = nalgebra::convert(1. / 32.);
+    let int_scale: T = nalgebra::convert(256.);
+    // 2^-16 for f32, scaled with the precision
+    let float_scale = T::default_epsilon() * nalgebra::convert(128.);
+
+    nalgebra::Vector3::from_fn(|i, _| {
+        let p = point[i];
+        let n = normal[i];
+        if p.abs() < origin {
+            return p + float_scale * n;
+        }
+
+        let ulps = nalgebra::convert_unchecked::<T, f64>(int_scale * n) as i64;
+        p.add_ulps(if p < T::zero() { -ulps } else { ulps })
+    })
 }
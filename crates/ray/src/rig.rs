@@ -0,0 +1,160 @@
+//! Several cameras rigidly mounted on one body, with ground truth between them.
+//!
+//! Ground truth comes from the hits of one camera's pixel rays ([`Rig::hits`]),
+//! shared by depth, correspondences and disparity so they always agree.
+
+use crate::Float;
+use crate::Real;
+use crate::camera;
+use crate::distance::Distance;
+use crate::projection;
+use crate::projection::Projection;
+use crate::ray;
+
+pub struct Rig<T = Float> {
+    // body to world
+    pose: nalgebra::Isometry3<T>,
+    // with their camera to body transforms
+    cameras: Vec<(camera::Camera<T>, nalgebra::Isometry3<T>)>,
+}
+
+/// Where a pixel's hit lands in another camera
+#[derive(Copy, Clone, Debug)]
+pub struct Correspondence<T = Float> {
+    pub pixel: nalgebra::Vector2<T>,
+    /// whether the other camera sees the point, false when occluded
+    pub visible: bool,
+}
+
+impl<T: Real> Rig<T> {
+    /// rig without cameras, `pose` is body to world
+    pub fn new(pose: nalgebra::Isometry3<T>) -> Self {
+        Self {
+            pose,
+            cameras: Vec::new(),
+        }
+    }
+
+    /// Rectified stereo pair of identical cameras, left (0) at the body
+    /// origin and right (1) `baseline` along the camera x axis.
+    pub fn stereo(camera: camera::Camera<T>, baseline: Distance<T>) -> Self {
+        Self::new(nalgebra::Isometry3::identity())
+            .with_camera(camera.clone(), nalgebra::Isometry3::identity())
+            .with_camera(
+                camera,
+                nalgebra::Isometry3::translation(baseline.m(), T::zero(), T::zero()),
+            )
+    }
+
+    /// # Arguments
+    /// - `extrinsics`: camera to body
+    pub fn with_camera(
+        mut self,
+        camera: camera::Camera<T>,
+        extrinsics: nalgebra::Isometry3<T>,
+    ) -> Self {
+        self.cameras.push((camera, extrinsics));
+        self
+    }
+
+    /// body to world
+    pub fn with_pose(mut self, pose: nalgebra::Isometry3<T>) -> Self {
+        self.pose = pose;
+        self
+    }
+
+    pub fn pose(&self) -> &nalgebra::Isometry3<T> {
+        &self.pose
+    }
+
+    pub fn len(&self) -> usize {
+        self.cameras.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cameras.is_empty()
+    }
+
+    pub fn camera(&self, index: usize) -> &camera::Camera<T> {
+        &self.cameras[index].0
+    }
+
+    /// camera to world
+    pub fn camera_pose(&self, index: usize) -> nalgebra::Isometry3<T> {
+        self.pose * self.cameras[index].1
+    }
+
+    /// World rays of every pixel of a camera, row major.
+    pub fn rays(&self, index: usize) -> impl Iterator<Item = ray::Ray<T>> + '_ {
+        let pose = self.camera_pose(index);
+        projection::pixel_rays(self.camera(index)).map(move |ray| {
+            ray.expect("pinhole cameras cover every pixel")
+                .transform(&pose)
+        })
+    }
+
+    /// World points hit by every pixel of a camera, row major.
+    ///
+    /// # Arguments
+    /// - `trace`: distance along a world ray to the closest hit in the scene
+    pub fn hits(
+        &self,
+        index: usize,
+        trace: impl Fn(&ray::Ray<T>) -> Option<T>,
+    ) -> Vec<Option<nalgebra::Vector3<T>>> {
+        self.rays(index)
+            .map(|ray| trace(&ray).map(|distance| ray.at(distance)))
+            .collect()
+    }
+
+    /// depth along the optical axis of a camera to each hit
+    pub fn depth(&self, index: usize, hits: &[Option<nalgebra::Vector3<T>>]) -> Vec<Option<T>> {
+        let pose = self.camera_pose(index);
+        hits.iter()
+            .map(|hit| hit.map(|point| pose.inverse_transform_point(&point.into()).z))
+            .collect()
+    }
+
+    /// Pixel in camera `to` of each hit, `None` where the hit is behind it.
+    ///
+    /// Visibility is checked by tracing from `to` towards the hit.
+    pub fn correspondences(
+        &self,
+        to: usize,
+        hits: &[Option<nalgebra::Vector3<T>>],
+        trace: impl Fn(&ray::Ray<T>) -> Option<T>,
+    ) -> Vec<Option<Correspondence<T>>> {
+        let pose = self.camera_pose(to);
+        let origin = pose.translation.vector;
+        hits.iter()
+            .map(|hit| {
+                let point = (*hit)?;
+                let pixel = self.camera(to).world_to_pixel(&pose, &point)?;
+
+                let (direction, distance) = nalgebra::UnitVector3::new_and_get(point - origin);
+                // allow for the hit itself, found from the other side
+                let visible = trace(&ray::Ray::new(origin, direction))
+                    .is_none_or(|t| t >= distance * (T::one() - nalgebra::convert(1e-4)));
+                Some(Correspondence { pixel, visible })
+            })
+            .collect()
+    }
+
+    /// Horizontal disparity from the `left` camera's pixels to their
+    /// correspondences, in pixels; occluded pixels keep their geometric
+    /// disparity, mask them with [`Correspondence::visible`].
+    pub fn disparity(
+        &self,
+        left: usize,
+        correspondences: &[Option<Correspondence<T>>],
+    ) -> Vec<Option<T>> {
+        let width = self.camera(left).width_px() as usize;
+        correspondences
+            .iter()
+            .enumerate()
+            .map(|(i, correspondence)| {
+                correspondence.map(|c| nalgebra::convert::<f64, T>((i % width) as f64) - c.pixel.x)
+            })
+            .collect()
+    }
+}
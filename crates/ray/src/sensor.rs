@@ -0,0 +1,338 @@
+//! Radiometric sensor model, turning rendered radiance into raw frames.
+//!
+//! Radiance in W/(sr m²) reaching the lens becomes irradiance on the pixel
+//! through the f-number (with cos⁴ falloff), photons through the exposure
+//! time and pixel area, electrons through the quantum efficiency, and finally
+//! digital numbers through the gain and bit depth. Shot, dark and read noise
+//! change every frame, PRNU and DSNU are a fixed pattern of the sensor.
+
+use crate::Float;
+use crate::camera;
+use crate::consts;
+use crate::distance::Distance;
+use crate::random;
+
+// planck constant times speed of light, J m
+const PLANCK_TIMES_C: Float = 1.986_445_9e-25;
+
+/// Color filter array, named by the 2x2 tile starting at the top left pixel
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Bayer {
+    Rggb,
+    Bggr,
+    Grbg,
+    Gbrg,
+}
+
+impl Bayer {
+    /// channel seen by a pixel, 0 red, 1 green, 2 blue
+    pub fn channel(&self, x: u32, y: u32) -> usize {
+        let tile = match self {
+            Bayer::Rggb => [0, 1, 1, 2],
+            Bayer::Bggr => [2, 1, 1, 0],
+            Bayer::Grbg => [1, 0, 2, 1],
+            Bayer::Gbrg => [1, 2, 0, 1],
+        };
+        tile[((y % 2) * 2 + x % 2) as usize]
+    }
+}
+
+pub struct Sensor {
+    f_number: Float,
+    // seconds
+    exposure_time: Float,
+    quantum_efficiency: Float,
+    // electrons
+    full_well: Float,
+    // electrons rms
+    read_noise: Float,
+    // electrons per second
+    dark_current: Float,
+    // relative standard deviation of the pixel gain
+    prnu: Float,
+    // standard deviation of the dark signal, electrons
+    dsnu: Float,
+    // digital numbers per electron, by default the full well maps to the full range
+    gain: Option<Float>,
+    bit_depth: u32,
+    // digital numbers
+    black_level: Float,
+    cfa: Option<Bayer>,
+    // mean wavelength of the red, green and blue channels
+    channel_wavelengths: [Distance; 3],
+    fixed_pattern_seed: u64,
+}
+
+impl Sensor {
+    /// Noise free 12 bit monochrome sensor, the gain maps the full well to the full range.
+    pub fn new(f_number: Float, exposure_time: Float) -> Self {
+        debug_assert!(f_number > 0., "f-number can not be <= 0.0");
+        debug_assert!(exposure_time >= 0., "exposure time can not be negative");
+        Self {
+            f_number,
+            exposure_time,
+            quantum_efficiency: 0.6,
+            full_well: 10_000.,
+            read_noise: 0.,
+            dark_current: 0.,
+            prnu: 0.,
+            dsnu: 0.,
+            gain: None,
+            bit_depth: 12,
+            black_level: 0.,
+            cfa: None,
+            channel_wavelengths: [
+                Distance::from_nm(610.),
+                Distance::from_nm(540.),
+                Distance::from_nm(465.),
+            ],
+            fixed_pattern_seed: 0,
+        }
+    }
+
+    /// fraction of photons converted to electrons, [0, 1]
+    pub fn with_quantum_efficiency(mut self, quantum_efficiency: Float) -> Self {
+        debug_assert!(
+            (0.0..=1.).contains(&quantum_efficiency),
+            "quantum efficiency is within [0, 1]"
+        );
+        self.quantum_efficiency = quantum_efficiency;
+        self
+    }
+
+    /// electrons a pixel holds before saturating
+    pub fn with_full_well(mut self, full_well: Float) -> Self {
+        debug_assert!(full_well > 0., "full well can not be <= 0.0");
+        self.full_well = full_well;
+        self
+    }
+
+    /// # Arguments
+    /// - `read_noise`: electrons rms
+    /// - `dark_current`: electrons per second
+    pub fn with_noise(mut self, read_noise: Float, dark_current: Float) -> Self {
+        debug_assert!(
+            read_noise >= 0. && dark_current >= 0.,
+            "noise can not be negative"
+        );
+        self.read_noise = read_noise;
+        self.dark_current = dark_current;
+        self
+    }
+
+    /// Fixed pattern noise, the same in every frame of a sensor with the same seed.
+    ///
+    /// # Arguments
+    /// - `prnu`: relative standard deviation of the pixel response
+    /// - `dsnu`: standard deviation of the dark signal, electrons
+    pub fn with_fixed_pattern_noise(mut self, prnu: Float, dsnu: Float, seed: u64) -> Self {
+        debug_assert!(prnu >= 0. && dsnu >= 0., "noise can not be negative");
+        self.prnu = prnu;
+        self.dsnu = dsnu;
+        self.fixed_pattern_seed = seed;
+        self
+    }
+
+    /// digital numbers per electron, instead of mapping the full well to the full range
+    pub fn with_gain(mut self, gain: Float) -> Self {
+        debug_assert!(gain > 0., "gain can not be <= 0.0");
+        self.gain = Some(gain);
+        self
+    }
+
+    pub fn with_bit_depth(mut self, bit_depth: u32) -> Self {
+        debug_assert!((1..=16).contains(&bit_depth), "bit depth is within [1, 16]");
+        self.bit_depth = bit_depth;
+        self
+    }
+
+    /// offset in digital numbers, keeps negative read noise from clipping
+    pub fn with_black_level(mut self, black_level: Float) -> Self {
+        self.black_level = black_level;
+        self
+    }
+
+    pub fn with_bayer(mut self, cfa: Bayer) -> Self {
+        self.cfa = Some(cfa);
+        self
+    }
+
+    /// mean wavelength of the red, green and blue channels, for the photon count
+    pub fn with_channel_wavelengths(mut self, channel_wavelengths: [Distance; 3]) -> Self {
+        self.channel_wavelengths = channel_wavelengths;
+        self
+    }
+
+    pub fn f_number(&self) -> Float {
+        self.f_number
+    }
+
+    pub fn exposure_time(&self) -> Float {
+        self.exposure_time
+    }
+
+    pub fn bit_depth(&self) -> u32 {
+        self.bit_depth
+    }
+
+    /// digital numbers per electron
+    pub fn gain(&self) -> Float {
+        self.gain
+            .unwrap_or_else(|| max_value(self.bit_depth) / self.full_well)
+    }
+
+    pub fn cfa(&self) -> Option<Bayer> {
+        self.cfa
+    }
+
+    /// mean number of electrons collected from radiance at the lens, before noise and saturation
+    pub fn electrons(
+        &self,
+        camera: &camera::Camera,
+        x: u32,
+        y: u32,
+        radiance: Float,
+        wavelength: Distance,
+    ) -> Float {
+        let direction = camera.pixel_to_camera_vector(x as Float + 0.5, y as Float + 0.5);
+        let cos = direction.z / direction.norm();
+        let irradiance = consts::PI * radiance * cos.powi(4) / (4. * self.f_number * self.f_number);
+
+        let area = camera.pixel_pitch_x().m() * camera.pixel_pitch_y().m();
+        let energy = irradiance * area * self.exposure_time;
+        let photons = energy * wavelength.m() / PLANCK_TIMES_C;
+        photons * self.quantum_efficiency
+    }
+
+    /// Raw frame from linear RGB radiance per pixel, row major.
+    ///
+    /// With a [`Bayer`] mosaic every pixel only sees its own channel, without
+    /// one the pixel sees the mean of the channels.
+    pub fn expose(
+        &self,
+        camera: &camera::Camera,
+        radiance: &[nalgebra::Vector3<Float>],
+        rng: &mut random::Rng,
+    ) -> RawFrame {
+        debug_assert_eq!(
+            radiance.len(),
+            (camera.width_px() * camera.height_px()) as usize,
+            "one radiance per pixel"
+        );
+        self.expose_with(camera, rng, |x, y, i| match self.cfa {
+            Some(cfa) => {
+                let channel = cfa.channel(x, y);
+                self.electrons(
+                    camera,
+                    x,
+                    y,
+                    radiance[i][channel],
+                    self.channel_wavelengths[channel],
+                )
+            }
+            None => {
+                (0..3)
+                    .map(|c| {
+                        self.electrons(camera, x, y, radiance[i][c], self.channel_wavelengths[c])
+                    })
+                    .sum::<Float>()
+                    / 3.
+            }
+        })
+    }
+
+    /// Raw frame from single band radiance per pixel, row major, e.g. from
+    /// a [`crate::spectral::SpectralResponse`] with one channel.
+    pub fn expose_mono(
+        &self,
+        camera: &camera::Camera,
+        radiance: &[Float],
+        wavelength: Distance,
+        rng: &mut random::Rng,
+    ) -> RawFrame {
+        debug_assert_eq!(
+            radiance.len(),
+            (camera.width_px() * camera.height_px()) as usize,
+            "one radiance per pixel"
+        );
+        self.expose_with(camera, rng, |x, y, i| {
+            self.electrons(camera, x, y, radiance[i], wavelength)
+        })
+    }
+
+    fn expose_with(
+        &self,
+        camera: &camera::Camera,
+        rng: &mut random::Rng,
+        mean_electrons: impl Fn(u32, u32, usize) -> Float,
+    ) -> RawFrame {
+        let (width, height) = (camera.width_px(), camera.height_px());
+        // the same sequence every frame, so the pattern stays fixed
+        let mut pattern = random::Rng::new(self.fixed_pattern_seed);
+        let dark = self.dark_current * self.exposure_time;
+        let max = max_value(self.bit_depth);
+        let gain = self.gain();
+
+        let data = (0..width * height)
+            .map(|i| {
+                let (x, y) = (i % width, i / width);
+                let response = 1. + self.prnu * pattern.normal();
+                let dark_offset = self.dsnu * pattern.normal();
+
+                let signal = rng.poisson((mean_electrons(x, y, i as usize) * response).max(0.));
+                let dark = rng.poisson((dark + dark_offset).max(0.));
+                let electrons =
+                    (signal + dark).min(self.full_well) + self.read_noise * rng.normal();
+
+                (electrons * gain + self.black_level).round().clamp(0., max) as u16
+            })
+            .collect();
+
+        RawFrame {
+            width,
+            height,
+            bit_depth: self.bit_depth,
+            cfa: self.cfa,
+            data,
+        }
+    }
+}
+
+fn max_value(bit_depth: u32) -> Float {
+    ((1u32 << bit_depth) - 1) as Float
+}
+
+/// Digital numbers straight off the sensor, row major
+pub struct RawFrame {
+    width: u32,
+    height: u32,
+    bit_depth: u32,
+    cfa: Option<Bayer>,
+    data: Vec<u16>,
+}
+
+impl RawFrame {
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn bit_depth(&self) -> u32 {
+        self.bit_depth
+    }
+
+    pub fn cfa(&self) -> Option<Bayer> {
+        self.cfa
+    }
+
+    pub fn value(&self, x: u32, y: u32) -> u16 {
+        self.data[(y * self.width + x) as usize]
+    }
+
+    pub fn data(&self) -> &[u16] {
+        &self.data
+    }
+}
@@ -1,69 +1,273 @@
+use super::mesh;
+use super::sphere;
 use super::triangle;
+use crate::Float;
+use crate::Real;
+use crate::aabb;
+use crate::bvh;
+use crate::material;
+use crate::packet;
 use crate::ray;
 use crate::ray_mesh_intersections;
 
-pub struct CompositeObject {
-    mesh: Vec<triangle::Triangle>,
-    color: [u8; 3],
-    refractive_index: f32,
-    extinction_coefficient: f32,
+pub struct CompositeObject<T = Float> {
+    mesh: Vec<triangle::Triangle<T>>,
+    bvh: bvh::Bvh<T>,
+    // per triangle vertex uvs, in a, b, c order
+    uvs: Option<Vec<[nalgebra::Vector2<T>; 3]>>,
+    material: material::MaterialId,
 }
 
-impl CompositeObject {
-    pub fn new(
-        mesh: Vec<triangle::Triangle>,
-        color: [u8; 3],
-        refractive_index: f32,
-        extinction_coefficient: f32,
-    ) -> Self {
+impl<T: Real> CompositeObject<T> {
+    pub fn new(mesh: Vec<triangle::Triangle<T>>, material: material::MaterialId) -> Self {
+        let bvh = build_bvh(&mesh);
         Self {
             mesh,
-            color,
-            refractive_index,
-            extinction_coefficient,
+            bvh,
+            uvs: None,
+            material,
         }
     }
 
-    pub fn ray_intersection(&self, ray: &ray::Ray, epsilon: f32) -> impl Iterator<Item = f32> {
-        ray_mesh_intersections(&self.mesh, ray, epsilon)
+    /// vertex uvs of every triangle, in a, b, c order
+    pub fn with_uvs(mut self, uvs: Vec<[nalgebra::Vector2<T>; 3]>) -> Self {
+        debug_assert_eq!(uvs.len(), self.mesh.len(), "one set of uvs per triangle");
+        self.uvs = Some(uvs);
+        self
     }
 
-    pub fn color(&self) -> &[u8; 3] {
-        &self.color
+    pub fn uvs(&self) -> Option<&[[nalgebra::Vector2<T>; 3]]> {
+        self.uvs.as_deref()
     }
 
-    pub fn refractive_index(&self) -> f32 {
-        self.refractive_index
+    /// Interpolated vertex uv of a point on a triangle of the mesh,
+    /// the triangle's barycentric uv when the mesh has no uvs.
+    pub fn uv(&self, triangle: usize, point: &nalgebra::Vector3<T>) -> nalgebra::Vector2<T> {
+        let Some(uvs) = self.uvs.as_ref() else {
+            return self.mesh[triangle].uv(point);
+        };
+        let [wa, wb, wc] = self.mesh[triangle].barycentric(point);
+        let [a, b, c] = uvs[triangle];
+        a * wa + b * wb + c * wc
     }
 
-    pub fn extinction_coefficient(&self) -> f32 {
-        self.extinction_coefficient
+    /// direction of increasing u across a triangle of the mesh
+    fn tangent(&self, triangle: usize) -> nalgebra::Vector3<T> {
+        let t = &self.mesh[triangle];
+        let edge1 = t.b() - t.a();
+        let edge2 = t.c() - t.a();
+        let Some([a, b, c]) = self.uvs.as_ref().map(|uvs| uvs[triangle]) else {
+            return edge1;
+        };
+
+        let duv1 = b - a;
+        let duv2 = c - a;
+        let det = duv1.x * duv2.y - duv1.y * duv2.x;
+        match det == T::zero() {
+            true => edge1,
+            false => (edge1 * duv2.y - edge2 * duv1.y) / det,
+        }
+    }
+
+    pub fn ray_intersection(&self, ray: &ray::Ray<T>) -> impl Iterator<Item = T> {
+        ray_mesh_intersections(&self.mesh, ray)
+    }
+
+    /// closest hit, as (distance, triangle index)
+    pub fn closest_ray_intersection(&self, ray: &ray::Ray<T>) -> Option<(T, usize)> {
+        self.bvh
+            .closest_hit(ray, |i| self.mesh[i].ray_intersection(ray))
+    }
+
+    /// closest hit of every ray in the packet, as (distance, triangle index)
+    pub fn closest_ray_packet_intersection<const N: usize>(
+        &self,
+        packet: &packet::RayPacket<N, T>,
+    ) -> packet::Lanes<Option<(T, usize)>, N> {
+        self.bvh
+            .closest_hit_packet(packet, |i| self.mesh[i].ray_packet_intersection(packet))
+    }
+
+    pub fn mesh(&self) -> &[triangle::Triangle<T>] {
+        &self.mesh
+    }
+
+    pub fn material(&self) -> material::MaterialId {
+        self.material
+    }
+
+    pub fn bounds(&self) -> aabb::Aabb<T> {
+        self.bvh.bounds()
     }
 
-    pub fn scale_in_place(&mut self, scale: f32) {
+    /// approximate, see [`mesh::bounding_sphere`]
+    pub fn bounding_sphere(&self) -> sphere::Sphere<T> {
+        mesh::bounding_sphere(&self.mesh)
+    }
+
+    pub fn surface_area(&self) -> T {
+        mesh::surface_area(&self.mesh)
+    }
+
+    /// positive when closed with outward normals
+    pub fn signed_volume(&self) -> T {
+        mesh::signed_volume(&self.mesh)
+    }
+
+    pub fn centroid(&self) -> nalgebra::Vector3<T> {
+        mesh::centroid(&self.mesh)
+    }
+
+    pub fn face_normals(&self) -> Vec<nalgebra::UnitVector3<T>> {
+        mesh::face_normals(&self.mesh)
+    }
+
+    /// area weighted normals at the a, b and c vertex of every triangle
+    pub fn vertex_normals(&self) -> Vec<[nalgebra::UnitVector3<T>; 3]> {
+        mesh::vertex_normals(&self.mesh)
+    }
+
+    /// closest point of the surface with its triangle index, None for an empty mesh
+    pub fn closest_point(
+        &self,
+        point: &nalgebra::Vector3<T>,
+    ) -> Option<(nalgebra::Vector3<T>, usize)> {
+        let (_, triangle) = self.bvh.nearest(point, |i| {
+            (self.mesh[i].closest_point(point) - point).norm_squared()
+        })?;
+        Some((self.mesh[triangle].closest_point(point), triangle))
+    }
+
+    /// to the surface, infinite for an empty mesh
+    pub fn distance(&self, point: &nalgebra::Vector3<T>) -> T {
+        self.closest_point(point)
+            .map_or(T::INFINITY, |(closest, _)| (closest - point).norm())
+    }
+
+    /// see [`mesh::winding_number`], visits every triangle
+    pub fn winding_number(&self, point: &nalgebra::Vector3<T>) -> T {
+        mesh::winding_number(&self.mesh, point)
+    }
+
+    pub fn validate(&self) -> mesh::Validation<T> {
+        mesh::validate(&self.mesh)
+    }
+
+    /// Makes the winding consistent and closed parts face outwards,
+    /// see [`mesh::orient`]. Returns the number of flipped triangles.
+    pub fn orient_in_place(&mut self) -> usize {
+        let flipped = mesh::orient(&mut self.mesh);
+        if let Some(uvs) = self.uvs.as_mut() {
+            uvs.iter_mut()
+                .zip(&flipped)
+                .filter(|(_, flipped)| **flipped)
+                .for_each(|(uv, _)| uv.swap(1, 2));
+        }
+        // same bounds, the bvh stays valid
+        flipped.iter().filter(|f| **f).count()
+    }
+
+    pub fn scale_in_place(&mut self, scale: T) {
         self.mesh
             .iter_mut()
-            .for_each(|triangle| *triangle = triangle.scale(scale))
+            .for_each(|triangle| *triangle = triangle.scale(scale));
+        self.bvh = build_bvh(&self.mesh);
     }
 
-    pub fn rotate_in_place(&mut self, rotation: &nalgebra::UnitQuaternion<f32>) {
+    pub fn rotate_in_place(&mut self, rotation: &nalgebra::UnitQuaternion<T>) {
         self.mesh
             .iter_mut()
-            .for_each(|triangle| *triangle = triangle.rotate(rotation))
+            .for_each(|triangle| *triangle = triangle.rotate(rotation));
+        self.bvh = build_bvh(&self.mesh);
     }
 
     /// make sure you are in the right coords
-    pub fn translate_in_place(&mut self, translation: &nalgebra::Vector3<f32>) {
+    pub fn translate_in_place(&mut self, translation: &nalgebra::Vector3<T>) {
         self.mesh
             .iter_mut()
-            .for_each(|triangle| *triangle = triangle.translate(translation))
+            .for_each(|triangle| *triangle = triangle.translate(translation));
+        self.bvh = build_bvh(&self.mesh);
+    }
+}
+
+/// skewed off the axes, so rays rarely run along the edges of axis aligned meshes
+const PARITY_DIRECTIONS: [[f64; 3]; 3] = [
+    [1., 0.3127, 0.1542],
+    [-0.2461, 1., 0.4271],
+    [0.3719, -0.1913, -1.],
+];
+
+/// Inside when rays from the point cross the surface an odd number of times,
+/// for closed meshes of any winding. Goes with two of three rays, in case one
+/// slips through where triangles meet.
+impl<T: Real> super::Solid<T> for CompositeObject<T> {
+    fn contains(&self, point: &nalgebra::Vector3<T>) -> bool {
+        if self.bounds().distance_squared(point) > T::zero() {
+            return false;
+        }
+        let odd = PARITY_DIRECTIONS
+            .iter()
+            .filter(|direction| {
+                let ray = ray::Ray::new(
+                    *point,
+                    nalgebra::UnitVector3::new_normalize(nalgebra::Vector3::from(
+                        direction.map(nalgebra::convert),
+                    )),
+                );
+                let mut crossings = 0;
+                // never reporting a hit visits every triangle along the ray
+                self.bvh.any_hit(&ray, |i| {
+                    crossings += self.mesh[i].ray_intersection(&ray).is_some() as usize;
+                    false
+                });
+                crossings % 2 == 1
+            })
+            .count();
+        odd >= 2
     }
+
+    fn signed_distance(&self, point: &nalgebra::Vector3<T>) -> T {
+        match self.contains(point) {
+            true => -self.distance(point),
+            false => self.distance(point),
+        }
+    }
+}
+
+fn build_bvh<T: Real>(mesh: &[triangle::Triangle<T>]) -> bvh::Bvh<T> {
+    bvh::Bvh::new(&mesh.iter().map(|t| t.bounds()).collect::<Vec<_>>())
 }
 
-impl super::Traceable for CompositeObject {
-    fn trace(&self, ray: &ray::Ray, epsilon: f32) -> Option<f32> {
-        self.ray_intersection(ray, epsilon)
-            .filter(|dist| *dist >= 0.)
-            .min_by(|a, b| a.total_cmp(b))
+impl<T: Real> super::Traceable<T> for CompositeObject<T> {
+    fn trace(&self, ray: &ray::Ray<T>) -> Option<T> {
+        self.closest_ray_intersection(ray).map(|(dist, _)| dist)
+    }
+
+    fn hit(&self, ray: &ray::Ray<T>) -> Option<super::Hit<T>> {
+        let (distance, triangle) = self.closest_ray_intersection(ray)?;
+        let hit = super::Hit::new(
+            ray,
+            distance,
+            self.mesh[triangle].normal(),
+            &self.tangent(triangle),
+            |p| self.uv(triangle, p),
+        );
+        Some(super::Hit {
+            primitive: triangle,
+            ..hit
+        })
+    }
+
+    fn occluded(&self, ray: &ray::Ray<T>) -> bool {
+        self.bvh
+            .any_hit(ray, |i| self.mesh[i].ray_intersection(ray).is_some())
+    }
+
+    fn trace_packet<const N: usize>(
+        &self,
+        packet: &packet::RayPacket<N, T>,
+    ) -> packet::Lanes<Option<T>, N> {
+        self.closest_ray_packet_intersection(packet)
+            .map(|hit| hit.map(|(dist, _)| dist))
     }
 }
@@ -0,0 +1,56 @@
+//! Boolean combinations of solids, for point queries such as collision
+//! checks and keeping objects apart. Not traced.
+
+use super::Solid;
+use crate::Float;
+use crate::Real;
+
+pub enum Csg<T = Float> {
+    Shape(super::Shape<T>),
+    Union(Box<Csg<T>>, Box<Csg<T>>),
+    Intersection(Box<Csg<T>>, Box<Csg<T>>),
+    /// the first without the second
+    Difference(Box<Csg<T>>, Box<Csg<T>>),
+}
+
+impl<T: Real> Csg<T> {
+    pub fn union(self, other: impl Into<Csg<T>>) -> Self {
+        Csg::Union(Box::new(self), Box::new(other.into()))
+    }
+
+    pub fn intersection(self, other: impl Into<Csg<T>>) -> Self {
+        Csg::Intersection(Box::new(self), Box::new(other.into()))
+    }
+
+    pub fn difference(self, other: impl Into<Csg<T>>) -> Self {
+        Csg::Difference(Box::new(self), Box::new(other.into()))
+    }
+}
+
+impl<T: Real> From<super::Shape<T>> for Csg<T> {
+    fn from(shape: super::Shape<T>) -> Self {
+        Csg::Shape(shape)
+    }
+}
+
+impl<T: Real> Solid<T> for Csg<T> {
+    fn contains(&self, point: &nalgebra::Vector3<T>) -> bool {
+        match self {
+            Csg::Shape(s) => s.contains(point),
+            Csg::Union(a, b) => a.contains(point) || b.contains(point),
+            Csg::Intersection(a, b) => a.contains(point) && b.contains(point),
+            Csg::Difference(a, b) => a.contains(point) && !b.contains(point),
+        }
+    }
+
+    /// Exact outside unions, elsewhere a bound never further from 0 than the
+    /// true distance, so objects kept this far apart do not overlap.
+    fn signed_distance(&self, point: &nalgebra::Vector3<T>) -> T {
+        match self {
+            Csg::Shape(s) => s.signed_distance(point),
+            Csg::Union(a, b) => a.signed_distance(point).min(b.signed_distance(point)),
+            Csg::Intersection(a, b) => a.signed_distance(point).max(b.signed_distance(point)),
+            Csg::Difference(a, b) => a.signed_distance(point).max(-b.signed_distance(point)),
+        }
+    }
+}
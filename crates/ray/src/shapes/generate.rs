@@ -0,0 +1,501 @@
+//! Triangle meshes of common solids.
+//!
+//! Triangles wind counter clockwise seen from outside, so their normals
+//! point out. Solids are centered on the origin, solids of revolution turn
+//! around the z axis. Generated in [`Float`] precision.
+
+use std::collections::HashMap;
+
+use super::composite;
+use super::triangle;
+use crate::Float;
+use crate::consts::PI;
+use crate::material;
+
+/// Triangles with their vertex uvs and shading normals, in a, b, c order
+#[derive(Clone, Debug, Default)]
+pub struct Mesh {
+    triangles: Vec<triangle::Triangle>,
+    uvs: Vec<[nalgebra::Vector2<Float>; 3]>,
+    normals: Vec<[nalgebra::UnitVector3<Float>; 3]>,
+}
+
+#[derive(Copy, Clone)]
+struct Vertex {
+    position: nalgebra::Vector3<Float>,
+    uv: nalgebra::Vector2<Float>,
+    normal: nalgebra::UnitVector3<Float>,
+}
+
+impl Mesh {
+    pub fn triangles(&self) -> &[triangle::Triangle] {
+        &self.triangles
+    }
+
+    pub fn uvs(&self) -> &[[nalgebra::Vector2<Float>; 3]] {
+        &self.uvs
+    }
+
+    /// smooth across curved surfaces, sharp across edges and creases
+    pub fn normals(&self) -> &[[nalgebra::UnitVector3<Float>; 3]] {
+        &self.normals
+    }
+
+    /// triangles and uvs as one object, the shading normals are dropped
+    pub fn into_object(self, material: material::MaterialId) -> composite::CompositeObject {
+        composite::CompositeObject::new(self.triangles, material).with_uvs(self.uvs)
+    }
+
+    /// skips triangles with two vertices in the same place, where a surface meets an axis
+    fn push(&mut self, [a, b, c]: [Vertex; 3]) {
+        if a.position == b.position || b.position == c.position || c.position == a.position {
+            return;
+        }
+        self.triangles
+            .push(triangle::Triangle::new(a.position, b.position, c.position));
+        self.uvs.push([a.uv, b.uv, c.uv]);
+        self.normals.push([a.normal, b.normal, c.normal]);
+    }
+
+    /// a, b, c and c, d, a, like [`triangle::quad_to_triangles`]
+    fn push_quad(&mut self, [a, b, c, d]: [Vertex; 4]) {
+        self.push([a, b, c]);
+        self.push([c, d, a]);
+    }
+}
+
+/// Axis aligned box, flat faces with uvs spanning 0 to 1 on each
+///
+/// # Arguments
+/// - `size`: edge lengths along x, y and z
+pub fn cuboid(size: &nalgebra::Vector3<Float>) -> Mesh {
+    debug_assert!(size.min() > 0.0, "size can not be <= 0.0");
+    let half = size / 2.;
+    let mut mesh = Mesh::default();
+    for axis in 0..3 {
+        for sign in [-1., 1.] {
+            let normal = nalgebra::Vector3::ith(axis, sign);
+            // u x v = normal, so the face is counter clockwise seen from outside
+            let (mut u, mut v) = ((axis + 1) % 3, (axis + 2) % 3);
+            if sign < 0. {
+                std::mem::swap(&mut u, &mut v);
+            }
+            let corner = |su: Float, sv: Float| Vertex {
+                position: normal.component_mul(&half)
+                    + nalgebra::Vector3::ith(u, su * half[u])
+                    + nalgebra::Vector3::ith(v, sv * half[v]),
+                uv: nalgebra::Vector2::new((su + 1.) / 2., (sv + 1.) / 2.),
+                normal: nalgebra::UnitVector3::new_unchecked(normal),
+            };
+            mesh.push_quad([
+                corner(-1., -1.),
+                corner(1., -1.),
+                corner(1., 1.),
+                corner(-1., 1.),
+            ]);
+        }
+    }
+    mesh
+}
+
+/// Flat rectangle in the xy plane facing +z, split into a grid of quads
+///
+/// # Arguments
+/// - `size`: extent along x and y
+/// - `divisions`: quads along x and y
+pub fn grid(size: &nalgebra::Vector2<Float>, divisions: [usize; 2]) -> Mesh {
+    debug_assert!(size.min() > 0.0, "size can not be <= 0.0");
+    debug_assert!(divisions.iter().all(|d| *d > 0), "divisions can not be 0");
+    let [nx, ny] = divisions;
+    let vertex = |i: usize, j: usize| {
+        let uv = nalgebra::Vector2::new(i as Float / nx as Float, j as Float / ny as Float);
+        Vertex {
+            position: nalgebra::Vector3::new((uv.x - 0.5) * size.x, (uv.y - 0.5) * size.y, 0.),
+            uv,
+            normal: nalgebra::Vector3::z_axis(),
+        }
+    };
+    let mut mesh = Mesh::default();
+    for j in 0..ny {
+        for i in 0..nx {
+            mesh.push_quad([
+                vertex(i, j),
+                vertex(i + 1, j),
+                vertex(i + 1, j + 1),
+                vertex(i, j + 1),
+            ]);
+        }
+    }
+    mesh
+}
+
+/// Sphere of latitude rings and longitude segments, poles on the z axis
+pub fn uv_sphere(radius: Float, segments: usize, rings: usize) -> Mesh {
+    debug_assert!(radius > 0.0, "radius can not be <= 0.0");
+    debug_assert!(rings >= 2, "rings can not be < 2");
+    let profile = (0..=rings)
+        .map(|i| {
+            let (sin, cos) = (PI * i as Float / rings as Float).sin_cos();
+            match i == 0 || i == rings {
+                // exactly on the axis, so the poles close up
+                true => nalgebra::Vector2::new(0., -cos.signum() * radius),
+                false => nalgebra::Vector2::new(sin * radius, -cos * radius),
+            }
+        })
+        .collect::<Vec<_>>();
+    let mut mesh = revolve(&profile, segments);
+    mesh.normals = radial_normals(&mesh.triangles);
+    mesh
+}
+
+/// Sphere of near equal triangles, an icosahedron with every triangle split
+/// in four `level` times
+pub fn icosphere(radius: Float, level: u32) -> Mesh {
+    debug_assert!(radius > 0.0, "radius can not be <= 0.0");
+    let t = (1. + Float::sqrt(5.)) / 2.;
+    let mut vertices = [
+        [-1., t, 0.],
+        [1., t, 0.],
+        [-1., -t, 0.],
+        [1., -t, 0.],
+        [0., -1., t],
+        [0., 1., t],
+        [0., -1., -t],
+        [0., 1., -t],
+        [t, 0., -1.],
+        [t, 0., 1.],
+        [-t, 0., -1.],
+        [-t, 0., 1.],
+    ]
+    .map(|v| nalgebra::Vector3::from(v).normalize())
+    .to_vec();
+    let mut faces = vec![
+        [0, 11, 5],
+        [0, 5, 1],
+        [0, 1, 7],
+        [0, 7, 10],
+        [0, 10, 11],
+        [1, 5, 9],
+        [5, 11, 4],
+        [11, 10, 2],
+        [10, 7, 6],
+        [7, 1, 8],
+        [3, 9, 4],
+        [3, 4, 2],
+        [3, 2, 6],
+        [3, 6, 8],
+        [3, 8, 9],
+        [4, 9, 5],
+        [2, 4, 11],
+        [6, 2, 10],
+        [8, 6, 7],
+        [9, 8, 1],
+    ];
+
+    for _ in 0..level {
+        // edge -> vertex at its middle, shared by both triangles of the edge
+        let mut middles = HashMap::new();
+        let mut middle = |a: usize, b: usize| {
+            *middles.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                vertices.push((vertices[a] + vertices[b]).normalize());
+                vertices.len() - 1
+            })
+        };
+        faces = faces
+            .into_iter()
+            .flat_map(|[a, b, c]| {
+                let (ab, bc, ca) = (middle(a, b), middle(b, c), middle(c, a));
+                [[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+            })
+            .collect();
+    }
+
+    let mut mesh = Mesh::default();
+    for face in faces {
+        let directions = face.map(|v| vertices[v]);
+        let mut uvs = directions.map(|d| {
+            nalgebra::Vector2::new(
+                d.y.atan2(d.x) / (2. * PI) + 0.5,
+                d.z.clamp(-1., 1.).acos() / PI,
+            )
+        });
+        // triangles across the seam take u past 1 rather than wrapping around
+        if uvs.iter().map(|uv| uv.x).fold(0., Float::max)
+            - uvs.iter().map(|uv| uv.x).fold(1., Float::min)
+            > 0.5
+        {
+            uvs.iter_mut()
+                .filter(|uv| uv.x < 0.5)
+                .for_each(|uv| uv.x += 1.);
+        }
+        let [a, b, c] = [0, 1, 2].map(|i| Vertex {
+            position: directions[i] * radius,
+            uv: uvs[i],
+            normal: nalgebra::UnitVector3::new_unchecked(directions[i]),
+        });
+        mesh.push([a, b, c]);
+    }
+    mesh
+}
+
+/// Closed cylinder with flat caps
+pub fn cylinder(radius: Float, height: Float, segments: usize) -> Mesh {
+    debug_assert!(radius > 0.0, "radius can not be <= 0.0");
+    debug_assert!(height > 0.0, "height can not be <= 0.0");
+    let (bottom, top) = (-height / 2., height / 2.);
+    revolve(
+        &[
+            nalgebra::Vector2::new(0., bottom),
+            nalgebra::Vector2::new(radius, bottom),
+            nalgebra::Vector2::new(radius, bottom),
+            nalgebra::Vector2::new(radius, top),
+            nalgebra::Vector2::new(radius, top),
+            nalgebra::Vector2::new(0., top),
+        ],
+        segments,
+    )
+}
+
+/// Closed cone, base toward -z and tip toward +z
+pub fn cone(radius: Float, height: Float, segments: usize) -> Mesh {
+    debug_assert!(radius > 0.0, "radius can not be <= 0.0");
+    debug_assert!(height > 0.0, "height can not be <= 0.0");
+    let (bottom, top) = (-height / 2., height / 2.);
+    revolve(
+        &[
+            nalgebra::Vector2::new(0., bottom),
+            nalgebra::Vector2::new(radius, bottom),
+            nalgebra::Vector2::new(radius, bottom),
+            nalgebra::Vector2::new(0., top),
+        ],
+        segments,
+    )
+}
+
+/// Ring in the xy plane
+///
+/// # Arguments
+/// - `major_radius`: from the z axis to the middle of the tube
+/// - `minor_radius`: of the tube
+/// - `segments`: around the z axis
+/// - `rings`: around the tube
+pub fn torus(major_radius: Float, minor_radius: Float, segments: usize, rings: usize) -> Mesh {
+    debug_assert!(minor_radius > 0.0, "minor radius can not be <= 0.0");
+    debug_assert!(
+        major_radius > minor_radius,
+        "major radius can not be <= minor radius"
+    );
+    debug_assert!(rings >= 3, "rings can not be < 3");
+    let profile = (0..=rings)
+        .map(|i| {
+            // the last point is exactly the first, closing the tube
+            let (sin, cos) = (2. * PI * (i % rings) as Float / rings as Float).sin_cos();
+            nalgebra::Vector2::new(major_radius + minor_radius * cos, minor_radius * sin)
+        })
+        .collect::<Vec<_>>();
+    revolve(&profile, segments)
+}
+
+/// Surface of revolution, turning a profile around the z axis.
+///
+/// Profile points are (distance from the axis, z), running counter clockwise
+/// around the solid so it is on the left. Normals are smooth along the
+/// profile, repeat a point for a crease. Ending on the first point closes
+/// the profile, start and end on the axis to close the solid.
+///
+/// # Arguments
+/// - `profile`: the outline, at least two points
+/// - `segments`: around the axis
+pub fn revolve(profile: &[nalgebra::Vector2<Float>], segments: usize) -> Mesh {
+    debug_assert!(profile.len() >= 2, "profile can not have < 2 points");
+    debug_assert!(
+        profile.iter().all(|p| p.x >= 0.0),
+        "profile can not cross the axis"
+    );
+    debug_assert!(segments >= 3, "segments can not be < 3");
+
+    let closed = profile.first() == profile.last();
+    let edges = profile.len() - 1;
+    // outward, to the right of the direction of travel, None for repeated points
+    let edge_normals = profile
+        .windows(2)
+        .map(|p| {
+            let along = p[1] - p[0];
+            nalgebra::Unit::try_new(nalgebra::Vector2::new(along.y, -along.x), 0.)
+        })
+        .collect::<Vec<_>>();
+    let mut lengths = vec![0.];
+    for p in profile.windows(2) {
+        lengths.push(lengths[lengths.len() - 1] + (p[1] - p[0]).norm());
+    }
+    let total = lengths[edges].max(Float::MIN_POSITIVE);
+
+    // averaged with the neighbouring edge, unless that is a crease
+    let smooth = |edge: usize, neighbour: Option<usize>| {
+        let normal = edge_normals[edge].expect("only called for edges with length");
+        match neighbour.and_then(|n| edge_normals[n]) {
+            Some(other) => {
+                nalgebra::Unit::try_new(normal.into_inner() + other.into_inner(), Float::EPSILON)
+                    .unwrap_or(normal)
+            }
+            None => normal,
+        }
+    };
+
+    let mut mesh = Mesh::default();
+    for edge in (0..edges).filter(|e| edge_normals[*e].is_some()) {
+        let previous = match edge {
+            0 => closed.then_some(edges - 1),
+            _ => Some(edge - 1),
+        };
+        let next = match edge + 1 == edges {
+            true => closed.then_some(0),
+            false => Some(edge + 1),
+        };
+        let ends = [
+            (edge, smooth(edge, previous)),
+            (edge + 1, smooth(edge, next)),
+        ];
+
+        for segment in 0..segments {
+            let vertex = |(point, normal): (usize, nalgebra::Unit<nalgebra::Vector2<Float>>),
+                          around: usize| {
+                // wrapping the angle makes the seam watertight
+                let (sin, cos) =
+                    (2. * PI * (around % segments) as Float / segments as Float).sin_cos();
+                let p = profile[point];
+                Vertex {
+                    position: nalgebra::Vector3::new(p.x * cos, p.x * sin, p.y),
+                    uv: nalgebra::Vector2::new(
+                        around as Float / segments as Float,
+                        lengths[point] / total,
+                    ),
+                    normal: nalgebra::UnitVector3::new_normalize(nalgebra::Vector3::new(
+                        normal.x * cos,
+                        normal.x * sin,
+                        normal.y,
+                    )),
+                }
+            };
+            mesh.push_quad([
+                vertex(ends[0], segment),
+                vertex(ends[0], segment + 1),
+                vertex(ends[1], segment + 1),
+                vertex(ends[1], segment),
+            ]);
+        }
+    }
+    mesh
+}
+
+/// Prism, pushing a polygon in the xy plane from z = 0 to z = `depth`,
+/// with flat sides and caps.
+///
+/// # Arguments
+/// - `profile`: simple polygon, without repeating the first point, either winding
+/// - `depth`: along +z
+pub fn extrude(profile: &[nalgebra::Vector2<Float>], depth: Float) -> Mesh {
+    debug_assert!(profile.len() >= 3, "profile can not have < 3 points");
+    debug_assert!(depth > 0.0, "depth can not be <= 0.0");
+
+    let mut polygon = profile.to_vec();
+    let twice_area = (0..polygon.len())
+        .map(|i| polygon[i].perp(&polygon[(i + 1) % polygon.len()]))
+        .sum::<Float>();
+    if twice_area < 0. {
+        polygon.reverse();
+    }
+    let n = polygon.len();
+
+    let mut mesh = Mesh::default();
+    let perimeter = (0..n)
+        .map(|i| (polygon[(i + 1) % n] - polygon[i]).norm())
+        .sum::<Float>();
+    let mut along = 0.;
+    for i in 0..n {
+        let (from, to) = (polygon[i], polygon[(i + 1) % n]);
+        let length = (to - from).norm();
+        let Some(normal) = nalgebra::UnitVector3::try_new(
+            nalgebra::Vector3::new(to.y - from.y, from.x - to.x, 0.),
+            0.,
+        ) else {
+            continue;
+        };
+        let vertex = |p: nalgebra::Vector2<Float>, u: Float, z: Float| Vertex {
+            position: nalgebra::Vector3::new(p.x, p.y, z),
+            uv: nalgebra::Vector2::new(u / perimeter, z / depth),
+            normal,
+        };
+        mesh.push_quad([
+            vertex(from, along, 0.),
+            vertex(to, along + length, 0.),
+            vertex(to, along + length, depth),
+            vertex(from, along, depth),
+        ]);
+        along += length;
+    }
+
+    // caps, uvs spanning the bounds of the polygon
+    let min = polygon.iter().fold(polygon[0], |m, p| m.inf(p));
+    let extent = polygon.iter().fold(polygon[0], |m, p| m.sup(p)) - min;
+    for (z, normal) in [
+        (0., -nalgebra::Vector3::z_axis()),
+        (depth, nalgebra::Vector3::z_axis()),
+    ] {
+        let vertex = |i: usize| Vertex {
+            position: nalgebra::Vector3::new(polygon[i].x, polygon[i].y, z),
+            uv: (polygon[i] - min).component_div(&extent),
+            normal,
+        };
+        for [a, b, c] in triangulate(&polygon) {
+            match z == 0. {
+                // seen from below
+                true => mesh.push([vertex(a), vertex(c), vertex(b)]),
+                false => mesh.push([vertex(a), vertex(b), vertex(c)]),
+            }
+        }
+    }
+    mesh
+}
+
+/// Ear clipping of a counter clockwise simple polygon, counter clockwise triangles
+fn triangulate(polygon: &[nalgebra::Vector2<Float>]) -> Vec<[usize; 3]> {
+    let turn =
+        |a: usize, b: usize, c: usize| (polygon[b] - polygon[a]).perp(&(polygon[c] - polygon[a]));
+    let mut remaining = (0..polygon.len()).collect::<Vec<_>>();
+    let mut triangles = Vec::with_capacity(polygon.len() - 2);
+    while remaining.len() > 3 {
+        let m = remaining.len();
+        let corner = |i: usize| {
+            [
+                remaining[(i + m - 1) % m],
+                remaining[i],
+                remaining[(i + 1) % m],
+            ]
+        };
+        let ear = (0..m).find(|i| {
+            let [a, b, c] = corner(*i);
+            turn(a, b, c) > 0.
+                && remaining.iter().all(|p| {
+                    [a, b, c].contains(p)
+                        || turn(a, b, *p) < 0.
+                        || turn(b, c, *p) < 0.
+                        || turn(c, a, *p) < 0.
+                })
+        });
+        // only for polygons that are not simple, clip anything to make progress
+        let ear = ear.unwrap_or(0);
+        triangles.push(corner(ear));
+        remaining.remove(ear);
+    }
+    triangles.push([remaining[0], remaining[1], remaining[2]]);
+    triangles
+}
+
+/// normals of a sphere around the origin
+fn radial_normals(triangles: &[triangle::Triangle]) -> Vec<[nalgebra::UnitVector3<Float>; 3]> {
+    triangles
+        .iter()
+        .map(|t| [t.a(), t.b(), t.c()].map(|v| nalgebra::UnitVector3::new_normalize(*v)))
+        .collect()
+}
@@ -0,0 +1,332 @@
+//! Analysis and repair of triangle meshes.
+//!
+//! Meshes are triangle soups, vertices are shared by triangles when their
+//! positions are exactly equal.
+
+use std::collections::HashMap;
+
+use super::sphere;
+use super::triangle;
+use crate::Float;
+use crate::Real;
+use crate::aabb;
+
+pub fn bounds<T: Real>(mesh: &[triangle::Triangle<T>]) -> aabb::Aabb<T> {
+    mesh.iter()
+        .fold(aabb::Aabb::EMPTY, |bounds, t| bounds.union(&t.bounds()))
+}
+
+/// Sphere around every vertex, Ritter 1990, up to a few percent larger than the smallest one.
+pub fn bounding_sphere<T: Real>(mesh: &[triangle::Triangle<T>]) -> sphere::Sphere<T> {
+    let points = || mesh.iter().flat_map(|t| [t.a(), t.b(), t.c()]);
+    let Some(first) = points().next() else {
+        return sphere::Sphere::new(nalgebra::Vector3::zeros(), T::zero());
+    };
+    let farthest = |from: &nalgebra::Vector3<T>| {
+        points()
+            .max_by(|a, b| {
+                (*a - from)
+                    .norm_squared()
+                    .total_cmp(&(*b - from).norm_squared())
+            })
+            .unwrap_or(first)
+    };
+    let start = farthest(first);
+    let end = farthest(start);
+
+    let two = nalgebra::convert::<_, T>(2.);
+    let mut center = (start + end) / two;
+    let mut radius = (end - start).norm() / two;
+    for point in points() {
+        let distance = (point - center).norm();
+        if distance > radius {
+            // grow just enough to hold the point, keeping the far side in place
+            let new_radius = (radius + distance) / two;
+            center += (point - center) * ((new_radius - radius) / distance);
+            radius = new_radius;
+        }
+    }
+    sphere::Sphere::new(center, radius)
+}
+
+pub fn surface_area<T: Real>(mesh: &[triangle::Triangle<T>]) -> T {
+    mesh.iter().fold(T::zero(), |area, t| area + t.area())
+}
+
+/// Enclosed volume of a closed mesh, positive when the triangles wind
+/// counter clockwise seen from outside (normals pointing out).
+pub fn signed_volume<T: Real>(mesh: &[triangle::Triangle<T>]) -> T {
+    mesh.iter().fold(T::zero(), |volume, t| {
+        volume + t.a().dot(&t.b().cross(t.c()))
+    }) / nalgebra::convert(6.)
+}
+
+/// Center of mass of the solid a closed mesh encloses, the area weighted
+/// center of the surface when it encloses no volume.
+pub fn centroid<T: Real>(mesh: &[triangle::Triangle<T>]) -> nalgebra::Vector3<T> {
+    let volume = signed_volume(mesh);
+    let area = surface_area(mesh);
+    if volume.abs() > T::default_epsilon() * area.powf(nalgebra::convert(1.5)) {
+        // sum of the tetrahedra to the origin
+        let moment = mesh
+            .iter()
+            .map(|t| (t.a() + t.b() + t.c()) * t.a().dot(&t.b().cross(t.c())))
+            .sum::<nalgebra::Vector3<T>>();
+        return moment / (volume * nalgebra::convert(24.));
+    }
+    if area == T::zero() {
+        return bounds(mesh).centroid();
+    }
+    mesh.iter()
+        .map(|t| (t.a() + t.b() + t.c()) * (t.area() / nalgebra::convert(3.)))
+        .sum::<nalgebra::Vector3<T>>()
+        / area
+}
+
+/// Generalized winding number of a point, the solid angle the triangles
+/// cover seen from it over 4 pi.
+///
+/// 1 inside a closed mesh with outward normals, -1 with inward normals and
+/// 0 outside, in between for meshes with holes.
+pub fn winding_number<T: Real>(mesh: &[triangle::Triangle<T>], point: &nalgebra::Vector3<T>) -> T {
+    // Van Oosterom & Strackee, the solid angle of a triangle
+    mesh.iter().fold(T::zero(), |solid_angle, t| {
+        let [a, b, c] = [t.a(), t.b(), t.c()].map(|v| v - point);
+        let [la, lb, lc] = [a.norm(), b.norm(), c.norm()];
+        let numerator = a.dot(&b.cross(&c));
+        let denominator = la * lb * lc + a.dot(&b) * lc + b.dot(&c) * la + c.dot(&a) * lb;
+        solid_angle + numerator.atan2(denominator) * nalgebra::convert(2.)
+    }) / (T::pi() * nalgebra::convert(4.))
+}
+
+pub fn face_normals<T: Real>(mesh: &[triangle::Triangle<T>]) -> Vec<nalgebra::UnitVector3<T>> {
+    mesh.iter().map(|t| t.normal()).collect()
+}
+
+/// Smooth normals at the a, b and c vertex of every triangle, averaging
+/// the normals of the triangles sharing the vertex weighted by their area.
+pub fn vertex_normals<T: Real>(
+    mesh: &[triangle::Triangle<T>],
+) -> Vec<[nalgebra::UnitVector3<T>; 3]> {
+    let (indices, positions) = weld(mesh);
+    let mut sums = vec![nalgebra::Vector3::zeros(); positions.len()];
+    for (t, vertices) in mesh.iter().zip(&indices) {
+        // length is twice the area
+        let weighted = (t.b() - t.a()).cross(&(t.c() - t.a()));
+        for v in vertices {
+            sums[*v] += weighted;
+        }
+    }
+
+    mesh.iter()
+        .zip(&indices)
+        .map(|(t, vertices)| {
+            vertices.map(|v| {
+                nalgebra::UnitVector3::try_new(sums[v], T::default_epsilon())
+                    .unwrap_or_else(|| t.normal())
+            })
+        })
+        .collect()
+}
+
+/// Problems found by [`validate`]
+#[derive(Clone, Debug)]
+pub struct Validation<T = Float> {
+    /// triangles with no area
+    pub degenerate_triangles: Vec<usize>,
+    /// shared by more than two triangles
+    pub non_manifold_edges: Vec<[nalgebra::Vector3<T>; 2]>,
+    /// belonging to a single triangle, the rims of holes
+    pub boundary_edges: Vec<[nalgebra::Vector3<T>; 2]>,
+    /// run in the same direction by both of their triangles, one of them being flipped
+    pub inconsistent_edges: Vec<[nalgebra::Vector3<T>; 2]>,
+    /// loops of boundary edges
+    pub holes: usize,
+}
+
+impl<T> Validation<T> {
+    /// every edge shared by exactly two triangles
+    pub fn is_closed(&self) -> bool {
+        self.boundary_edges.is_empty() && self.non_manifold_edges.is_empty()
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.is_closed()
+            && self.degenerate_triangles.is_empty()
+            && self.inconsistent_edges.is_empty()
+    }
+}
+
+pub fn validate<T: Real>(mesh: &[triangle::Triangle<T>]) -> Validation<T> {
+    let (indices, positions) = weld(mesh);
+    let mut validation = Validation {
+        degenerate_triangles: mesh
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| {
+                let longest = [t.b() - t.a(), t.c() - t.b(), t.a() - t.c()]
+                    .iter()
+                    .map(|e| e.norm_squared())
+                    .fold(T::zero(), T::max);
+                t.area() <= T::default_epsilon() * longest
+            })
+            .map(|(i, _)| i)
+            .collect(),
+        non_manifold_edges: Vec::new(),
+        boundary_edges: Vec::new(),
+        inconsistent_edges: Vec::new(),
+        holes: 0,
+    };
+
+    let edge = |(from, to): (usize, usize)| [positions[from], positions[to]];
+    let mut rims = Vec::new();
+    for (key, uses) in edges(&indices) {
+        match uses.as_slice() {
+            [_] => {
+                validation.boundary_edges.push(edge(key));
+                rims.push(key);
+            }
+            [(_, first), (_, second)] if first == second => {
+                validation.inconsistent_edges.push(edge(key))
+            }
+            [_, _] => {}
+            _ => validation.non_manifold_edges.push(edge(key)),
+        }
+    }
+
+    // every connected group of rim vertices is one hole
+    let mut parent = (0..positions.len()).collect::<Vec<_>>();
+    for (from, to) in &rims {
+        let (a, b) = (root(&mut parent, *from), root(&mut parent, *to));
+        parent[a] = b;
+    }
+    let mut loops = rims
+        .iter()
+        .map(|(from, _)| root(&mut parent, *from))
+        .collect::<Vec<_>>();
+    loops.sort_unstable();
+    loops.dedup();
+    validation.holes = loops.len();
+
+    validation
+}
+
+/// Flips triangles so neighbours wind the same way across every edge they
+/// share, then turns every closed part outwards. Open parts keep the
+/// winding of their first triangle.
+///
+/// Returns whether each triangle was flipped.
+pub fn orient<T: Real>(mesh: &mut [triangle::Triangle<T>]) -> Vec<bool> {
+    let (indices, _) = weld(mesh);
+    let edges = edges(&indices);
+    let mut neighbours = vec![Vec::new(); mesh.len()];
+    let mut closed = vec![true; mesh.len()];
+    for uses in edges.values() {
+        match uses.as_slice() {
+            [(a, a_forward), (b, b_forward)] => {
+                // the same direction along the edge means one of them is flipped
+                let same = a_forward == b_forward;
+                neighbours[*a].push((*b, same));
+                neighbours[*b].push((*a, same));
+            }
+            _ => uses.iter().for_each(|(t, _)| closed[*t] = false),
+        }
+    }
+
+    let mut flip = vec![None; mesh.len()];
+    for seed in 0..mesh.len() {
+        if flip[seed].is_some() {
+            continue;
+        }
+        flip[seed] = Some(false);
+        let mut part = vec![seed];
+        let mut stack = vec![seed];
+        while let Some(t) = stack.pop() {
+            let flipped = flip[t] == Some(true);
+            for (n, same) in &neighbours[t] {
+                if flip[*n].is_none() {
+                    flip[*n] = Some(flipped ^ same);
+                    part.push(*n);
+                    stack.push(*n);
+                }
+            }
+        }
+
+        let oriented = |t: usize| match flip[t] == Some(true) {
+            true => mesh[t].flipped(),
+            false => mesh[t],
+        };
+        let volume = signed_volume(&part.iter().map(|t| oriented(*t)).collect::<Vec<_>>());
+        if volume < T::zero() && part.iter().all(|t| closed[*t]) {
+            part.iter().for_each(|t| flip[*t] = flip[*t].map(|f| !f));
+        }
+    }
+
+    let flip = flip
+        .into_iter()
+        .map(|f| f == Some(true))
+        .collect::<Vec<_>>();
+    for (t, flipped) in mesh.iter_mut().zip(&flip) {
+        if *flipped {
+            *t = t.flipped();
+        }
+    }
+    flip
+}
+
+/// Shared vertex indices of every triangle and the distinct positions.
+fn weld<T: Real>(mesh: &[triangle::Triangle<T>]) -> (Vec<[usize; 3]>, Vec<nalgebra::Vector3<T>>) {
+    // adding 0 turns -0 into 0, so both weld together
+    let corners = mesh
+        .iter()
+        .flat_map(|t| [t.a(), t.b(), t.c()].map(|v| v.add_scalar(T::zero())))
+        .collect::<Vec<_>>();
+    let mut order = (0..corners.len()).collect::<Vec<_>>();
+    let compare = |a: &nalgebra::Vector3<T>, b: &nalgebra::Vector3<T>| {
+        a.x.total_cmp(&b.x)
+            .then(a.y.total_cmp(&b.y))
+            .then(a.z.total_cmp(&b.z))
+    };
+    order.sort_unstable_by(|a, b| compare(&corners[*a], &corners[*b]));
+
+    let mut positions: Vec<nalgebra::Vector3<T>> = Vec::new();
+    let mut index = vec![0; corners.len()];
+    for corner in order {
+        if positions
+            .last()
+            .is_none_or(|last| compare(last, &corners[corner]).is_ne())
+        {
+            positions.push(corners[corner]);
+        }
+        index[corner] = positions.len() - 1;
+    }
+
+    let indices = index.chunks_exact(3).map(|c| [c[0], c[1], c[2]]).collect();
+    (indices, positions)
+}
+
+/// Triangles using every undirected edge (low index, high index), with
+/// whether they run it from low to high.
+fn edges(indices: &[[usize; 3]]) -> HashMap<(usize, usize), Vec<(usize, bool)>> {
+    let mut edges = HashMap::<_, Vec<_>>::new();
+    for (t, [a, b, c]) in indices.iter().enumerate() {
+        for (from, to) in [(*a, *b), (*b, *c), (*c, *a)] {
+            if from != to {
+                edges
+                    .entry((from.min(to), from.max(to)))
+                    .or_default()
+                    .push((t, from < to));
+            }
+        }
+    }
+    edges
+}
+
+/// union find root, halving the path on the way
+fn root(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
@@ -1,29 +1,194 @@
+use crate::Float;
+use crate::Real;
+use crate::packet;
 use crate::ray;
 
 pub mod composite;
+pub mod csg;
+pub mod generate;
+pub mod mesh;
 pub mod plane;
 pub mod sphere;
 pub mod triangle;
 
-pub trait Traceable {
-    /// trace to the first intersection in a positive direction
-    fn trace(&self, ray: &ray::Ray, epsilon: f32) -> Option<f32>;
+/// Where and how a ray hit a surface
+#[derive(Copy, Clone, Debug)]
+pub struct Hit<T = Float> {
+    pub distance: T,
+    pub point: nalgebra::Vector3<T>,
+    /// geometric normal, out of the shape, not necessarily facing the ray
+    pub normal: nalgebra::UnitVector3<T>,
+    /// direction of increasing u, perpendicular to the normal
+    pub tangent: nalgebra::UnitVector3<T>,
+    pub uv: nalgebra::Vector2<T>,
+    /// width of the pixel footprint in uv units, 0 when the ray has no differentials
+    pub uv_footprint: T,
+    /// index of the triangle within a composite, 0 for other shapes
+    pub primitive: usize,
 }
 
-pub enum Shape {
-    Composite(composite::CompositeObject),
-    Plane(plane::Plane),
-    Sphere(sphere::Sphere),
-    Triangle(triangle::Triangle),
+impl<T: Real> Hit<T> {
+    /// Fills in the surface description of a hit.
+    ///
+    /// `uv_at` maps points on (or near) the surface to uv, used to work out
+    /// the footprint from the ray differentials.
+    pub(crate) fn new(
+        ray: &ray::Ray<T>,
+        distance: T,
+        normal: nalgebra::UnitVector3<T>,
+        tangent: &nalgebra::Vector3<T>,
+        uv_at: impl Fn(&nalgebra::Vector3<T>) -> nalgebra::Vector2<T>,
+    ) -> Self {
+        let point = ray.at(distance);
+        let uv = uv_at(&point);
+
+        // tangent with any normal component removed, any perpendicular when degenerate
+        let tangent = nalgebra::UnitVector3::try_new(
+            tangent - normal.into_inner() * normal.dot(tangent),
+            T::default_epsilon(),
+        )
+        .unwrap_or_else(|| perpendicular(&normal));
+
+        let uv_footprint = ray
+            .surface_differentials(&point, &normal)
+            .map(|(dpdx, dpdy)| {
+                let dx = uv_at(&(point + dpdx)) - uv;
+                let dy = uv_at(&(point + dpdy)) - uv;
+                dx.amax().max(dy.amax())
+            })
+            .unwrap_or_else(T::zero);
+
+        Self {
+            distance,
+            point,
+            normal,
+            tangent,
+            uv,
+            uv_footprint,
+            primitive: 0,
+        }
+    }
+
+    /// the same hit seen from another frame
+    pub fn transform(&self, transform: &nalgebra::Isometry3<T>) -> Self {
+        Self {
+            point: transform.transform_point(&self.point.into()).coords,
+            normal: transform.rotation * self.normal,
+            tangent: transform.rotation * self.tangent,
+            ..*self
+        }
+    }
+}
+
+/// any unit vector perpendicular to `v`
+pub(crate) fn perpendicular<T: Real>(v: &nalgebra::UnitVector3<T>) -> nalgebra::UnitVector3<T> {
+    let least_aligned = match v.iamin() {
+        0 => nalgebra::Vector3::x(),
+        1 => nalgebra::Vector3::y(),
+        _ => nalgebra::Vector3::z(),
+    };
+    nalgebra::UnitVector3::new_normalize(v.cross(&least_aligned))
+}
+
+/// Ray queries against a shape.
+///
+/// There is no epsilon, hits are exact within the interval of the ray. Rays
+/// leaving a surface avoid hitting it again by starting at
+/// [`ray::Ray::spawn`] or by shortening their interval.
+pub trait Traceable<T: Real = Float> {
+    /// trace to the first intersection within the interval of the ray
+    fn trace(&self, ray: &ray::Ray<T>) -> Option<T>;
+
+    /// [`Traceable::trace`], describing the surface at the intersection
+    fn hit(&self, ray: &ray::Ray<T>) -> Option<Hit<T>>;
+
+    /// whether anything is hit within the interval of the ray, e.g. for shadow rays
+    fn occluded(&self, ray: &ray::Ray<T>) -> bool {
+        self.trace(ray).is_some()
+    }
+
+    /// [`Traceable::trace`] for every ray in the packet
+    fn trace_packet<const N: usize>(
+        &self,
+        packet: &packet::RayPacket<N, T>,
+    ) -> packet::Lanes<Option<T>, N> {
+        std::array::from_fn(|lane| self.trace(&packet.ray(lane)))
+    }
+}
+
+/// Point queries on shapes enclosing space, e.g. for collision checks
+pub trait Solid<T: Real = Float> {
+    fn contains(&self, point: &nalgebra::Vector3<T>) -> bool;
+
+    /// distance to the surface, negative inside
+    fn signed_distance(&self, point: &nalgebra::Vector3<T>) -> T;
+}
+
+pub enum Shape<T = Float> {
+    Composite(composite::CompositeObject<T>),
+    Plane(plane::Plane<T>),
+    Sphere(sphere::Sphere<T>),
+    Triangle(triangle::Triangle<T>),
 }
 
-impl Traceable for Shape {
-    fn trace(&self, ray: &ray::Ray, epsilon: f32) -> Option<f32> {
+impl<T: Real> Traceable<T> for Shape<T> {
+    fn trace(&self, ray: &ray::Ray<T>) -> Option<T> {
+        match self {
+            Shape::Composite(s) => s.trace(ray),
+            Shape::Plane(s) => s.trace(ray),
+            Shape::Sphere(s) => s.trace(ray),
+            Shape::Triangle(s) => s.trace(ray),
+        }
+    }
+
+    fn hit(&self, ray: &ray::Ray<T>) -> Option<Hit<T>> {
+        match self {
+            Shape::Composite(s) => s.hit(ray),
+            Shape::Plane(s) => s.hit(ray),
+            Shape::Sphere(s) => s.hit(ray),
+            Shape::Triangle(s) => s.hit(ray),
+        }
+    }
+
+    fn occluded(&self, ray: &ray::Ray<T>) -> bool {
+        match self {
+            Shape::Composite(s) => s.occluded(ray),
+            Shape::Plane(s) => s.occluded(ray),
+            Shape::Sphere(s) => s.occluded(ray),
+            Shape::Triangle(s) => s.occluded(ray),
+        }
+    }
+
+    fn trace_packet<const N: usize>(
+        &self,
+        packet: &packet::RayPacket<N, T>,
+    ) -> packet::Lanes<Option<T>, N> {
+        match self {
+            Shape::Composite(s) => s.trace_packet(packet),
+            Shape::Plane(s) => s.trace_packet(packet),
+            Shape::Sphere(s) => s.trace_packet(packet),
+            Shape::Triangle(s) => s.trace_packet(packet),
+        }
+    }
+}
+
+/// triangles enclose nothing, their signed distance is never negative
+impl<T: Real> Solid<T> for Shape<T> {
+    fn contains(&self, point: &nalgebra::Vector3<T>) -> bool {
+        match self {
+            Shape::Composite(s) => s.contains(point),
+            Shape::Plane(s) => s.contains(point),
+            Shape::Sphere(s) => s.contains(point),
+            Shape::Triangle(_) => false,
+        }
+    }
+
+    fn signed_distance(&self, point: &nalgebra::Vector3<T>) -> T {
         match self {
-            Shape::Composite(s) => s.trace(ray, epsilon),
-            Shape::Plane(s) => s.trace(ray, epsilon),
-            Shape::Sphere(s) => s.trace(ray, epsilon),
-            Shape::Triangle(s) => s.trace(ray, epsilon),
+            Shape::Composite(s) => s.signed_distance(point),
+            Shape::Plane(s) => s.signed_distance(point),
+            Shape::Sphere(s) => s.signed_distance(point),
+            Shape::Triangle(s) => s.distance(point),
         }
     }
 }
@@ -1,31 +1,68 @@
+use crate::Float;
+use crate::Real;
 use crate::ray;
 
-pub enum PlaneIntersection {
-    Intersection(f32),
+pub enum PlaneIntersection<T = Float> {
+    Intersection(T),
     Contained,
     None,
 }
 
-pub struct Plane {
-    origin: nalgebra::Vector3<f32>,
-    normal: nalgebra::UnitVector3<f32>,
+pub struct Plane<T = Float> {
+    origin: nalgebra::Vector3<T>,
+    normal: nalgebra::UnitVector3<T>,
+    // uv tiling, u along u_axis, both repeating every uv_scale
+    u_axis: nalgebra::UnitVector3<T>,
+    uv_scale: T,
 }
 
-impl Plane {
-    pub fn new(origin: nalgebra::Vector3<f32>, normal: nalgebra::UnitVector3<f32>) -> Self {
-        Self { origin, normal }
+impl<T: Real> Plane<T> {
+    pub fn new(origin: nalgebra::Vector3<T>, normal: nalgebra::UnitVector3<T>) -> Self {
+        Self {
+            origin,
+            u_axis: super::perpendicular(&normal),
+            normal,
+            uv_scale: T::one(),
+        }
     }
 
-    pub fn ray_intersection(&self, ray: &ray::Ray, epsilon: f32) -> PlaneIntersection {
-        debug_assert!(epsilon > 0.0, "epsilon can not be negative");
+    /// Orients the uv tiling of the plane, starting at the origin.
+    ///
+    /// # Arguments
+    /// - `u_axis`: direction of increasing u, projected onto the plane
+    /// - `uv_scale`: distance covered by one unit of uv, i.e. one repeat of a texture
+    pub fn with_uv(mut self, u_axis: &nalgebra::Vector3<T>, uv_scale: T) -> Self {
+        debug_assert!(uv_scale > T::zero(), "uv scale can not be <= 0.0");
+        let projected = u_axis - self.normal.into_inner() * self.normal.dot(u_axis);
+        self.u_axis = nalgebra::UnitVector3::new_normalize(projected);
+        self.uv_scale = uv_scale;
+        self
+    }
 
+    pub fn origin(&self) -> &nalgebra::Vector3<T> {
+        &self.origin
+    }
+
+    pub fn normal(&self) -> &nalgebra::UnitVector3<T> {
+        &self.normal
+    }
+
+    /// planar coordinates of the point, see [`Plane::with_uv`]
+    pub fn uv(&self, point: &nalgebra::Vector3<T>) -> nalgebra::Vector2<T> {
+        let offset = point - self.origin;
+        let v_axis = self.normal.cross(&self.u_axis);
+        nalgebra::Vector2::new(offset.dot(&self.u_axis), offset.dot(&v_axis)) / self.uv_scale
+    }
+
+    pub fn closest_point(&self, point: &nalgebra::Vector3<T>) -> nalgebra::Vector3<T> {
+        point - self.normal.into_inner() * super::Solid::signed_distance(self, point)
+    }
+
+    pub fn ray_intersection(&self, ray: &ray::Ray<T>) -> PlaneIntersection<T> {
         let denominator = ray.direction().dot(&self.normal);
         let numerator = (self.origin - ray.origin()).dot(&self.normal);
 
-        match (
-            crate::is_zero(numerator, epsilon),
-            crate::is_zero(denominator, epsilon),
-        ) {
+        match (numerator == T::zero(), denominator == T::zero()) {
             (true, true) => PlaneIntersection::Contained,
             (false, true) => PlaneIntersection::None,
             _ => PlaneIntersection::Intersection(numerator / denominator),
@@ -33,12 +70,34 @@ impl Plane {
     }
 }
 
-impl super::Traceable for Plane {
-    fn trace(&self, ray: &crate::ray::Ray, epsilon: f32) -> Option<f32> {
-        match self.ray_intersection(ray, epsilon) {
-            PlaneIntersection::Intersection(dist) if dist >= 0. => Some(dist),
-            PlaneIntersection::Contained => Some(0.),
+/// the half space behind the plane, away from the normal
+impl<T: Real> super::Solid<T> for Plane<T> {
+    fn contains(&self, point: &nalgebra::Vector3<T>) -> bool {
+        super::Solid::signed_distance(self, point) <= T::zero()
+    }
+
+    fn signed_distance(&self, point: &nalgebra::Vector3<T>) -> T {
+        (point - self.origin).dot(&self.normal)
+    }
+}
+
+impl<T: Real> super::Traceable<T> for Plane<T> {
+    fn trace(&self, ray: &ray::Ray<T>) -> Option<T> {
+        match self.ray_intersection(ray) {
+            PlaneIntersection::Intersection(dist) if ray.contains(dist) => Some(dist),
+            PlaneIntersection::Contained => Some(ray.t_min()),
             _ => None,
         }
     }
+
+    fn hit(&self, ray: &ray::Ray<T>) -> Option<super::Hit<T>> {
+        let distance = self.trace(ray)?;
+        Some(super::Hit::new(
+            ray,
+            distance,
+            self.normal,
+            &self.u_axis,
+            |p| self.uv(p),
+        ))
+    }
 }
@@ -1,54 +1,104 @@
+use crate::Float;
+use crate::Real;
 use crate::ray;
 
-pub enum SphereIntersection {
-    Two(f32, f32),
-    One(f32),
+pub enum SphereIntersection<T = Float> {
+    Two(T, T),
+    One(T),
     None,
 }
 
-pub struct Sphere {
-    origin: nalgebra::Vector3<f32>,
-    radius: f32,
+pub struct Sphere<T = Float> {
+    origin: nalgebra::Vector3<T>,
+    radius: T,
 }
 
-impl Sphere {
-    pub fn new(origin: nalgebra::Vector3<f32>, radius: f32) -> Self {
+impl<T: Real> Sphere<T> {
+    pub fn new(origin: nalgebra::Vector3<T>, radius: T) -> Self {
         Self { origin, radius }
     }
 
-    pub fn ray_intersection(&self, ray: &ray::Ray, epsilon: f32) -> SphereIntersection {
-        debug_assert!(epsilon > 0.0, "epsilon can not be negative");
-
+    pub fn ray_intersection(&self, ray: &ray::Ray<T>) -> SphereIntersection<T> {
         let center_dist = ray.origin() - self.origin;
         let dir_dot_dist = ray.direction().dot(&center_dist);
 
         let discriminant =
             dir_dot_dist.powi(2) - (center_dist.dot(&center_dist) - self.radius.powi(2));
 
-        if discriminant < 0. {
+        if discriminant < T::zero() {
             return SphereIntersection::None;
         }
 
-        if crate::is_zero(discriminant, epsilon) {
+        if discriminant == T::zero() {
             return SphereIntersection::One(-dir_dot_dist);
         }
 
         let disc_sqrt = discriminant.sqrt();
         SphereIntersection::Two(-dir_dot_dist + disc_sqrt, -dir_dot_dist - disc_sqrt)
     }
+
+    pub fn origin(&self) -> &nalgebra::Vector3<T> {
+        &self.origin
+    }
+
+    pub fn radius(&self) -> T {
+        self.radius
+    }
+
+    /// closest point of the surface, any when the point is the origin
+    pub fn closest_point(&self, point: &nalgebra::Vector3<T>) -> nalgebra::Vector3<T> {
+        let direction = nalgebra::UnitVector3::try_new(point - self.origin, T::zero())
+            .unwrap_or_else(nalgebra::Vector3::x_axis);
+        self.origin + direction.into_inner() * self.radius
+    }
+
+    /// to the surface
+    pub fn distance(&self, point: &nalgebra::Vector3<T>) -> T {
+        super::Solid::signed_distance(self, point).abs()
+    }
+
+    pub fn normal(&self, point: &nalgebra::Vector3<T>) -> nalgebra::UnitVector3<T> {
+        nalgebra::UnitVector3::new_normalize(point - self.origin)
+    }
+
+    /// Spherical coordinates of the point, u around the z axis starting
+    /// at -x, v from +z (0) to -z (1).
+    pub fn uv(&self, point: &nalgebra::Vector3<T>) -> nalgebra::Vector2<T> {
+        let d = self.normal(point);
+        nalgebra::Vector2::new(
+            nalgebra::convert::<_, T>(0.5) + d.y.atan2(d.x) / T::two_pi(),
+            d.z.clamp(-T::one(), T::one()).acos() / T::pi(),
+        )
+    }
+}
+
+impl<T: Real> super::Solid<T> for Sphere<T> {
+    fn contains(&self, point: &nalgebra::Vector3<T>) -> bool {
+        (point - self.origin).norm_squared() <= self.radius * self.radius
+    }
+
+    fn signed_distance(&self, point: &nalgebra::Vector3<T>) -> T {
+        (point - self.origin).norm() - self.radius
+    }
 }
 
-impl super::Traceable for Sphere {
-    fn trace(&self, ray: &ray::Ray, epsilon: f32) -> Option<f32> {
-        match self.ray_intersection(ray, epsilon) {
-            SphereIntersection::Two(a, b) => match (a >= 0., b >= 0.) {
-                (true, true) => Some(a.min(b)),
-                (true, false) => Some(a),
-                (false, true) => Some(b),
-                (false, false) => None,
-            },
-            SphereIntersection::One(a) if a >= 0. => Some(a),
+impl<T: Real> super::Traceable<T> for Sphere<T> {
+    fn trace(&self, ray: &ray::Ray<T>) -> Option<T> {
+        match self.ray_intersection(ray) {
+            SphereIntersection::Two(a, b) => [a.min(b), a.max(b)]
+                .into_iter()
+                .find(|dist| ray.contains(*dist)),
+            SphereIntersection::One(a) if ray.contains(a) => Some(a),
             _ => None,
         }
     }
+
+    fn hit(&self, ray: &ray::Ray<T>) -> Option<super::Hit<T>> {
+        let distance = self.trace(ray)?;
+        let normal = self.normal(&ray.at(distance));
+        let tangent = nalgebra::Vector3::new(-normal.y, normal.x, T::zero());
+        Some(super::Hit::new(ray, distance, normal, &tangent, |p| {
+            self.uv(p)
+        }))
+    }
 }
@@ -1,22 +1,22 @@
+use crate::Float;
+use crate::Real;
+use crate::aabb;
+use crate::packet;
 use crate::ray;
 
-#[derive(Debug)]
-pub struct Triangle {
-    a: nalgebra::Vector3<f32>,
-    b: nalgebra::Vector3<f32>,
-    c: nalgebra::Vector3<f32>,
+#[derive(Copy, Clone, Debug)]
+pub struct Triangle<T = Float> {
+    a: nalgebra::Vector3<T>,
+    b: nalgebra::Vector3<T>,
+    c: nalgebra::Vector3<T>,
 }
 
-impl Triangle {
-    pub fn new(
-        a: nalgebra::Vector3<f32>,
-        b: nalgebra::Vector3<f32>,
-        c: nalgebra::Vector3<f32>,
-    ) -> Self {
+impl<T: Real> Triangle<T> {
+    pub fn new(a: nalgebra::Vector3<T>, b: nalgebra::Vector3<T>, c: nalgebra::Vector3<T>) -> Self {
         Self { a, b, c }
     }
 
-    pub fn rotate(&self, rotation: &nalgebra::UnitQuaternion<f32>) -> Self {
+    pub fn rotate(&self, rotation: &nalgebra::UnitQuaternion<T>) -> Self {
         Self::new(
             rotation.transform_vector(&self.a),
             rotation.transform_vector(&self.b),
@@ -24,7 +24,7 @@ impl Triangle {
         )
     }
 
-    pub fn translate(&self, translation: &nalgebra::Vector3<f32>) -> Self {
+    pub fn translate(&self, translation: &nalgebra::Vector3<T>) -> Self {
         Self::new(
             self.a + translation,
             self.b + translation,
@@ -33,67 +33,266 @@ impl Triangle {
     }
 
     /// scales away from origin
-    pub fn scale(&self, scale: f32) -> Self {
-        Self::new(scale * self.a, scale * self.b, scale * self.c)
+    pub fn scale(&self, scale: T) -> Self {
+        Self::new(self.a * scale, self.b * scale, self.c * scale)
     }
 
-    pub fn a(&self) -> &nalgebra::Vector3<f32> {
+    pub fn a(&self) -> &nalgebra::Vector3<T> {
         &self.a
     }
 
-    pub fn b(&self) -> &nalgebra::Vector3<f32> {
+    pub fn b(&self) -> &nalgebra::Vector3<T> {
         &self.b
     }
 
-    pub fn c(&self) -> &nalgebra::Vector3<f32> {
+    pub fn c(&self) -> &nalgebra::Vector3<T> {
         &self.c
     }
 
-    /// taken from https://en.wikipedia.org/wiki/M%C3%B6ller%E2%80%93Trumbore_intersection_algorithm
-    pub fn ray_intersection(&self, ray: &ray::Ray, epsilon: f32) -> Option<f32> {
-        let edge1 = self.b() - self.a();
-        let edge2 = self.c() - self.a();
+    /// the same triangle wound the other way, b and c swapped
+    pub fn flipped(&self) -> Self {
+        Self::new(self.a, self.c, self.b)
+    }
+
+    pub fn area(&self) -> T {
+        (self.b - self.a).cross(&(self.c - self.a)).norm() * nalgebra::convert(0.5)
+    }
+
+    pub fn bounds(&self) -> aabb::Aabb<T> {
+        aabb::Aabb::from_points([&self.a, &self.b, &self.c])
+    }
+
+    /// geometric normal, following the right hand rule for a -> b -> c
+    pub fn normal(&self) -> nalgebra::UnitVector3<T> {
+        nalgebra::UnitVector3::new_normalize((self.b - self.a).cross(&(self.c - self.a)))
+    }
+
+    /// closest point of the triangle, on its face, an edge or a corner
+    pub fn closest_point(&self, point: &nalgebra::Vector3<T>) -> nalgebra::Vector3<T> {
+        // Ericson, Real-Time Collision Detection 5.1.5
+        let zero = T::zero();
+        let ab = self.b - self.a;
+        let ac = self.c - self.a;
+
+        let ap = point - self.a;
+        let d1 = ab.dot(&ap);
+        let d2 = ac.dot(&ap);
+        if d1 <= zero && d2 <= zero {
+            return self.a;
+        }
+
+        let bp = point - self.b;
+        let d3 = ab.dot(&bp);
+        let d4 = ac.dot(&bp);
+        if d3 >= zero && d4 <= d3 {
+            return self.b;
+        }
 
-        let ray_cross_edge2 = ray.direction().cross(&edge2);
-        let det = edge1.dot(&ray_cross_edge2);
+        let vc = d1 * d4 - d3 * d2;
+        if vc <= zero && d1 >= zero && d3 <= zero {
+            return self.a + ab * (d1 / (d1 - d3));
+        }
 
-        if crate::is_zero(det, epsilon) {
-            return None;
+        let cp = point - self.c;
+        let d5 = ab.dot(&cp);
+        let d6 = ac.dot(&cp);
+        if d6 >= zero && d5 <= d6 {
+            return self.c;
         }
 
-        let inv_det = 1.0 / det;
-        let s = ray.origin() - self.a();
-        let u = inv_det * s.dot(&ray_cross_edge2);
-        if !(0.0..=1.0).contains(&u) {
-            return None;
+        let vb = d5 * d2 - d1 * d6;
+        if vb <= zero && d2 >= zero && d6 <= zero {
+            return self.a + ac * (d2 / (d2 - d6));
         }
 
-        let s_cross_e1 = s.cross(&edge1);
-        let v = inv_det * ray.direction().dot(&s_cross_e1);
-        if v < 0.0 || u + v > 1.0 {
-            return None;
+        let va = d3 * d6 - d5 * d4;
+        if va <= zero && d4 - d3 >= zero && d5 - d6 >= zero {
+            return self.b + (self.c - self.b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
         }
 
-        let t = inv_det * edge2.dot(&s_cross_e1);
+        let denominator = va + vb + vc;
+        self.a + ab * (vb / denominator) + ac * (vc / denominator)
+    }
+
+    pub fn distance(&self, point: &nalgebra::Vector3<T>) -> T {
+        (self.closest_point(point) - point).norm()
+    }
+
+    /// Barycentric weights of a, b and c for a point in the plane of the triangle
+    pub fn barycentric(&self, point: &nalgebra::Vector3<T>) -> [T; 3] {
+        let edge1 = self.b - self.a;
+        let edge2 = self.c - self.a;
+        let offset = point - self.a;
+
+        let d11 = edge1.dot(&edge1);
+        let d12 = edge1.dot(&edge2);
+        let d22 = edge2.dot(&edge2);
+        let d1 = offset.dot(&edge1);
+        let d2 = offset.dot(&edge2);
+        let denominator = d11 * d22 - d12 * d12;
+
+        let wb = (d22 * d1 - d12 * d2) / denominator;
+        let wc = (d11 * d2 - d12 * d1) / denominator;
+        [T::one() - wb - wc, wb, wc]
+    }
+
+    /// barycentric coordinates (weights of b and c) of the point as uv
+    pub fn uv(&self, point: &nalgebra::Vector3<T>) -> nalgebra::Vector2<T> {
+        let [_, wb, wc] = self.barycentric(point);
+        nalgebra::Vector2::new(wb, wc)
+    }
+
+    /// Watertight ray triangle intersection, rays through shared edges or
+    /// vertices hit at least one of the triangles, with no epsilon.
+    ///
+    /// taken from [Woop, Benthin and Wald 2013](https://jcgt.org/published/0002/01/05/)
+    pub fn ray_intersection(&self, ray: &ray::Ray<T>) -> Option<T> {
+        let o = ray.origin();
+        let [hit] = watertight(
+            &[[o.x], [o.y], [o.z]],
+            &watertight_transform(ray.direction()).map(|row| row.map(|c| [c])),
+            (&[ray.t_min()], &[ray.t_max()]),
+            [&self.a, &self.b, &self.c],
+        );
+        hit
+    }
 
-        if t < 0. { None } else { Some(t) }
+    /// [`Triangle::ray_intersection`] for every ray in the packet
+    pub fn ray_packet_intersection<const N: usize>(
+        &self,
+        packet: &packet::RayPacket<N, T>,
+    ) -> packet::Lanes<Option<T>, N> {
+        watertight(
+            packet.origin(),
+            packet.watertight_transform(),
+            (packet.t_min(), packet.t_max()),
+            [&self.a, &self.b, &self.c],
+        )
     }
 }
 
-impl super::Traceable for Triangle {
-    fn trace(&self, ray: &ray::Ray, epsilon: f32) -> Option<f32> {
-        match self.ray_intersection(ray, epsilon) {
-            Some(dist) if dist >= 0. => Some(dist),
-            _ => None,
+/// Rows taking a point relative to the ray origin to where the ray runs
+/// along +z: permuted so the dominant axis of the direction is z, with x and
+/// y swapped to keep the winding, then sheared.
+///
+/// Every other coefficient is 0 or 1, so applying the rows as a matrix
+/// gives exactly what indexing the permuted axes would.
+pub(crate) fn watertight_transform<T: Real>(direction: &nalgebra::Vector3<T>) -> [[T; 3]; 3] {
+    let kz = (0..3)
+        .max_by(|a, b| direction[*a].abs().total_cmp(&direction[*b].abs()))
+        .unwrap_or(2);
+    let (kx, ky) = match direction[kz] < T::zero() {
+        true => ((kz + 2) % 3, (kz + 1) % 3),
+        false => ((kz + 1) % 3, (kz + 2) % 3),
+    };
+
+    let mut rows = [[T::zero(); 3]; 3];
+    rows[0][kx] = T::one();
+    rows[0][kz] = -direction[kx] / direction[kz];
+    rows[1][ky] = T::one();
+    rows[1][kz] = -direction[ky] / direction[kz];
+    rows[2][kz] = T::one() / direction[kz];
+    rows
+}
+
+/// Shared by the single ray (as one lane) and packet paths so both give
+/// identical results.
+///
+/// The lane loops are straight line arithmetic, hits are masked rather than
+/// returned early, so they vectorize. Only lanes exactly on an edge, which
+/// are rare, take a scalar detour.
+#[inline(always)]
+fn watertight<const N: usize, T: Real>(
+    origin: &[packet::Lanes<T, N>; 3],
+    transform: &[[packet::Lanes<T, N>; 3]; 3],
+    (t_min, t_max): (&packet::Lanes<T, N>, &packet::Lanes<T, N>),
+    vertices: [&nalgebra::Vector3<T>; 3],
+) -> packet::Lanes<Option<T>, N> {
+    // [vertex][axis][lane], relative to the origin and sheared so the ray runs along +z
+    let mut sheared = [[[T::zero(); N]; 3]; 3];
+    for (vertex, position) in sheared.iter_mut().zip(vertices) {
+        let mut relative = [[T::zero(); N]; 3];
+        for (axis, relative) in relative.iter_mut().enumerate() {
+            for lane in 0..N {
+                relative[lane] = position[axis] - origin[axis][lane];
+            }
+        }
+        for (axis, row) in vertex.iter_mut().zip(transform) {
+            for lane in 0..N {
+                axis[lane] = row[0][lane] * relative[0][lane]
+                    + row[1][lane] * relative[1][lane]
+                    + row[2][lane] * relative[2][lane];
+            }
+        }
+    }
+    let [[ax, ay, az], [bx, by, bz], [cx, cy, cz]] = sheared;
+
+    // scaled barycentrics
+    let mut u = [T::zero(); N];
+    let mut v = [T::zero(); N];
+    let mut w = [T::zero(); N];
+    for lane in 0..N {
+        u[lane] = cx[lane] * by[lane] - cy[lane] * bx[lane];
+        v[lane] = ax[lane] * cy[lane] - ay[lane] * cx[lane];
+        w[lane] = bx[lane] * ay[lane] - by[lane] * ax[lane];
+    }
+
+    // exactly on an edge, redo in double precision to get the sign right (nothing new for f64)
+    let zero = T::zero();
+    for lane in 0..N {
+        if u[lane] == zero || v[lane] == zero || w[lane] == zero {
+            let [ax, bx, cx, ay, by, cy] =
+                [ax[lane], bx[lane], cx[lane], ay[lane], by[lane], cy[lane]]
+                    .map(nalgebra::convert_unchecked::<T, f64>);
+            u[lane] = nalgebra::convert(cx * by - cy * bx);
+            v[lane] = nalgebra::convert(ax * cy - ay * cx);
+            w[lane] = nalgebra::convert(bx * ay - by * ax);
         }
     }
+
+    let mut t = [zero; N];
+    let mut hit = [false; N];
+    for lane in 0..N {
+        let (u, v, w) = (u[lane], v[lane], w[lane]);
+        let outside =
+            ((u < zero) | (v < zero) | (w < zero)) & ((u > zero) | (v > zero) | (w > zero));
+        let det = u + v + w;
+        t[lane] = (u * az[lane] + v * bz[lane] + w * cz[lane]) / det;
+        hit[lane] = !outside & (det != zero) & (t_min[lane] <= t[lane]) & (t[lane] <= t_max[lane]);
+    }
+
+    std::array::from_fn(|lane| hit[lane].then_some(t[lane]))
+}
+
+impl<T: Real> super::Traceable<T> for Triangle<T> {
+    fn trace(&self, ray: &ray::Ray<T>) -> Option<T> {
+        self.ray_intersection(ray)
+    }
+
+    fn hit(&self, ray: &ray::Ray<T>) -> Option<super::Hit<T>> {
+        let distance = self.ray_intersection(ray)?;
+        Some(super::Hit::new(
+            ray,
+            distance,
+            self.normal(),
+            &(self.b - self.a),
+            |p| self.uv(p),
+        ))
+    }
+
+    fn trace_packet<const N: usize>(
+        &self,
+        packet: &packet::RayPacket<N, T>,
+    ) -> packet::Lanes<Option<T>, N> {
+        self.ray_packet_intersection(packet)
+    }
 }
 
-pub fn quad_to_triangles(
-    a: nalgebra::Vector3<f32>,
-    b: nalgebra::Vector3<f32>,
-    c: nalgebra::Vector3<f32>,
-    d: nalgebra::Vector3<f32>,
-) -> [Triangle; 2] {
+pub fn quad_to_triangles<T: Real>(
+    a: nalgebra::Vector3<T>,
+    b: nalgebra::Vector3<T>,
+    c: nalgebra::Vector3<T>,
+    d: nalgebra::Vector3<T>,
+) -> [Triangle<T>; 2] {
     [Triangle::new(a, b, c), Triangle::new(c, d, a)]
 }
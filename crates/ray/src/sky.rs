@@ -0,0 +1,136 @@
+//! Clear sky radiance for the time of day.
+//!
+//! [Preetham, Shirley and Smits 1999](https://doi.org/10.1145/311535.311545)
+//! analytic daylight model: luminance and chromaticity of every direction
+//! of the sky from the sun position and the turbidity of the air. Like
+//! [`crate::solar`] the sky lives in the north-east-down frame.
+
+use crate::Float;
+use crate::solar;
+use crate::spectral;
+
+/// Perez et al. 1993 luminance distribution coefficients A to E
+#[derive(Copy, Clone, Debug)]
+struct Perez([Float; 5]);
+
+impl Perez {
+    /// relative value for a direction `theta` from the zenith, `gamma` from the sun
+    fn value(&self, cos_theta: Float, gamma: Float) -> Float {
+        let [a, b, c, d, e] = self.0;
+        let cos_gamma = gamma.cos();
+        (1. + a * (b / cos_theta).exp()) * (1. + c * (d * gamma).exp() + e * cos_gamma * cos_gamma)
+    }
+}
+
+pub struct Sky {
+    // towards the sun
+    sun_direction: nalgebra::UnitVector3<Float>,
+    turbidity: Float,
+    // zenith luminance (W/(sr m²), see solar::LUMINOUS_EFFICACY) and chromaticity,
+    // already divided by the perez value at the zenith
+    zenith: [Float; 3],
+    perez: [Perez; 3],
+}
+
+impl Sky {
+    /// # Arguments
+    /// - `turbidity`: haziness, 2 for a very clear sky to 10 for haze
+    pub fn new(sun: &solar::SunPosition, turbidity: Float) -> Self {
+        debug_assert!(
+            (1.7..=10.).contains(&turbidity),
+            "turbidity is within [1.7, 10]"
+        );
+        let t = turbidity;
+        let sun_direction = sun.direction_ned();
+        // the model only holds for the sun above the horizon
+        let theta_sun = (-sun_direction.z).clamp(0., 1.).acos();
+
+        let chi = (4. / 9. - t / 120.) * (crate::consts::PI - 2. * theta_sun);
+        // kcd/m²
+        let zenith_luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let chromaticity = |m: [[Float; 4]; 3]| {
+            let thetas = [theta_sun.powi(3), theta_sun.powi(2), theta_sun, 1.];
+            let row = |r: [Float; 4]| r.iter().zip(thetas).map(|(a, b)| a * b).sum::<Float>();
+            t * t * row(m[0]) + t * row(m[1]) + row(m[2])
+        };
+        let zenith_x = chromaticity([
+            [0.00166, -0.00375, 0.00209, 0.],
+            [-0.02903, 0.06377, -0.03202, 0.00394],
+            [0.11693, -0.21196, 0.06052, 0.25886],
+        ]);
+        let zenith_y = chromaticity([
+            [0.00275, -0.00610, 0.00317, 0.],
+            [-0.04214, 0.08970, -0.04153, 0.00516],
+            [0.15346, -0.26756, 0.06670, 0.26688],
+        ]);
+
+        let perez = [
+            Perez([
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ]),
+            Perez([
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ]),
+            Perez([
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ]),
+        ];
+
+        let zenith = [
+            zenith_luminance.max(0.) * 1000. / solar::LUMINOUS_EFFICACY,
+            zenith_x,
+            zenith_y,
+        ];
+        Self {
+            sun_direction,
+            turbidity,
+            zenith: std::array::from_fn(|i| zenith[i] / perez[i].value(1., theta_sun)),
+            perez,
+        }
+    }
+
+    pub fn turbidity(&self) -> Float {
+        self.turbidity
+    }
+
+    /// towards the sun, north-east-down
+    pub fn sun_direction(&self) -> &nalgebra::UnitVector3<Float> {
+        &self.sun_direction
+    }
+
+    /// Linear RGB radiance in W/(sr m²) (luminance over
+    /// [`solar::LUMINOUS_EFFICACY`]) arriving from a direction, black when
+    /// the sun is down. Directions below the horizon see the horizon.
+    ///
+    /// # Arguments
+    /// - `direction`: towards the sky, north-east-down
+    pub fn radiance(&self, direction: &nalgebra::UnitVector3<Float>) -> nalgebra::Vector3<Float> {
+        if self.sun_direction.z >= 0. {
+            return nalgebra::Vector3::zeros();
+        }
+        // keeps 1 / cos theta finite at the horizon
+        let cos_theta = (-direction.z).max(0.01);
+        let gamma = direction.dot(&self.sun_direction).clamp(-1., 1.).acos();
+
+        let [luminance, x, y] =
+            std::array::from_fn(|i| self.zenith[i] * self.perez[i].value(cos_theta, gamma));
+        if y <= 0. {
+            return nalgebra::Vector3::zeros();
+        }
+        let xyz =
+            nalgebra::Vector3::new(x / y * luminance, luminance, (1. - x - y) / y * luminance);
+        spectral::xyz_to_linear_srgb(&xyz).map(|c| c.max(0.))
+    }
+}
@@ -0,0 +1,210 @@
+//! Position of the sun for a time and place on earth.
+//!
+//! Follows the [NOAA solar calculator](https://gml.noaa.gov/grad/solcalc/calcdetails.html),
+//! accurate to about a hundredth of a degree between 1800 and 2100. The
+//! sun is given in the local north-east-down (NED) frame of the observer,
+//! with atmospheric refraction applied to the elevation.
+
+use crate::Float;
+use crate::angle::Angle;
+use crate::distance::Distance;
+use crate::light;
+
+/// extraterrestrial illuminance at 1 au, lux
+const SOLAR_ILLUMINANCE: Float = 128_000.;
+/// lumen per watt at 555 nm, the conversion used for all sun and sky radiometry
+pub const LUMINOUS_EFFICACY: Float = 683.;
+
+/// Instant in UTC, kept as a julian day
+#[derive(Copy, Clone, Debug)]
+pub struct UtcTime {
+    julian_day: f64,
+}
+
+impl UtcTime {
+    /// calendar date and time of day, Gregorian calendar
+    pub fn new(year: i32, month: u32, day: u32, hour: u32, minute: u32, second: f64) -> Self {
+        debug_assert!((1..=12).contains(&month), "month is within [1, 12]");
+        debug_assert!((1..=31).contains(&day), "day is within [1, 31]");
+        // Meeus, Astronomical Algorithms, chapter 7
+        let (year, month) = match month <= 2 {
+            true => (year - 1, month + 12),
+            false => (year, month),
+        };
+        let century = (year as f64 / 100.).floor();
+        let gregorian = 2. - century + (century / 4.).floor();
+        let day_fraction = (hour as f64 + (minute as f64 + second / 60.) / 60.) / 24.;
+        Self {
+            julian_day: (365.25 * (year as f64 + 4716.)).floor()
+                + (30.6001 * (month as f64 + 1.)).floor()
+                + day as f64
+                + day_fraction
+                + gregorian
+                - 1524.5,
+        }
+    }
+
+    /// seconds since 1970-01-01 00:00 UTC
+    pub fn from_unix_seconds(seconds: f64) -> Self {
+        Self {
+            julian_day: seconds / 86_400. + 2_440_587.5,
+        }
+    }
+
+    pub fn julian_day(&self) -> f64 {
+        self.julian_day
+    }
+
+    /// minutes since midnight
+    fn minute_of_day(&self) -> f64 {
+        (self.julian_day + 0.5).fract() * 1440.
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct SunPosition {
+    /// clockwise from north
+    pub azimuth: Angle,
+    /// above the horizon, including refraction
+    pub elevation: Angle,
+    /// earth to sun
+    pub distance: Distance,
+}
+
+impl SunPosition {
+    /// Sun seen from a geodetic location at a time.
+    ///
+    /// # Arguments
+    /// - `latitude`: positive north
+    /// - `longitude`: positive east
+    pub fn new(time: UtcTime, latitude: Angle, longitude: Angle) -> Self {
+        let century = (time.julian_day - 2_451_545.) / 36_525.;
+
+        // geometric mean longitude and anomaly of the sun, degrees
+        let mean_longitude = (280.46646 + century * (36000.76983 + century * 0.0003032)) % 360.;
+        let mean_anomaly = 357.52911 + century * (35999.05029 - 0.0001537 * century);
+        let eccentricity = 0.016708634 - century * (0.000042037 + 0.0000001267 * century);
+
+        let anomaly = mean_anomaly.to_radians();
+        let center = anomaly.sin() * (1.914602 - century * (0.004817 + 0.000014 * century))
+            + (2. * anomaly).sin() * (0.019993 - 0.000101 * century)
+            + (3. * anomaly).sin() * 0.000289;
+        let true_longitude = mean_longitude + center;
+        let true_anomaly = (mean_anomaly + center).to_radians();
+        let distance_au = 1.000001018 * (1. - eccentricity * eccentricity)
+            / (1. + eccentricity * true_anomaly.cos());
+
+        let omega = (125.04 - 1934.136 * century).to_radians();
+        let apparent_longitude = (true_longitude - 0.00569 - 0.00478 * omega.sin()).to_radians();
+        let mean_obliquity = 23.
+            + (26.
+                + (21.448 - century * (46.815 + century * (0.00059 - century * 0.001813))) / 60.)
+                / 60.;
+        let obliquity = (mean_obliquity + 0.00256 * omega.cos()).to_radians();
+        let declination = (obliquity.sin() * apparent_longitude.sin()).asin();
+
+        // equation of time, minutes
+        let y = (obliquity / 2.).tan().powi(2);
+        let l = mean_longitude.to_radians();
+        let equation_of_time = 4.
+            * (y * (2. * l).sin() - 2. * eccentricity * anomaly.sin()
+                + 4. * eccentricity * y * anomaly.sin() * (2. * l).cos()
+                - 0.5 * y * y * (4. * l).sin()
+                - 1.25 * eccentricity * eccentricity * (2. * anomaly).sin())
+            .to_degrees();
+
+        let longitude_degrees: f64 = nalgebra::convert(longitude.degs());
+        let true_solar_time =
+            (time.minute_of_day() + equation_of_time + 4. * longitude_degrees).rem_euclid(1440.);
+        let hour_angle = (true_solar_time / 4. - 180.).to_radians();
+
+        let latitude: f64 = nalgebra::convert(latitude.rads());
+        let cos_zenith = (latitude.sin() * declination.sin()
+            + latitude.cos() * declination.cos() * hour_angle.cos())
+        .clamp(-1., 1.);
+        let zenith = cos_zenith.acos();
+
+        let azimuth = match zenith.sin() * latitude.cos() {
+            0. => 180.,
+            denominator => {
+                let cos_azimuth = ((latitude.sin() * cos_zenith - declination.sin()) / denominator)
+                    .clamp(-1., 1.);
+                let angle = cos_azimuth.acos().to_degrees();
+                match hour_angle > 0. {
+                    true => (angle + 180.) % 360.,
+                    false => (540. - angle) % 360.,
+                }
+            }
+        };
+
+        let elevation = 90. - zenith.to_degrees();
+        Self {
+            azimuth: Angle::from_degs(nalgebra::convert(azimuth)),
+            elevation: Angle::from_degs(nalgebra::convert(elevation + refraction(elevation))),
+            distance: Distance::from_m(nalgebra::convert(distance_au * 149_597_870_700.)),
+        }
+    }
+
+    /// unit vector towards the sun in the north-east-down frame of the observer
+    pub fn direction_ned(&self) -> nalgebra::UnitVector3<Float> {
+        let (sin_el, cos_el) = self.elevation.rads().sin_cos();
+        let (sin_az, cos_az) = self.azimuth.rads().sin_cos();
+        nalgebra::UnitVector3::new_normalize(nalgebra::Vector3::new(
+            cos_el * cos_az,
+            cos_el * sin_az,
+            -sin_el,
+        ))
+    }
+
+    pub fn is_up(&self) -> bool {
+        self.elevation.rads() > 0.
+    }
+
+    /// Kasten and Young 1989 relative optical air mass, 1 at the zenith
+    pub fn air_mass(&self) -> Float {
+        let elevation = self.elevation.degs().max(0.);
+        1. / ((elevation.to_radians()).sin() + 0.50572 * (elevation + 6.07995).powf(-1.6364))
+    }
+
+    /// Sunlight on the ground as a directional light in the NED frame,
+    /// dimmed and reddened by Rayleigh and aerosol extinction along the
+    /// air mass, `None` below the horizon.
+    ///
+    /// The irradiance is illuminance over [`LUMINOUS_EFFICACY`], matching
+    /// the units of [`crate::sky::Sky`], with channels at 610, 540 and 465 nm.
+    ///
+    /// # Arguments
+    /// - `turbidity`: haziness, 2 for a very clear sky to 10 for haze
+    pub fn light(&self, turbidity: Float) -> Option<light::DirectionalLight> {
+        if !self.is_up() {
+            return None;
+        }
+        let distance_au = self.distance.m() / 149_597_870_700.;
+        let outside = SOLAR_ILLUMINANCE / LUMINOUS_EFFICACY / (distance_au * distance_au);
+        let air_mass = self.air_mass();
+
+        // Preetham et al. 1999, Ångström aerosol exponent 1.3
+        let aerosol = 0.04608 * turbidity - 0.04586;
+        let transmittance = nalgebra::Vector3::new(0.61, 0.54, 0.465).map(|um: Float| {
+            let rayleigh = 0.008735 * um.powf(-4.08);
+            let haze = aerosol * um.powf(-1.3);
+            (-(rayleigh + haze) * air_mass).exp()
+        });
+        Some(light::DirectionalLight::new(
+            -self.direction_ned(),
+            transmittance * outside,
+        ))
+    }
+}
+
+/// atmospheric refraction raising an elevation, degrees
+fn refraction(elevation: f64) -> f64 {
+    let tan = elevation.to_radians().tan();
+    let arcseconds = match elevation {
+        e if e > 85. => 0.,
+        e if e > 5. => 58.1 / tan - 0.07 / tan.powi(3) + 0.000086 / tan.powi(5),
+        e if e > -0.575 => 1735. + e * (-518.2 + e * (103.4 + e * (-12.79 + e * 0.711))),
+        _ => -20.772 / tan,
+    };
+    arcseconds / 3600.
+}
@@ -0,0 +1,258 @@
+//! Spectral quantities, dispersion and conversion to color.
+//!
+//! Wavelengths are [`Distance`]s, tabulated data is in nanometers.
+//! Spectral renders trace a handful of wavelengths per pixel (see
+//! [`stratified_wavelengths`]) and reduce the radiance at those wavelengths
+//! to sensor channels with a [`SpectralResponse`].
+
+use crate::Float;
+use crate::distance::Distance;
+
+/// visible range, in nm
+pub const VISIBLE_NM: (Float, Float) = (380., 780.);
+/// visible plus near infrared, the range of a typical silicon sensor, in nm
+pub const VISIBLE_NIR_NM: (Float, Float) = (380., 1100.);
+
+// integral of the CIE y matching function, so unit radiance gives Y = 1
+const CIE_Y_INTEGRAL: Float = 106.856895;
+
+pub enum Spectrum {
+    Constant(Float),
+    Sampled(SampledSpectrum),
+    /// black body at a temperature in kelvin, normalized to 1 at its peak
+    Blackbody(Float),
+}
+
+impl Spectrum {
+    pub fn value(&self, wavelength: Distance) -> Float {
+        match self {
+            Spectrum::Constant(value) => *value,
+            Spectrum::Sampled(s) => s.value(wavelength),
+            Spectrum::Blackbody(temperature) => {
+                // wien's displacement law gives the peak
+                let peak = Distance::from_m(2.897_772e-3 / temperature);
+                planck(wavelength, *temperature) / planck(peak, *temperature)
+            }
+        }
+    }
+
+    /// CIE standard illuminant D65 (average daylight), normalized to 100 at 560 nm
+    pub fn d65() -> Self {
+        Spectrum::Sampled(SampledSpectrum::regular(380., 10., D65.to_vec()))
+    }
+
+    /// CIE standard illuminant A (incandescent)
+    pub fn illuminant_a() -> Self {
+        Spectrum::Blackbody(2856.)
+    }
+}
+
+/// Piecewise linear spectrum, constant beyond the first and last sample
+pub struct SampledSpectrum {
+    wavelengths_nm: Vec<Float>,
+    values: Vec<Float>,
+}
+
+impl SampledSpectrum {
+    /// `wavelengths_nm` must be increasing
+    pub fn new(wavelengths_nm: Vec<Float>, values: Vec<Float>) -> Self {
+        debug_assert!(!values.is_empty(), "spectrum needs at least one sample");
+        debug_assert_eq!(
+            wavelengths_nm.len(),
+            values.len(),
+            "one value per wavelength"
+        );
+        debug_assert!(
+            wavelengths_nm.windows(2).all(|w| w[0] < w[1]),
+            "wavelengths must be increasing"
+        );
+        Self {
+            wavelengths_nm,
+            values,
+        }
+    }
+
+    /// samples every `step_nm` starting at `start_nm`
+    pub fn regular(start_nm: Float, step_nm: Float, values: Vec<Float>) -> Self {
+        let wavelengths_nm = (0..values.len())
+            .map(|i| start_nm + i as Float * step_nm)
+            .collect();
+        Self::new(wavelengths_nm, values)
+    }
+
+    pub fn value(&self, wavelength: Distance) -> Float {
+        let nm = wavelength.nm();
+        let upper = self.wavelengths_nm.partition_point(|w| *w < nm);
+        if upper == 0 {
+            return self.values[0];
+        }
+        if upper == self.values.len() {
+            return self.values[upper - 1];
+        }
+
+        let (w0, w1) = (self.wavelengths_nm[upper - 1], self.wavelengths_nm[upper]);
+        let t = (nm - w0) / (w1 - w0);
+        self.values[upper - 1] * (1. - t) + self.values[upper] * t
+    }
+}
+
+/// Planck's law, spectral radiance of a black body in W/(sr m² m)
+pub fn planck(wavelength: Distance, temperature: Float) -> Float {
+    // first and second radiation constants, 2hc² and hc/k
+    const C1: Float = 1.191_043e-16;
+    const C2: Float = 1.438_777e-2;
+    let l = wavelength.m();
+    // split l⁵ so it does not underflow in f32
+    C1 / (l * l * l) / (l * l) / (C2 / (l * temperature)).exp_m1()
+}
+
+/// Wavelength dependent refractive index
+#[derive(Copy, Clone, Debug)]
+pub enum Dispersion {
+    Constant(Float),
+    /// n = a + b / λ² + c / λ⁴, λ in µm
+    Cauchy {
+        a: Float,
+        b: Float,
+        c: Float,
+    },
+    /// n² = 1 + Σ bᵢ λ² / (λ² - cᵢ), λ in µm
+    Sellmeier {
+        b: [Float; 3],
+        c: [Float; 3],
+    },
+}
+
+// published coefficients, kept at full precision
+#[allow(clippy::excessive_precision)]
+impl Dispersion {
+    /// Schott N-BK7 borosilicate crown glass
+    pub const BK7: Self = Self::Sellmeier {
+        b: [1.03961212, 0.231792344, 1.01046945],
+        c: [0.00600069867, 0.0200179144, 103.560653],
+    };
+
+    /// fused silica (Malitson 1965)
+    pub const FUSED_SILICA: Self = Self::Sellmeier {
+        b: [0.6961663, 0.4079426, 0.8974794],
+        c: [0.00467914826, 0.0135120631, 97.9340025],
+    };
+
+    pub fn refractive_index(&self, wavelength: Distance) -> Float {
+        let um = wavelength.um();
+        let um2 = um * um;
+        match self {
+            Dispersion::Constant(n) => *n,
+            Dispersion::Cauchy { a, b, c } => a + b / um2 + c / (um2 * um2),
+            Dispersion::Sellmeier { b, c } => (1.
+                + b.iter()
+                    .zip(c)
+                    .map(|(b, c)| b * um2 / (um2 - c))
+                    .sum::<Float>())
+            .sqrt(),
+        }
+    }
+
+    /// Abbe number, how dispersive the material is across the visible, lower is more
+    pub fn abbe_number(&self) -> Float {
+        let n_d = self.refractive_index(Distance::from_nm(587.56));
+        let n_f = self.refractive_index(Distance::from_nm(486.13));
+        let n_c = self.refractive_index(Distance::from_nm(656.27));
+        (n_d - 1.) / (n_f - n_c)
+    }
+}
+
+/// CIE 1931 2° color matching functions (x̄, ȳ, z̄)
+///
+/// taken from the multi lobe fit of [Wyman, Sloan and Shirley 2013](https://jcgt.org/published/0002/02/01/)
+pub fn cie_xyz(wavelength: Distance) -> nalgebra::Vector3<Float> {
+    let nm = wavelength.nm();
+    let g = |mu: Float, sigma1: Float, sigma2: Float| {
+        let sigma = if nm < mu { sigma1 } else { sigma2 };
+        (-0.5 * ((nm - mu) / sigma).powi(2)).exp()
+    };
+
+    nalgebra::Vector3::new(
+        1.056 * g(599.8, 37.9, 31.0) + 0.362 * g(442.0, 16.0, 26.7) - 0.065 * g(501.1, 20.4, 26.2),
+        0.821 * g(568.8, 46.9, 40.5) + 0.286 * g(530.9, 16.3, 31.1),
+        1.217 * g(437.0, 11.8, 36.0) + 0.681 * g(459.0, 26.0, 13.8),
+    )
+}
+
+/// CIE XYZ to linear sRGB (D65 white point)
+pub fn xyz_to_linear_srgb(xyz: &nalgebra::Vector3<Float>) -> nalgebra::Vector3<Float> {
+    #[rustfmt::skip]
+    let m = nalgebra::Matrix3::new(
+        3.2404542, -1.5371385, -0.4985314,
+        -0.969266, 1.8760108, 0.041556,
+        0.0556434, -0.2040259, 1.0572252,
+    );
+    m * xyz
+}
+
+/// `count` wavelengths spread evenly over `range_nm`, jittered by `offset` in [0, 1)
+pub fn stratified_wavelengths(
+    count: usize,
+    range_nm: (Float, Float),
+    offset: Float,
+) -> Vec<Distance> {
+    debug_assert!(count > 0, "need at least one wavelength");
+    let step = (range_nm.1 - range_nm.0) / count as Float;
+    (0..count)
+        .map(|i| Distance::from_nm(range_nm.0 + (i as Float + offset) * step))
+        .collect()
+}
+
+/// How a sensor turns spectral radiance into channel values
+pub enum SpectralResponse {
+    /// CIE XYZ, normalized so a flat spectrum of 1 has Y = 1
+    Cie,
+    /// one sensitivity curve per channel, e.g. a single band or the RGB of a real camera
+    Channels(Vec<Spectrum>),
+}
+
+impl SpectralResponse {
+    pub fn channels(&self) -> usize {
+        match self {
+            SpectralResponse::Cie => 3,
+            SpectralResponse::Channels(curves) => curves.len(),
+        }
+    }
+
+    /// sensitivity of every channel at the wavelength
+    pub fn response(&self, wavelength: Distance) -> Vec<Float> {
+        match self {
+            SpectralResponse::Cie => (cie_xyz(wavelength) / CIE_Y_INTEGRAL).as_slice().to_vec(),
+            SpectralResponse::Channels(curves) => {
+                curves.iter().map(|c| c.value(wavelength)).collect()
+            }
+        }
+    }
+
+    /// Estimates the channel values from radiance at wavelengths drawn
+    /// uniformly over `range_nm`, e.g. with [`stratified_wavelengths`].
+    pub fn integrate(&self, samples: &[(Distance, Float)], range_nm: (Float, Float)) -> Vec<Float> {
+        let mut channels = vec![0.; self.channels()];
+        if samples.is_empty() {
+            return channels;
+        }
+
+        let weight = (range_nm.1 - range_nm.0) / samples.len() as Float;
+        for (wavelength, radiance) in samples {
+            for (channel, response) in channels.iter_mut().zip(self.response(*wavelength)) {
+                *channel += radiance * response * weight;
+            }
+        }
+        channels
+    }
+}
+
+// CIE D65, 380 nm to 830 nm in 10 nm steps
+#[rustfmt::skip]
+const D65: [Float; 46] = [
+    49.9755, 54.6482, 82.7549, 91.486, 93.4318, 86.6823, 104.865, 117.008, 117.812, 114.861,
+    115.923, 108.811, 109.354, 107.802, 104.79, 107.689, 104.405, 104.046, 100.0, 96.3342,
+    95.788, 88.6856, 90.0062, 89.5991, 87.6987, 83.2886, 83.6992, 80.0268, 80.2146, 82.2778,
+    78.2842, 69.7213, 71.6091, 74.349, 61.604, 69.8856, 75.087, 63.5927, 46.4182, 66.8054,
+    63.3828, 64.304, 59.4519, 51.959, 57.4406, 60.3125,
+];
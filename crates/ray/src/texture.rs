@@ -0,0 +1,365 @@
+//! Textures looked up by surface uv, used for albedo, roughness and normal maps.
+//!
+//! Every texture is sampled with the width of the pixel footprint in uv
+//! units (see [`crate::shapes::Hit::uv_footprint`]), which selects the mip
+//! level of image textures and filters procedural ones. A footprint of 0 is
+//! a point sample.
+
+use crate::Float;
+
+pub enum Texture {
+    Constant(nalgebra::Vector3<Float>),
+    Image(ImageTexture),
+    Checkerboard(Checkerboard),
+    Grid(Grid),
+    Noise(Noise),
+}
+
+impl Texture {
+    pub fn sample(
+        &self,
+        uv: &nalgebra::Vector2<Float>,
+        footprint: Float,
+    ) -> nalgebra::Vector3<Float> {
+        match self {
+            Texture::Constant(value) => *value,
+            Texture::Image(t) => t.sample(uv, footprint),
+            Texture::Checkerboard(t) => t.sample(uv, footprint),
+            Texture::Grid(t) => t.sample(uv, footprint),
+            Texture::Noise(t) => t.sample(uv, footprint),
+        }
+    }
+}
+
+/// what happens to uv outside of [0, 1]
+#[derive(Copy, Clone, Debug)]
+pub enum Wrap {
+    Repeat,
+    Clamp,
+}
+
+struct MipLevel {
+    width: usize,
+    height: usize,
+    texels: Vec<nalgebra::Vector3<Float>>,
+}
+
+impl MipLevel {
+    fn texel(&self, x: isize, y: isize, wrap: Wrap) -> &nalgebra::Vector3<Float> {
+        let (x, y) = match wrap {
+            Wrap::Repeat => (
+                x.rem_euclid(self.width as isize) as usize,
+                y.rem_euclid(self.height as isize) as usize,
+            ),
+            Wrap::Clamp => (
+                x.clamp(0, self.width as isize - 1) as usize,
+                y.clamp(0, self.height as isize - 1) as usize,
+            ),
+        };
+        &self.texels[y * self.width + x]
+    }
+
+    /// half the resolution, box filtered
+    fn downsample(&self) -> Self {
+        let width = (self.width / 2).max(1);
+        let height = (self.height / 2).max(1);
+        let texels = (0..width * height)
+            .map(|i| {
+                let (x, y) = ((i % width) as isize * 2, (i / width) as isize * 2);
+                (self.texel(x, y, Wrap::Clamp)
+                    + self.texel(x + 1, y, Wrap::Clamp)
+                    + self.texel(x, y + 1, Wrap::Clamp)
+                    + self.texel(x + 1, y + 1, Wrap::Clamp))
+                    * 0.25
+            })
+            .collect();
+
+        Self {
+            width,
+            height,
+            texels,
+        }
+    }
+}
+
+/// Mip mapped image, u increases right and v increases down the image
+pub struct ImageTexture {
+    levels: Vec<MipLevel>,
+    wrap: Wrap,
+}
+
+impl ImageTexture {
+    /// row major texels, top row first
+    pub fn new(
+        width: usize,
+        height: usize,
+        texels: Vec<nalgebra::Vector3<Float>>,
+        wrap: Wrap,
+    ) -> Self {
+        debug_assert!(width > 0 && height > 0, "texture can not be empty");
+        debug_assert_eq!(texels.len(), width * height, "texels do not match size");
+
+        let mut levels = vec![MipLevel {
+            width,
+            height,
+            texels,
+        }];
+        while let Some(last) = levels.last().filter(|l| l.width > 1 || l.height > 1) {
+            let next = last.downsample();
+            levels.push(next);
+        }
+
+        Self { levels, wrap }
+    }
+
+    /// 8 bit sRGB color, e.g. an albedo map, decoded to linear
+    pub fn from_srgb8(width: usize, height: usize, rgb: &[u8], wrap: Wrap) -> Self {
+        let texels = rgb
+            .chunks_exact(3)
+            .map(|c| crate::color::srgb_to_linear_rgb([c[0], c[1], c[2]]))
+            .collect();
+        Self::new(width, height, texels, wrap)
+    }
+
+    /// 8 bit data, e.g. normal or roughness maps, mapped to [0, 1] without decoding
+    pub fn from_data8(width: usize, height: usize, rgb: &[u8], wrap: Wrap) -> Self {
+        let texels = rgb
+            .chunks_exact(3)
+            .map(|c| nalgebra::Vector3::new(c[0], c[1], c[2]).cast::<Float>() / 255.)
+            .collect();
+        Self::new(width, height, texels, wrap)
+    }
+
+    pub fn width(&self) -> usize {
+        self.levels[0].width
+    }
+
+    pub fn height(&self) -> usize {
+        self.levels[0].height
+    }
+
+    pub fn mip_levels(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// bilinear lookup in a single mip level, 0 being full resolution
+    pub fn bilinear(
+        &self,
+        level: usize,
+        uv: &nalgebra::Vector2<Float>,
+    ) -> nalgebra::Vector3<Float> {
+        let level = &self.levels[level.min(self.levels.len() - 1)];
+        let x = uv.x * level.width as Float - 0.5;
+        let y = uv.y * level.height as Float - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as isize, y0 as isize);
+
+        level.texel(x0, y0, self.wrap) * ((1. - fx) * (1. - fy))
+            + level.texel(x0 + 1, y0, self.wrap) * (fx * (1. - fy))
+            + level.texel(x0, y0 + 1, self.wrap) * ((1. - fx) * fy)
+            + level.texel(x0 + 1, y0 + 1, self.wrap) * (fx * fy)
+    }
+
+    /// trilinear lookup, blending the two mip levels closest to the footprint
+    pub fn sample(
+        &self,
+        uv: &nalgebra::Vector2<Float>,
+        footprint: Float,
+    ) -> nalgebra::Vector3<Float> {
+        let texels = footprint * self.width().max(self.height()) as Float;
+        if texels <= 1. {
+            return self.bilinear(0, uv);
+        }
+
+        let lod = texels.log2().min((self.levels.len() - 1) as Float);
+        let level = lod.floor();
+        let t = lod - level;
+        let level = level as usize;
+        self.bilinear(level, uv) * (1. - t) + self.bilinear(level + 1, uv) * t
+    }
+}
+
+/// Alternating squares, `frequency` squares per unit of uv
+pub struct Checkerboard {
+    even: nalgebra::Vector3<Float>,
+    odd: nalgebra::Vector3<Float>,
+    frequency: Float,
+}
+
+impl Checkerboard {
+    pub fn new(
+        even: nalgebra::Vector3<Float>,
+        odd: nalgebra::Vector3<Float>,
+        frequency: Float,
+    ) -> Self {
+        Self {
+            even,
+            odd,
+            frequency,
+        }
+    }
+
+    /// box filtered over the footprint, so distant squares blend rather than alias
+    pub fn sample(
+        &self,
+        uv: &nalgebra::Vector2<Float>,
+        footprint: Float,
+    ) -> nalgebra::Vector3<Float> {
+        let (s, t) = (uv.x * self.frequency, uv.y * self.frequency);
+        let half_width = 0.5 * footprint * self.frequency;
+
+        if half_width == 0. {
+            return match (s.floor() + t.floor()).rem_euclid(2.) == 0. {
+                true => self.even,
+                false => self.odd,
+            };
+        }
+
+        if half_width >= 1. {
+            return (self.even + self.odd) * 0.5;
+        }
+
+        // integral of the 0/1 square wave, from pbrt
+        let bump_integral =
+            |x: Float| (x / 2.).floor() + 2. * (x / 2. - (x / 2.).floor() - 0.5).max(0.);
+        let s_integral =
+            (bump_integral(s + half_width) - bump_integral(s - half_width)) / (2. * half_width);
+        let t_integral =
+            (bump_integral(t + half_width) - bump_integral(t - half_width)) / (2. * half_width);
+        let odd_fraction = s_integral + t_integral - 2. * s_integral * t_integral;
+
+        self.even * (1. - odd_fraction) + self.odd * odd_fraction
+    }
+}
+
+/// Lines along u and v over a background, `frequency` cells per unit of uv
+pub struct Grid {
+    line: nalgebra::Vector3<Float>,
+    background: nalgebra::Vector3<Float>,
+    frequency: Float,
+    line_width: Float,
+}
+
+impl Grid {
+    /// # Arguments
+    /// - `line_width`: width of the lines as a fraction of a cell, (0, 1)
+    pub fn new(
+        line: nalgebra::Vector3<Float>,
+        background: nalgebra::Vector3<Float>,
+        frequency: Float,
+        line_width: Float,
+    ) -> Self {
+        debug_assert!(
+            (0.0..=1.).contains(&line_width),
+            "line width is within [0, 1]"
+        );
+        Self {
+            line,
+            background,
+            frequency,
+            line_width,
+        }
+    }
+
+    /// blends to the average coverage once cells are smaller than the footprint
+    pub fn sample(
+        &self,
+        uv: &nalgebra::Vector2<Float>,
+        footprint: Float,
+    ) -> nalgebra::Vector3<Float> {
+        let coverage = 1. - (1. - self.line_width).powi(2);
+        let average = self.line * coverage + self.background * (1. - coverage);
+
+        let cells = footprint * self.frequency;
+        let on_line = |x: Float| {
+            let f = (x * self.frequency).rem_euclid(1.);
+            f < 0.5 * self.line_width || f > 1. - 0.5 * self.line_width
+        };
+        let point = match on_line(uv.x) || on_line(uv.y) {
+            true => self.line,
+            false => self.background,
+        };
+
+        let t = cells.clamp(0., 1.);
+        point * (1. - t) + average * t
+    }
+}
+
+/// Fractal gradient noise blending between two values
+pub struct Noise {
+    low: nalgebra::Vector3<Float>,
+    high: nalgebra::Vector3<Float>,
+    frequency: Float,
+    octaves: u32,
+    seed: u32,
+}
+
+impl Noise {
+    pub fn new(
+        low: nalgebra::Vector3<Float>,
+        high: nalgebra::Vector3<Float>,
+        frequency: Float,
+        octaves: u32,
+        seed: u32,
+    ) -> Self {
+        debug_assert!(octaves > 0, "noise needs at least one octave");
+        Self {
+            low,
+            high,
+            frequency,
+            octaves,
+            seed,
+        }
+    }
+
+    /// octaves finer than the footprint are dropped
+    pub fn sample(
+        &self,
+        uv: &nalgebra::Vector2<Float>,
+        footprint: Float,
+    ) -> nalgebra::Vector3<Float> {
+        let mut value = 0.;
+        let mut amplitude = 0.5;
+        let mut frequency = self.frequency;
+        for octave in 0..self.octaves {
+            if footprint * frequency > 1. {
+                break;
+            }
+            value +=
+                amplitude * self.gradient_noise(uv * frequency, self.seed.wrapping_add(octave));
+            amplitude *= 0.5;
+            frequency *= 2.;
+        }
+
+        let t = (0.5 + value).clamp(0., 1.);
+        self.low * (1. - t) + self.high * t
+    }
+
+    /// perlin style noise, roughly [-0.5, 0.5]
+    fn gradient_noise(&self, p: nalgebra::Vector2<Float>, seed: u32) -> Float {
+        let cell = p.map(|v| v.floor());
+        let f = p - cell;
+        let fade = f.map(|t| t * t * t * (t * (t * 6. - 15.) + 10.));
+
+        let corner = |dx: Float, dy: Float| {
+            let hash = hash(cell.x as i32 + dx as i32, cell.y as i32 + dy as i32, seed);
+            let angle = hash as Float / u32::MAX as Float * 2. * crate::consts::PI;
+            angle.cos() * (f.x - dx) + angle.sin() * (f.y - dy)
+        };
+
+        let bottom = corner(0., 0.) * (1. - fade.x) + corner(1., 0.) * fade.x;
+        let top = corner(0., 1.) * (1. - fade.x) + corner(1., 1.) * fade.x;
+        (bottom * (1. - fade.y) + top * fade.y) * crate::consts::FRAC_1_SQRT_2
+    }
+}
+
+/// integer hash of a lattice point
+fn hash(x: i32, y: i32, seed: u32) -> u32 {
+    let mut h = seed ^ (x as u32).wrapping_mul(0x8da6_b343) ^ (y as u32).wrapping_mul(0xd816_3841);
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x7feb_352d);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x846c_a68b);
+    h ^ (h >> 16)
+}
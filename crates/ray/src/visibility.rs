@@ -0,0 +1,152 @@
+//! Line of sight between points and viewsheds over terrain or scenes.
+//!
+//! Occlusion is left to the caller as an any hit query over the interval of
+//! a ray, like [`crate::shapes::Traceable::occluded`], e.g.
+//! `|ray| objects.iter().any(|o| o.shape().occluded(ray))`.
+
+use rayon::prelude::*;
+
+use crate::Float;
+use crate::bvh;
+use crate::heightfield;
+use crate::light::SHADOW_RAY_SHORTENING;
+use crate::ray;
+
+/// Whether nothing blocks the straight line between two points.
+///
+/// The ray stops just short of both ends, so surfaces the points sit on do
+/// not count.
+pub fn line_of_sight(
+    from: &nalgebra::Vector3<Float>,
+    to: &nalgebra::Vector3<Float>,
+    occluded: impl Fn(&ray::Ray) -> bool,
+) -> bool {
+    let Some((direction, distance)) = nalgebra::UnitVector3::try_new_and_get(to - from, 0.) else {
+        return true;
+    };
+    let ray = ray::Ray::new(*from, direction).with_interval(
+        distance * SHADOW_RAY_SHORTENING,
+        distance * (1. - SHADOW_RAY_SHORTENING),
+    );
+    !occluded(&ray)
+}
+
+/// [`line_of_sight`] for every (from, to) pair, in parallel
+pub fn lines_of_sight(
+    pairs: &[(nalgebra::Vector3<Float>, nalgebra::Vector3<Float>)],
+    occluded: impl Fn(&ray::Ray) -> bool + Sync,
+) -> Vec<bool> {
+    pairs
+        .par_iter()
+        .map(|(from, to)| line_of_sight(from, to, &occluded))
+        .collect()
+}
+
+/// What an observer can see of every cell of a raster
+pub struct Viewshed {
+    columns: usize,
+    rows: usize,
+    visible: Vec<bool>,
+    fractions: Vec<Float>,
+}
+
+impl Viewshed {
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// whether the center of the cell is in sight
+    pub fn visible(&self, column: usize, row: usize) -> bool {
+        self.visible[row * self.columns + column]
+    }
+
+    /// share of the cell in sight, from 0 to 1
+    pub fn fraction(&self, column: usize, row: usize) -> Float {
+        self.fractions[row * self.columns + column]
+    }
+
+    /// [`Viewshed::visible`] of every cell, row by row
+    pub fn visibility_raster(&self) -> &[bool] {
+        &self.visible
+    }
+
+    /// [`Viewshed::fraction`] of every cell, row by row
+    pub fn fraction_raster(&self) -> &[Float] {
+        &self.fractions
+    }
+}
+
+/// Visibility of every cell of a raster from one point, in parallel.
+///
+/// # Arguments
+/// - `observer`: where every line of sight starts
+/// - `size`: columns and rows of the raster
+/// - `samples`: lines of sight along each side of a cell for its fraction
+/// - `target`: point seen at raster coordinates, cell (c, r) spanning c to c + 1 and r to r + 1
+/// - `occluded`: any hit query, see the module docs
+pub fn viewshed(
+    observer: &nalgebra::Vector3<Float>,
+    size: [usize; 2],
+    samples: usize,
+    target: impl Fn(Float, Float) -> nalgebra::Vector3<Float> + Sync,
+    occluded: impl Fn(&ray::Ray) -> bool + Sync,
+) -> Viewshed {
+    debug_assert!(samples > 0, "samples can not be 0");
+    let [columns, rows] = size;
+    let sees = |column: Float, row: Float| line_of_sight(observer, &target(column, row), &occluded);
+
+    let (visible, fractions) = (0..columns * rows)
+        .into_par_iter()
+        .map(|i| {
+            let (column, row) = ((i % columns) as Float, (i / columns) as Float);
+            let stratum = |j: usize| (j as Float + 0.5) / samples as Float;
+            let clear = (0..samples * samples)
+                .filter(|j| sees(column + stratum(j % samples), row + stratum(j / samples)))
+                .count();
+            (
+                sees(column + 0.5, row + 0.5),
+                clear as Float / (samples * samples) as Float,
+            )
+        })
+        .unzip();
+
+    Viewshed {
+        columns,
+        rows,
+        visible,
+        fractions,
+    }
+}
+
+/// [`viewshed`] of the posts of a heightfield, blocked by the terrain alone.
+///
+/// Each post is a cell centered on it, one spacing across.
+///
+/// # Arguments
+/// - `target_height`: above the ground of the points looked at, e.g. for vehicles
+pub fn terrain_viewshed(
+    terrain: &heightfield::Heightfield,
+    observer: &nalgebra::Vector3<Float>,
+    target_height: Float,
+    samples: usize,
+) -> Viewshed {
+    let triangles = terrain.triangles();
+    let bvh = bvh::Bvh::new(&triangles.iter().map(|t| t.bounds()).collect::<Vec<_>>());
+    let origin = terrain.point(0, 0);
+
+    viewshed(
+        observer,
+        [terrain.columns(), terrain.rows()],
+        samples,
+        |column, row| {
+            let north = origin.x + (row - 0.5) * terrain.spacing();
+            let east = origin.y + (column - 0.5) * terrain.spacing();
+            terrain.surface_at(north, east) - nalgebra::Vector3::z() * target_height
+        },
+        |ray| bvh.any_hit(ray, |i| triangles[i].ray_intersection(ray).is_some()),
+    )
+}